@@ -6,6 +6,8 @@
 //! from the other side: `malloc_conf` seeds the `opt.*` subtree before `main`
 //! runs, and `malloc_message` is where the allocator's own output goes.
 
+use core::ffi::CStr;
+
 use libc::{c_char, c_int, c_void, size_t};
 
 unsafe extern "C" {
@@ -158,3 +160,60 @@ unsafe extern "C" {
 		opts: *const c_char,
 	);
 }
+
+/// Name of the environment variable from which jemalloc reads run-time
+/// options.
+///
+/// A prefixed build derives the name from the upper-cased symbol prefix.
+#[cfg(prefixed)]
+pub const MALLOC_CONF_ENV: &CStr = c"_RJEM_MALLOC_CONF";
+
+/// Name of the environment variable from which jemalloc reads run-time
+/// options.
+#[cfg(not(prefixed))]
+pub const MALLOC_CONF_ENV: &CStr = c"MALLOC_CONF";
+
+/// Whether this build links jemalloc's symbols under the `_rjem_` prefix.
+pub const PREFIXED: bool = cfg!(prefixed);
+
+/// Exports a `malloc_conf` definition under this build's symbol name.
+///
+/// `$bytes` is a constant `&'static [u8]` ending in NUL. Jemalloc reads the
+/// exported pointer before `main`, in place of its own empty definition. The
+/// macro is selected by this crate's `prefixed` cfg, so dependents need not
+/// know which symbol regime the build chose.
+#[cfg(prefixed)]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __export_malloc_conf {
+	($bytes:expr) => {
+		#[unsafe(export_name = "_rjem_malloc_conf")]
+		static MALLOC_CONF: ::core::option::Option<&'static ::core::ffi::c_char> =
+			// SAFETY: `u8` and `c_char` have identical one-byte layouts, and the
+			// caller supplies static NUL-terminated bytes.
+			::core::option::Option::Some(unsafe {
+					&*$bytes.as_ptr().cast::<::core::ffi::c_char>()
+				});
+	};
+}
+
+/// Exports a `malloc_conf` definition under this build's symbol name.
+///
+/// `$bytes` is a constant `&'static [u8]` ending in NUL. Jemalloc reads the
+/// exported pointer before `main`, in place of its own empty definition. The
+/// macro is selected by this crate's `prefixed` cfg, so dependents need not
+/// know which symbol regime the build chose.
+#[cfg(not(prefixed))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __export_malloc_conf {
+	($bytes:expr) => {
+		#[unsafe(export_name = "malloc_conf")]
+		static MALLOC_CONF: ::core::option::Option<&'static ::core::ffi::c_char> =
+			// SAFETY: `u8` and `c_char` have identical one-byte layouts, and the
+			// caller supplies static NUL-terminated bytes.
+			::core::option::Option::Some(unsafe {
+					&*$bytes.as_ptr().cast::<::core::ffi::c_char>()
+				});
+	};
+}
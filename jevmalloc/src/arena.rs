@@ -6,13 +6,25 @@
 mod destroy_error;
 mod dss;
 mod extent_hooks;
+#[cfg(target_os = "linux")]
+mod file;
+#[cfg(target_os = "linux")]
+mod huge_pages;
+#[cfg(target_os = "linux")]
+mod mapping;
 mod name;
+#[cfg(target_os = "linux")]
+mod numa;
+mod region;
+#[cfg(target_os = "linux")]
+mod secret;
 
 #[cfg(test)]
 mod tests;
 
 use core::{
 	ffi::{CStr, c_void},
+	mem::MaybeUninit,
 	ptr::NonNull,
 	result,
 };
@@ -29,6 +41,14 @@ pub use self::{
 		RawExtentHooks,
 	},
 	name::{ARENA_NAME_LEN, ArenaName},
+	region::{RegionArena, RegionHooks},
+};
+#[cfg(target_os = "linux")]
+pub use self::{
+	file::FileHooks,
+	huge_pages::HugePageHooks,
+	numa::{NumaArenas, NumaHooks},
+	secret::SecretHooks,
 };
 use crate::{
 	ctl::{Error, Key, Result, key, raw},
@@ -42,6 +62,17 @@ use crate::{
 /// the small set of controls that support that sentinel.
 pub const ARENA_INDEX_LIMIT: usize = 4095;
 
+/// Jemalloc's documented MIB sentinel for all arenas.
+///
+/// Arena commands that accept it apply to every initialized arena, and
+/// `stats.arenas.<i>.*` reports merged statistics at this index.
+pub const ALL_ARENAS: usize = 4096;
+
+/// Jemalloc's documented `stats.arenas.<i>` sentinel for destroyed arenas.
+///
+/// Statistics at this index merge every arena destroyed so far.
+pub const DESTROYED_ARENAS: usize = 4097;
+
 /// An owning or non-owning handle to one jemalloc arena.
 ///
 /// [`Arena::create`] returns an owner that attempts `arena.<i>.destroy` when
@@ -105,6 +136,186 @@ impl Arena {
 		unsafe { Self::create_with_raw_extent_hooks(hooks) }
 	}
 
+	/// Creates an explicitly managed arena backed by hugepages.
+	///
+	/// See [`HugePageHooks`] for how extents are mapped. As with any custom
+	/// table, jemalloc's Huge Page Allocator is not enabled for this arena.
+	///
+	/// # Errors
+	///
+	/// Returns an error if jemalloc cannot create the arena or returns an
+	/// invalid index.
+	#[cfg(target_os = "linux")]
+	pub fn create_with_huge_pages(hooks: &'static HugePageHooks) -> Result<Self> {
+		// SAFETY: the table's callbacks are this crate's hugepage callbacks,
+		// which uphold every mapping contract, synchronize through atomics, and
+		// never unwind.
+		unsafe { Self::create_with_extent_hooks(hooks.extent_hooks()) }
+	}
+
+	/// Creates an explicitly managed arena that allocates only from `region`.
+	///
+	/// A private [`RegionHooks`] table is placed at the start of the region and
+	/// given the rest, so the region bounds this arena alone; once it is
+	/// exhausted, allocations from the arena fail. The returned handle borrows
+	/// the region until it is dropped, which destroys the arena first.
+	///
+	/// # Errors
+	///
+	/// Returns `EINVAL` if the region cannot hold the table, or an error if
+	/// jemalloc cannot create the arena, including when the region cannot hold
+	/// the arena's metadata. The region is released either way.
+	pub fn create_in_region(region: &mut [MaybeUninit<u8>]) -> Result<RegionArena<'_>> {
+		RegionArena::new(region)
+	}
+
+	/// Creates an explicitly managed arena that allocates from the pool of
+	/// `hooks` after adding the raw `region`, such as a reserved mapping or a
+	/// shared memory segment.
+	///
+	/// Every arena created on one table shares its pool, and destroying an
+	/// arena returns its extents to the pool rather than the region to the
+	/// caller.
+	///
+	/// # Errors
+	///
+	/// Returns `ENOSPC` if the pool cannot track another region, or an error
+	/// if jemalloc cannot create the arena. On failure the region is withdrawn
+	/// from the pool again, unless another arena on the table already holds
+	/// part of it.
+	///
+	/// # Safety
+	///
+	/// The region must be readable and writable, disjoint from every other
+	/// region of the pool, and used by nothing but the pool for the rest of the
+	/// process, or until this call fails on a table that no other arena uses.
+	pub unsafe fn create_in_raw_region(
+		hooks: &'static RegionHooks,
+		region: NonNull<[u8]>,
+	) -> Result<Self> {
+		// SAFETY: the caller hands the region to the pool.
+		unsafe { hooks.add(region.cast(), region.len()) }?;
+
+		// SAFETY: the table's callbacks are this crate's pool callbacks, which
+		// uphold every mapping contract, synchronize through the pool lock, and
+		// never unwind.
+		let created = unsafe { Self::create_with_extent_hooks(hooks.extent_hooks()) };
+		if created.is_err() {
+			hooks.remove(region.cast(), region.len());
+		}
+
+		created
+	}
+
+	/// Creates an explicitly managed arena in a new memfd of `capacity` bytes.
+	///
+	/// The memfd, named `name` for diagnostics, becomes the file of `hooks`;
+	/// see [`FileHooks`] for how extents map to it. Share it with a child
+	/// process through [`FileHooks::fd`]. The memfd is created with
+	/// `MFD_CLOEXEC`, so a child that calls `exec` only inherits it once the
+	/// descriptor's `FD_CLOEXEC` flag is cleared.
+	///
+	/// # Errors
+	///
+	/// Returns `EBUSY` if the table already has a file, a system error if the
+	/// memfd cannot be created, sized, or mapped, or an error if jemalloc
+	/// cannot create the arena.
+	#[cfg(target_os = "linux")]
+	pub fn create_in_memfd(
+		hooks: &'static FileHooks,
+		name: &CStr,
+		capacity: usize,
+	) -> Result<Self> {
+		let fd = file::memfd(name)?;
+
+		// SAFETY: the new memfd is read-write and referenced by nothing else.
+		if let Err(error) = unsafe { hooks.attach(fd, capacity) } {
+			// SAFETY: the table did not take the descriptor, which is closed once.
+			unsafe { libc::close(fd) };
+			return Err(error);
+		}
+
+		// SAFETY: the table's callbacks are this crate's file callbacks, which
+		// uphold every mapping contract, synchronize through the window pool,
+		// and never unwind.
+		unsafe { Self::create_with_extent_hooks(hooks.extent_hooks()) }
+	}
+
+	/// Creates an explicitly managed arena in the file `fd`, sized to
+	/// `capacity` bytes.
+	///
+	/// The file's existing contents are discarded: it is truncated to zero
+	/// before being sized, so every extent starts zeroed.
+	///
+	/// # Errors
+	///
+	/// Returns the same errors as [`Arena::create_in_memfd`], except that no
+	/// memfd is created.
+	///
+	/// # Safety
+	///
+	/// `fd` must be a read-write descriptor of a regular file or memfd that
+	/// `hooks` owns for the rest of the process, and whose current contents
+	/// the caller gives up. Nothing else may close or truncate it, or write to
+	/// it outside the arena's allocations.
+	#[cfg(target_os = "linux")]
+	pub unsafe fn create_in_file(
+		hooks: &'static FileHooks,
+		fd: c_int,
+		capacity: usize,
+	) -> Result<Self> {
+		// SAFETY: the caller hands the descriptor to the table.
+		unsafe { hooks.attach(fd, capacity) }?;
+
+		// SAFETY: as in `create_in_memfd`.
+		unsafe { Self::create_with_extent_hooks(hooks.extent_hooks()) }
+	}
+
+	/// Creates an explicitly managed arena whose memory is bound to one NUMA
+	/// node.
+	///
+	/// See [`NumaHooks`] for how extents are placed, and [`NumaArenas`] for
+	/// one arena per node with thread routing.
+	///
+	/// # Errors
+	///
+	/// Returns `EINVAL` if the process may not allocate on the table's node, a
+	/// system error if the allowed nodes cannot be queried, or an error if
+	/// jemalloc cannot create the arena.
+	#[cfg(target_os = "linux")]
+	pub fn create_on_node(hooks: &'static NumaHooks) -> Result<Self> {
+		numa::check_node(hooks.node())?;
+
+		// SAFETY: the table's callbacks are this crate's NUMA callbacks, which
+		// uphold every mapping contract, share no mutable state, and never
+		// unwind.
+		unsafe { Self::create_with_extent_hooks(hooks.extent_hooks()) }
+	}
+
+	/// Creates an explicitly managed arena for key material, whose memory is
+	/// locked, excluded from core dumps and forked children, and wiped before
+	/// release.
+	///
+	/// See [`SecretHooks`] for what the table guarantees and what callers must
+	/// still do themselves.
+	///
+	/// Every extent is locked, including jemalloc's metadata for the arena, so
+	/// creating the arena alone needs at least about 4 MiB of `RLIMIT_MEMLOCK`
+	/// headroom, and its first allocation needs another extent. Many systems
+	/// default to 8 MiB or less for unprivileged processes.
+	///
+	/// # Errors
+	///
+	/// Returns an error if jemalloc cannot create the arena, including when
+	/// its first extent cannot be locked within `RLIMIT_MEMLOCK`.
+	#[cfg(target_os = "linux")]
+	pub fn create_secret(hooks: &'static SecretHooks) -> Result<Self> {
+		// SAFETY: the table's callbacks are this crate's secret callbacks, which
+		// uphold every mapping contract, share no mutable state, and never
+		// unwind.
+		unsafe { Self::create_with_extent_hooks(hooks.extent_hooks()) }
+	}
+
 	/// Creates an explicitly managed arena from a raw extent hook table
 	/// pointer.
 	///
@@ -349,17 +560,7 @@ impl Arena {
 	/// # Errors
 	///
 	/// Returns an error if jemalloc rejects the arena or name query.
-	pub fn name(&self) -> Result<ArenaName> {
-		let key = self.select(key::arena_name()?);
-		let mut name = ArenaName::default();
-		let mut name_ptr = name.as_mut_ptr();
-
-		// SAFETY: `arena.<i>.name` expects an initialized `char *` output slot
-		// pointing to a writable 32-byte buffer. `ArenaName` supplies that buffer.
-		unsafe { raw::get_in_place(&key, &mut name_ptr) }?;
-
-		Ok(name)
-	}
+	pub fn name(&self) -> Result<ArenaName> { read_name(self.index) }
 
 	/// Sets this arena's descriptive name from a terminated byte string.
 	///
@@ -672,6 +873,24 @@ impl Drop for Arena {
 	}
 }
 
+/// Copies the name of the arena at `index`.
+///
+/// Jemalloc serializes name reads with arena destruction and reports `EFAULT`
+/// for an index without a live arena, so no handle is required.
+pub(crate) fn read_name(index: usize) -> Result<ArenaName> {
+	let mut key = key::arena_name()?;
+	key[1] = index;
+
+	let mut name = ArenaName::default();
+	let mut name_ptr = name.as_mut_ptr();
+
+	// SAFETY: `arena.<i>.name` expects an initialized `char *` output slot
+	// pointing to a writable 32-byte buffer. `ArenaName` supplies that buffer.
+	unsafe { raw::get_in_place(&key, &mut name_ptr) }?;
+
+	Ok(name)
+}
+
 /// Converts a validated ordinary arena index to jemalloc's C representation.
 pub(super) fn validated_index(index: usize) -> Result<c_uint> {
 	if index >= ARENA_INDEX_LIMIT {
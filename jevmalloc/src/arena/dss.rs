@@ -1,6 +1,6 @@
 //! Typed dynamic storage allocation precedence.
 
-use core::ffi::CStr;
+use core::{ffi::CStr, fmt, str::FromStr};
 
 use libc::c_char;
 
@@ -35,6 +35,15 @@ impl Dss {
 		}
 	}
 
+	/// Returns the precedence string accepted by jemalloc.
+	const fn as_str(self) -> &'static str {
+		match self {
+			| Self::Disabled => "disabled",
+			| Self::Primary => "primary",
+			| Self::Secondary => "secondary",
+		}
+	}
+
 	/// Converts one of jemalloc's static precedence strings.
 	///
 	/// # Errors
@@ -51,12 +60,27 @@ impl Dss {
 		}
 
 		// SAFETY: the caller guarantees a readable, terminated C string.
-		let value = unsafe { CStr::from_ptr(ptr) }.to_bytes();
+		let value = unsafe { CStr::from_ptr(ptr) };
+		value
+			.to_str()
+			.map_err(|_| Error::invalid_argument())?
+			.parse()
+	}
+}
+
+impl FromStr for Dss {
+	type Err = Error;
+
+	fn from_str(value: &str) -> Result<Self> {
 		match value {
-			| b"disabled" => Ok(Self::Disabled),
-			| b"primary" => Ok(Self::Primary),
-			| b"secondary" => Ok(Self::Secondary),
+			| "disabled" => Ok(Self::Disabled),
+			| "primary" => Ok(Self::Primary),
+			| "secondary" => Ok(Self::Secondary),
 			| _ => Err(Error::invalid_argument()),
 		}
 	}
 }
+
+impl fmt::Display for Dss {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(self.as_str()) }
+}
@@ -174,6 +174,14 @@ pub enum ExtentHookResult {
 	Failure,
 }
 
+impl ExtentHookResult {
+	/// Reports [`Success`](Self::Success) for `true` and
+	/// [`Failure`](Self::Failure) otherwise.
+	pub(super) const fn from_success(success: bool) -> Self {
+		if success { Self::Success } else { Self::Failure }
+	}
+}
+
 /// Rust allocation callback with access to table-local state.
 ///
 /// The callback may run concurrently and must satisfy the raw allocation-hook
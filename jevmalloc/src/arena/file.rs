@@ -0,0 +1,360 @@
+//! A ready-made extent hook table that maps an arena from a file.
+
+use core::{
+	ffi::CStr,
+	fmt,
+	ptr::{NonNull, null_mut},
+	sync::atomic::{AtomicI32, AtomicUsize, Ordering},
+};
+
+use libc::{c_int, off_t};
+
+use super::{
+	Extent, ExtentAlloc, ExtentAllocation, ExtentCallbacks, ExtentHookResult, ExtentHooks,
+	ExtentMerge, ExtentRange, ExtentSplit, mapping, region::Pool,
+};
+use crate::ctl::{Error, Result};
+
+/// An extent hook table whose arenas live in a shared file mapping.
+///
+/// The table maps a memfd or caller file once, as a `MAP_SHARED` window of a
+/// fixed capacity, and carves extents from that window. An extent's file
+/// offset is therefore its distance from the window's start, available from
+/// [`FileHooks::offset_of`], so a child process that maps the same file sees
+/// the same data at the same offsets. Pages of a disk file are written back
+/// and reclaimed by the kernel under memory pressure instead of consuming swap.
+///
+/// Attaching a file discards its contents, truncating it to zero before sizing
+/// it, so the window starts as one hole. The hooks then translate jemalloc's
+/// page operations into file operations:
+///
+/// - commit allocates backing with `fallocate`, so exhausting the file system
+///   fails the commit instead of raising `SIGBUS` on a later write;
+/// - decommit, forced purge, deallocation, and destruction release backing with
+///   `fallocate(FALLOC_FL_PUNCH_HOLE)`, which also zeroes the range;
+/// - lazy purge advises `MADV_COLD`, letting the kernel reclaim the pages first
+///   while keeping their contents.
+///
+/// Free memory in the window is always a hole, so every new extent is zeroed.
+/// Once the window is exhausted, extent allocation fails.
+///
+/// Sharing is at the page level only. A forked child inherits the window as
+/// shared memory but a private copy of the arena's bookkeeping, so it must
+/// not allocate from or free into the arena.
+///
+/// ```no_run
+/// use jevmalloc::{Arena, FileHooks};
+///
+/// static SHARED: FileHooks = FileHooks::new();
+///
+/// let arena = Arena::create_in_memfd(&SHARED, c"index", 1 << 30)?;
+/// let fd = SHARED.fd().expect("the table is attached");
+/// # Ok::<(), jevmalloc::ctl::Error>(())
+/// ```
+pub struct FileHooks {
+	/// The installed table.
+	hooks: ExtentHooks<Backing>,
+}
+
+/// Callback state of [`FileHooks`].
+pub(super) struct Backing {
+	/// Free ranges of the window.
+	pool: Pool,
+
+	/// The mapped file descriptor, or `-1` before attachment.
+	fd: AtomicI32,
+
+	/// First address of the window, zero before attachment, or one while a
+	/// file is being attached.
+	base: AtomicUsize,
+}
+
+impl FileHooks {
+	/// Constructs a table with no file attached.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			hooks: ExtentHooks::new(
+				Backing {
+					pool: Pool::new(),
+					fd: AtomicI32::new(-1),
+					base: AtomicUsize::new(0),
+				},
+				ExtentCallbacks {
+					alloc: Some(alloc),
+					dalloc: Some(dalloc),
+					destroy: Some(destroy),
+					commit: Some(commit),
+					decommit: Some(punch),
+					purge_lazy: Some(purge_lazy),
+					purge_forced: Some(punch),
+					split: Some(split),
+					merge: Some(merge),
+				},
+			),
+		}
+	}
+
+	/// Returns the attached file descriptor.
+	///
+	/// The descriptor belongs to the table and must not be closed, truncated,
+	/// or written outside the arena's allocations.
+	#[must_use]
+	pub fn fd(&self) -> Option<c_int> {
+		let fd = self.hooks.state().fd.load(Ordering::Acquire);
+
+		(fd >= 0).then_some(fd)
+	}
+
+	/// Returns the size of the mapped window.
+	#[must_use]
+	pub fn capacity(&self) -> usize { self.hooks.state().pool.capacity() }
+
+	/// Returns the number of window bytes not currently held by jemalloc.
+	#[must_use]
+	pub fn available(&self) -> usize { self.hooks.state().pool.available() }
+
+	/// Returns the file offset of `ptr`, or `None` if it lies outside the
+	/// window.
+	#[must_use]
+	pub fn offset_of(&self, ptr: *const u8) -> Option<usize> {
+		let backing = self.hooks.state();
+		let base = backing.base.load(Ordering::Acquire);
+		let offset = ptr.addr().checked_sub(base)?;
+
+		(base != 0 && offset < backing.pool.capacity()).then_some(offset)
+	}
+
+	/// Empties `fd`, sizes it to `capacity` bytes, and maps it as the table's
+	/// window.
+	///
+	/// # Errors
+	///
+	/// Returns `EBUSY` if the table already has a file, `EINVAL` for a zero or
+	/// unrepresentable capacity, or the system error of a failed `ftruncate`
+	/// or `mmap`.
+	///
+	/// # Safety
+	///
+	/// `fd` must be a read-write descriptor of a regular file or memfd that
+	/// the table owns for the rest of the process, contents included. Nothing
+	/// else may truncate the file or write to it outside the arena's
+	/// allocations.
+	pub(super) unsafe fn attach(&self, fd: c_int, capacity: usize) -> Result {
+		let backing = self.hooks.state();
+		let length = off_t::try_from(capacity).map_err(|_| Error::invalid_argument())?;
+		if capacity == 0 {
+			return Err(Error::invalid_argument());
+		}
+		if backing
+			.base
+			.compare_exchange(0, 1, Ordering::Acquire, Ordering::Relaxed)
+			.is_err()
+		{
+			return Err(Error::busy());
+		}
+
+		let window = match map_window(fd, capacity, length) {
+			| Ok(window) => window,
+			| Err(error) => {
+				backing.base.store(0, Ordering::Release);
+				return Err(error);
+			},
+		};
+
+		// SAFETY: the new shared mapping is owned by the table alone, so the
+		// empty pool accepts it as one span.
+		unsafe { backing.pool.add(window, capacity) }?;
+		backing
+			.base
+			.store(window.as_ptr().addr(), Ordering::Release);
+		backing.fd.store(fd, Ordering::Release);
+
+		Ok(())
+	}
+
+	/// Returns the table to install.
+	pub(super) const fn extent_hooks(&self) -> &ExtentHooks<Backing> { &self.hooks }
+}
+
+impl Default for FileHooks {
+	fn default() -> Self { Self::new() }
+}
+
+impl fmt::Debug for FileHooks {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("FileHooks")
+			.field("fd", &self.fd())
+			.field("capacity", &self.capacity())
+			.field("available", &self.available())
+			.finish()
+	}
+}
+
+impl Backing {
+	/// Applies `fallocate` `mode` to `length` window bytes at `start`.
+	fn fallocate(&self, mode: c_int, start: NonNull<u8>, length: usize) -> bool {
+		let fd = self.fd.load(Ordering::Acquire);
+		let base = self.base.load(Ordering::Acquire);
+		let Some(offset) = start.as_ptr().addr().checked_sub(base) else {
+			return false;
+		};
+		let (Ok(offset), Ok(length)) = (off_t::try_from(offset), off_t::try_from(length)) else {
+			return false;
+		};
+
+		// SAFETY: the range lies in the table's file, and the caller permits
+		// the change to its contents.
+		unsafe { libc::fallocate(fd, mode, offset, length) == 0 }
+	}
+
+	/// Releases the backing of `length` bytes at `start`, zeroing them.
+	fn punch(&self, start: NonNull<u8>, length: usize) -> bool {
+		self.fallocate(libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE, start, length)
+	}
+}
+
+/// Creates and names a memfd for
+/// [`Arena::create_in_memfd`](super::Arena::create_in_memfd).
+pub(super) fn memfd(name: &CStr) -> Result<c_int> {
+	// SAFETY: `name` is a valid C string and the flags request a new
+	// descriptor owned by the caller.
+	let fd = unsafe { libc::memfd_create(name.as_ptr(), libc::MFD_CLOEXEC) };
+
+	if fd < 0 { Err(Error::last_os_error()) } else { Ok(fd) }
+}
+
+/// Empties and sizes the file, then maps its first `capacity` bytes.
+///
+/// Truncating to zero first drops any existing data, so the window is a hole
+/// and every extent carved from it is zeroed.
+fn map_window(fd: c_int, capacity: usize, length: off_t) -> Result<NonNull<u8>> {
+	for length in [0, length] {
+		// SAFETY: the caller of `attach` owns the file, its size, and its
+		// contents.
+		if unsafe { libc::ftruncate(fd, length) } != 0 {
+			return Err(Error::last_os_error());
+		}
+	}
+
+	// SAFETY: a new shared mapping at a kernel-chosen address cannot alias
+	// any existing Rust allocation.
+	let window = unsafe {
+		libc::mmap(
+			null_mut(),
+			capacity,
+			libc::PROT_READ | libc::PROT_WRITE,
+			libc::MAP_SHARED | libc::MAP_NORESERVE,
+			fd,
+			0,
+		)
+	};
+	if window == libc::MAP_FAILED {
+		return Err(Error::last_os_error());
+	}
+
+	NonNull::new(window.cast()).ok_or_else(Error::bad_address)
+}
+
+/// Carves a zeroed extent from the window, allocating its backing if jemalloc
+/// asks for committed memory.
+///
+/// # Safety
+///
+/// Jemalloc calls this with a valid request.
+unsafe fn alloc(backing: &Backing, request: ExtentAlloc) -> Option<ExtentAllocation> {
+	let address = backing
+		.pool
+		.take(request.address, request.size, request.alignment)?;
+
+	if request.committed && !backing.fallocate(0, address, request.size) {
+		backing.pool.give(address, request.size);
+		return None;
+	}
+
+	Some(ExtentAllocation {
+		address,
+		zeroed: true,
+		committed: request.committed,
+	})
+}
+
+/// Releases an extent's backing and returns it to the window.
+///
+/// # Safety
+///
+/// Jemalloc passes an extent carved from this window and gives up every use
+/// of it.
+unsafe fn dalloc(backing: &Backing, extent: Extent) -> ExtentHookResult {
+	if backing.punch(extent.address, extent.size)
+		&& backing.pool.give(extent.address, extent.size)
+	{
+		ExtentHookResult::Success
+	} else {
+		ExtentHookResult::Failure
+	}
+}
+
+/// Releases an extent's backing when its arena is destroyed.
+///
+/// The range returns to the window only once it is a hole again.
+///
+/// # Safety
+///
+/// Jemalloc passes an extent carved from this window and gives up every use
+/// of it.
+unsafe fn destroy(backing: &Backing, extent: Extent) {
+	if backing.punch(extent.address, extent.size) {
+		backing.pool.give(extent.address, extent.size);
+	}
+}
+
+/// Allocates file backing for a range.
+///
+/// # Safety
+///
+/// Jemalloc calls this with an extent carved from this window.
+unsafe fn commit(backing: &Backing, range: ExtentRange) -> ExtentHookResult {
+	// SAFETY: the range lies within the extent.
+	let start = unsafe { range.address.byte_add(range.offset) };
+
+	ExtentHookResult::from_success(backing.fallocate(0, start, range.length))
+}
+
+/// Releases file backing for a range, for decommit and forced purge alike.
+///
+/// # Safety
+///
+/// Jemalloc passes a range of an extent carved from this window whose
+/// contents it no longer needs.
+unsafe fn punch(backing: &Backing, range: ExtentRange) -> ExtentHookResult {
+	// SAFETY: the range lies within the extent.
+	let start = unsafe { range.address.byte_add(range.offset) };
+
+	ExtentHookResult::from_success(backing.punch(start, range.length))
+}
+
+/// Marks a range's pages as the first to reclaim.
+///
+/// # Safety
+///
+/// Jemalloc passes a range of an extent carved from this window whose
+/// contents it no longer needs.
+unsafe fn purge_lazy(_: &Backing, range: ExtentRange) -> ExtentHookResult {
+	// SAFETY: the advice keeps the contents of the table's own mapping.
+	unsafe { mapping::advise_range(range, libc::MADV_COLD) }
+}
+
+/// Accepts any split within the window.
+///
+/// # Safety
+///
+/// Jemalloc calls this with an extent carved from this window.
+unsafe fn split(_: &Backing, _: ExtentSplit) -> ExtentHookResult { ExtentHookResult::Success }
+
+/// Accepts any merge of adjacent extents within the window.
+///
+/// # Safety
+///
+/// Jemalloc calls this with two adjacent extents carved from this window.
+unsafe fn merge(_: &Backing, _: ExtentMerge) -> ExtentHookResult { ExtentHookResult::Success }
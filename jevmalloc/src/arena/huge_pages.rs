@@ -0,0 +1,392 @@
+//! A ready-made extent hook table that backs an arena with hugepages.
+
+use core::{
+	fmt,
+	ptr::{self, NonNull},
+	sync::atomic::{AtomicUsize, Ordering},
+};
+
+use super::{
+	Extent, ExtentAlloc, ExtentAllocation, ExtentCallbacks, ExtentHookResult, ExtentHooks,
+	ExtentMerge, ExtentRange, ExtentSplit, mapping,
+};
+
+/// The number of explicit hugepage mappings that can be live at once.
+///
+/// Allocations beyond this fall back to transparent hugepages.
+const EXPLICIT_REGIONS: usize = 64;
+
+/// An extent hook table that backs an arena with hugepages.
+///
+/// Each extent is aligned to the hugepage size. Requests whose size is a
+/// multiple of it are first mapped with `MAP_HUGETLB`, drawing on the
+/// system's reserved hugepage pool. When that pool is empty or the size does
+/// not fit, the extent is an ordinary anonymous mapping advised with
+/// `MADV_HUGEPAGE`, so transparent hugepages can back each aligned
+/// stretch.
+///
+/// The table honors the constraints of explicit hugepages: they are never
+/// decommitted, purged only in whole hugepages, and never merged with
+/// transparent mappings. Jemalloc may split them anywhere, since a split is
+/// only bookkeeping; each `MAP_HUGETLB` mapping is unmapped as a whole once
+/// every byte of it has been deallocated or destroyed, returning its pages to
+/// the system pool. Deallocation of transparent extents opts out, so jemalloc
+/// retains and reuses them until the arena is destroyed.
+///
+/// Install the table with
+/// [`Arena::create_with_huge_pages`](super::Arena::create_with_huge_pages):
+///
+/// ```no_run
+/// use jevmalloc::{Arena, HugePageHooks};
+///
+/// static HUGE: HugePageHooks = HugePageHooks::new();
+///
+/// let arena = Arena::create_with_huge_pages(&HUGE)?;
+/// # Ok::<(), jevmalloc::ctl::Error>(())
+/// ```
+pub struct HugePageHooks {
+	/// The installed table.
+	hooks: ExtentHooks<HugePages>,
+}
+
+/// Callback state of [`HugePageHooks`].
+pub(super) struct HugePages {
+	/// The hugepage size, a power of two.
+	page_size: usize,
+
+	/// Live `MAP_HUGETLB` mappings, used to classify extents.
+	regions: [Region; EXPLICIT_REGIONS],
+
+	/// Bytes currently mapped with `MAP_HUGETLB`.
+	explicit: AtomicUsize,
+
+	/// Bytes currently mapped with transparent hugepage advice.
+	transparent: AtomicUsize,
+}
+
+/// One `MAP_HUGETLB` mapping.
+///
+/// A zero `start` marks a free slot. A claimed slot publishes `end` last, and
+/// is unmapped and released once jemalloc has relinquished all of its bytes.
+struct Region {
+	/// First address of the mapping, or zero if the slot is free.
+	start: AtomicUsize,
+
+	/// One past the last address, or zero while the slot is being filled.
+	end: AtomicUsize,
+
+	/// Bytes of the mapping jemalloc has not yet relinquished.
+	mapped: AtomicUsize,
+}
+
+impl HugePageHooks {
+	/// The default hugepage size, 2 MiB.
+	pub const DEFAULT_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+	/// Constructs a table for the default 2 MiB hugepages.
+	#[must_use]
+	pub const fn new() -> Self { Self::with_page_size(Self::DEFAULT_PAGE_SIZE) }
+
+	/// Constructs a table for hugepages of `page_size` bytes, such as 1 GiB.
+	///
+	/// Explicit mappings request that size from the kernel, which must have a
+	/// pool of it configured; otherwise they fall back to transparent
+	/// hugepages.
+	///
+	/// # Panics
+	///
+	/// Panics if `page_size` is not a power of two of at least 64 KiB.
+	#[must_use]
+	pub const fn with_page_size(page_size: usize) -> Self {
+		assert!(
+			page_size.is_power_of_two() && page_size >= 64 * 1024,
+			"hugepage size must be a power of two of at least 64 KiB"
+		);
+
+		Self {
+			hooks: ExtentHooks::new(
+				HugePages {
+					page_size,
+					regions: [const { Region::new() }; EXPLICIT_REGIONS],
+					explicit: AtomicUsize::new(0),
+					transparent: AtomicUsize::new(0),
+				},
+				ExtentCallbacks {
+					alloc: Some(alloc),
+					dalloc: Some(dalloc),
+					destroy: Some(destroy),
+					commit: Some(commit),
+					purge_lazy: Some(purge_lazy),
+					purge_forced: Some(purge_forced),
+					split: Some(split),
+					merge: Some(merge),
+					..ExtentCallbacks::EMPTY
+				},
+			),
+		}
+	}
+
+	/// Returns the hugepage size.
+	#[must_use]
+	pub const fn page_size(&self) -> usize { self.hooks.state().page_size }
+
+	/// Returns the number of bytes currently mapped from the explicit
+	/// `MAP_HUGETLB` pool.
+	#[must_use]
+	pub fn explicit_bytes(&self) -> usize {
+		self.hooks
+			.state()
+			.explicit
+			.load(Ordering::Relaxed)
+	}
+
+	/// Returns the number of bytes currently mapped with transparent hugepage
+	/// advice.
+	#[must_use]
+	pub fn transparent_bytes(&self) -> usize {
+		self.hooks
+			.state()
+			.transparent
+			.load(Ordering::Relaxed)
+	}
+
+	/// Returns the table to install.
+	pub(super) const fn extent_hooks(&self) -> &ExtentHooks<HugePages> { &self.hooks }
+}
+
+impl Default for HugePageHooks {
+	fn default() -> Self { Self::new() }
+}
+
+impl fmt::Debug for HugePageHooks {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("HugePageHooks")
+			.field("page_size", &self.page_size())
+			.field("explicit_bytes", &self.explicit_bytes())
+			.field("transparent_bytes", &self.transparent_bytes())
+			.finish()
+	}
+}
+
+impl HugePages {
+	/// Reports whether `address` lies in a live `MAP_HUGETLB` mapping.
+	fn is_explicit(&self, address: NonNull<u8>) -> bool {
+		let address = address.as_ptr().addr();
+
+		self.regions.iter().any(|region| {
+			let end = region.end.load(Ordering::Acquire);
+			end > address && region.start.load(Ordering::Relaxed) <= address
+		})
+	}
+
+	/// Maps `size` bytes from the hugepage pool, if a region slot is free and
+	/// the pool can supply them.
+	fn map_explicit(&self, size: usize, alignment: usize) -> Option<NonNull<u8>> {
+		let region = self.regions.iter().find(|region| {
+			region
+				.start
+				.compare_exchange(0, 1, Ordering::Relaxed, Ordering::Relaxed)
+				.is_ok()
+		})?;
+
+		// Without `MAP_NORESERVE` the kernel reserves the pages up front, so an
+		// empty pool fails here instead of raising `SIGBUS` on first touch.
+		let shift = self.page_size.trailing_zeros();
+		let flags = libc::MAP_HUGETLB | (shift.cast_signed() << libc::MAP_HUGE_SHIFT);
+		let Some(address) = mapping::map_aligned(size, alignment, self.page_size, flags) else {
+			region.start.store(0, Ordering::Relaxed);
+			return None;
+		};
+
+		let start = address.as_ptr().expose_provenance();
+		region.start.store(start, Ordering::Relaxed);
+		region.mapped.store(size, Ordering::Relaxed);
+		region.end.store(start + size, Ordering::Release);
+		self.explicit.fetch_add(size, Ordering::Relaxed);
+
+		Some(address)
+	}
+
+	/// Accounts for `size` relinquished explicit bytes at `address`, unmapping
+	/// each mapping that has none left and releasing its slot.
+	///
+	/// # Safety
+	///
+	/// Jemalloc must have given up every use of the range.
+	unsafe fn release(&self, address: NonNull<u8>, size: usize) {
+		let first = address.as_ptr().addr();
+		let last = first + size;
+
+		for region in &self.regions {
+			let end = region.end.load(Ordering::Acquire);
+			let start = region.start.load(Ordering::Relaxed);
+			let overlap = end.min(last).saturating_sub(start.max(first));
+			if overlap == 0 || region.mapped.fetch_sub(overlap, Ordering::AcqRel) != overlap {
+				continue;
+			}
+
+			if let Some(mapping) = NonNull::new(ptr::with_exposed_provenance_mut(start)) {
+				// SAFETY: jemalloc has relinquished every byte of the mapping,
+				// which is unmapped whole as `MAP_HUGETLB` requires.
+				unsafe { mapping::unmap(mapping, end - start) };
+			}
+			self.explicit
+				.fetch_sub(end - start, Ordering::Relaxed);
+			region.end.store(0, Ordering::Relaxed);
+			region.start.store(0, Ordering::Release);
+		}
+	}
+}
+
+impl Region {
+	/// Constructs a free slot.
+	const fn new() -> Self {
+		Self {
+			start: AtomicUsize::new(0),
+			end: AtomicUsize::new(0),
+			mapped: AtomicUsize::new(0),
+		}
+	}
+}
+
+/// Maps a hugepage-aligned extent, preferring the explicit pool.
+///
+/// # Safety
+///
+/// Jemalloc calls this with a valid request; the function itself has no
+/// preconditions.
+unsafe fn alloc(state: &HugePages, request: ExtentAlloc) -> Option<ExtentAllocation> {
+	if request.address.is_some() || !request.alignment.is_power_of_two() {
+		return None;
+	}
+
+	let alignment = request.alignment.max(state.page_size);
+	let address = if request.size.is_multiple_of(state.page_size) {
+		state.map_explicit(request.size, alignment)
+	} else {
+		None
+	};
+
+	let address = match address {
+		| Some(address) => address,
+		| None => {
+			let address = mapping::map_aligned(request.size, alignment, mapping::os_page()?, 0)?;
+
+			// SAFETY: the new mapping is owned by this table. The advice is a
+			// hint whose failure leaves ordinary pages in place.
+			unsafe { mapping::advise(address, request.size, libc::MADV_HUGEPAGE) };
+			state
+				.transparent
+				.fetch_add(request.size, Ordering::Relaxed);
+			address
+		},
+	};
+
+	Some(ExtentAllocation { address, zeroed: true, committed: true })
+}
+
+/// Returns an explicit extent's bytes toward unmapping its mapping; transparent
+/// extents opt out and stay with jemalloc.
+///
+/// # Safety
+///
+/// Jemalloc passes an extent this table mapped and gives up every use of it.
+unsafe fn dalloc(state: &HugePages, extent: Extent) -> ExtentHookResult {
+	if !state.is_explicit(extent.address) {
+		return ExtentHookResult::Failure;
+	}
+
+	// SAFETY: jemalloc relinquishes the extent.
+	unsafe { state.release(extent.address, extent.size) };
+
+	ExtentHookResult::Success
+}
+
+/// Unmaps an extent when its arena is destroyed, deferring explicit extents
+/// until their whole mapping is relinquished.
+///
+/// # Safety
+///
+/// Jemalloc passes an extent this table mapped and gives up every use of it.
+unsafe fn destroy(state: &HugePages, extent: Extent) {
+	if state.is_explicit(extent.address) {
+		// SAFETY: jemalloc relinquishes the extent.
+		unsafe { state.release(extent.address, extent.size) };
+		return;
+	}
+
+	state
+		.transparent
+		.fetch_sub(extent.size, Ordering::Relaxed);
+
+	// SAFETY: jemalloc relinquishes the extent, and ordinary mappings can be
+	// unmapped piecewise.
+	unsafe { mapping::unmap(extent.address, extent.size) };
+}
+
+/// Accepts a commit request; this table never decommits memory.
+///
+/// # Safety
+///
+/// Jemalloc calls this with an extent this table mapped.
+unsafe fn commit(_: &HugePages, _: ExtentRange) -> ExtentHookResult { ExtentHookResult::Success }
+
+/// Frees transparent pages lazily; explicit hugepages opt out.
+///
+/// # Safety
+///
+/// Jemalloc passes a range of an extent this table mapped whose contents it
+/// no longer needs.
+unsafe fn purge_lazy(state: &HugePages, range: ExtentRange) -> ExtentHookResult {
+	if state.is_explicit(range.address) {
+		return ExtentHookResult::Failure;
+	}
+
+	// SAFETY: jemalloc discards the range, which lies within the mapping.
+	unsafe { mapping::advise_range(range, libc::MADV_FREE) }
+}
+
+/// Discards pages immediately, in whole hugepages for explicit mappings.
+///
+/// # Safety
+///
+/// Jemalloc passes a range of an extent this table mapped whose contents it
+/// no longer needs.
+unsafe fn purge_forced(state: &HugePages, range: ExtentRange) -> ExtentHookResult {
+	// SAFETY: jemalloc discards the range, which lies within the mapping.
+	let start = unsafe { range.address.byte_add(range.offset) };
+	if state.is_explicit(range.address)
+		&& (!start
+			.as_ptr()
+			.addr()
+			.is_multiple_of(state.page_size)
+			|| !range.length.is_multiple_of(state.page_size))
+	{
+		return ExtentHookResult::Failure;
+	}
+
+	// SAFETY: jemalloc discards the range, and explicit mappings only see
+	// whole hugepages.
+	unsafe { mapping::advise_range(range, libc::MADV_DONTNEED) }
+}
+
+/// Accepts any split; explicit mappings are only unmapped whole, so a split
+/// is bookkeeping alone.
+///
+/// # Safety
+///
+/// Jemalloc calls this with an extent this table mapped.
+unsafe fn split(_: &HugePages, _: ExtentSplit) -> ExtentHookResult { ExtentHookResult::Success }
+
+/// Merges adjacent extents of the same kind.
+///
+/// # Safety
+///
+/// Jemalloc calls this with two adjacent extents this table mapped.
+unsafe fn merge(state: &HugePages, merge: ExtentMerge) -> ExtentHookResult {
+	if state.is_explicit(merge.first_address) == state.is_explicit(merge.second_address) {
+		ExtentHookResult::Success
+	} else {
+		ExtentHookResult::Failure
+	}
+}
@@ -0,0 +1,106 @@
+//! Anonymous memory mappings shared by the built-in extent hook tables.
+
+use core::ptr::{NonNull, null_mut};
+
+use libc::c_int;
+
+use super::{ExtentHookResult, ExtentRange};
+
+/// Returns the operating system's base page size.
+pub(super) fn os_page() -> Option<usize> {
+	// SAFETY: `sysconf` reads a process-wide constant and has no preconditions.
+	let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+
+	usize::try_from(page)
+		.ok()
+		.filter(|page| page.is_power_of_two())
+}
+
+/// Maps `size` private read-write bytes at an `alignment` boundary.
+///
+/// `flags` is added to `MAP_PRIVATE | MAP_ANONYMOUS`. `granule` is the
+/// alignment `mmap` already guarantees for these flags; the mapping is
+/// over-allocated by `alignment - granule` bytes and the excess is unmapped in
+/// multiples of `granule`. Both `size` and `alignment` must be multiples of
+/// `granule`, and `alignment` a power of two no smaller than it.
+pub(super) fn map_aligned(
+	size: usize,
+	alignment: usize,
+	granule: usize,
+	flags: c_int,
+) -> Option<NonNull<u8>> {
+	let span = size.checked_add(alignment.checked_sub(granule)?)?;
+
+	// SAFETY: a new anonymous private mapping at a kernel-chosen address
+	// cannot alias any existing Rust allocation.
+	let base = unsafe {
+		libc::mmap(
+			null_mut(),
+			span,
+			libc::PROT_READ | libc::PROT_WRITE,
+			libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | flags,
+			-1,
+			0,
+		)
+	};
+	if base == libc::MAP_FAILED {
+		return None;
+	}
+
+	let base = NonNull::new(base.cast::<u8>())?;
+	let lead = base.as_ptr().addr().next_multiple_of(alignment) - base.as_ptr().addr();
+	let trail = span - lead - size;
+
+	let start = base.as_ptr().wrapping_add(lead);
+	let end = start.wrapping_add(size);
+
+	if lead > 0 {
+		// SAFETY: the head and tail are disjoint, granule-aligned pieces of the
+		// mapping created above and are not used after this call.
+		unsafe { unmap(base, lead) };
+	}
+	if trail > 0 {
+		// SAFETY: as above, for the tail past the aligned range.
+		unsafe { unmap(NonNull::new(end)?, trail) };
+	}
+
+	NonNull::new(start)
+}
+
+/// Unmaps `size` bytes at `address`, reporting whether the kernel accepted
+/// the request.
+///
+/// # Safety
+///
+/// The range must be a page-aligned part of a mapping owned by the caller and
+/// must not be accessed afterwards.
+pub(super) unsafe fn unmap(address: NonNull<u8>, size: usize) -> bool {
+	// SAFETY: the caller owns the range and gives it up.
+	unsafe { libc::munmap(address.as_ptr().cast(), size) == 0 }
+}
+
+/// Applies `madvise` `advice` to `length` bytes at `address`, reporting whether
+/// the kernel accepted it.
+///
+/// # Safety
+///
+/// The range must be a page-aligned part of a mapping owned by the caller,
+/// and the advice must not discard contents the caller still needs.
+pub(super) unsafe fn advise(address: NonNull<u8>, length: usize, advice: c_int) -> bool {
+	// SAFETY: the caller owns the range and permits the advice.
+	unsafe { libc::madvise(address.as_ptr().cast(), length, advice) == 0 }
+}
+
+/// Applies `madvise` `advice` to a range of an extent, as a hook result.
+///
+/// # Safety
+///
+/// The extent must be part of a mapping owned by the calling table, and the
+/// advice must not discard contents jemalloc still needs.
+pub(super) unsafe fn advise_range(range: ExtentRange, advice: c_int) -> ExtentHookResult {
+	// SAFETY: jemalloc passes a range within the extent.
+	let start = unsafe { range.address.byte_add(range.offset) };
+
+	// SAFETY: the caller owns the range and permits the advice.
+	ExtentHookResult::from_success(unsafe { advise(start, range.length, advice) })
+}
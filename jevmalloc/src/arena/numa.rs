@@ -0,0 +1,377 @@
+//! Extent hooks that bind an arena's memory to one NUMA node.
+
+use core::{
+	fmt,
+	ptr::{self, NonNull, null_mut},
+	sync::atomic::{AtomicUsize, Ordering},
+};
+
+use libc::{c_long, c_uint, c_ulong};
+
+use super::{
+	Arena, Extent, ExtentAlloc, ExtentAllocation, ExtentCallbacks, ExtentHookResult, ExtentHooks,
+	ExtentMerge, ExtentRange, ExtentSplit, mapping,
+};
+use crate::ctl::{Error, Result};
+
+/// Words in the node masks passed to the kernel.
+const MASK_WORDS: usize = 16;
+
+/// Nodes addressable by a node mask.
+const MASK_NODES: usize = MASK_WORDS * c_ulong::BITS as usize;
+
+/// Nodes served by [`NumaArenas`].
+const NUMA_NODES: usize = 64;
+
+/// `get_mempolicy` flag that reports the allowed nodes.
+const MPOL_F_MEMS_ALLOWED: c_long = 1 << 2;
+
+/// `mbind` flag that fails if existing pages violate the policy.
+const MPOL_MF_STRICT: c_long = 1;
+
+/// A node mask in the kernel's representation.
+type NodeMask = [c_ulong; MASK_WORDS];
+
+/// An extent hook table that binds an arena's memory to one NUMA node.
+///
+/// Each extent is a fresh anonymous mapping bound with
+/// `mbind(MPOL_BIND)` before any page is touched, so every page of the arena,
+/// including its metadata, is placed on the node or not at all. Deallocation
+/// and destruction unmap extents, and purging advises the kernel as
+/// jemalloc's default hooks do; the policy stays with the remaining pages.
+///
+/// ```no_run
+/// use jevmalloc::{Arena, NumaHooks};
+///
+/// static NODE_0: NumaHooks = NumaHooks::new(0);
+///
+/// let arena = Arena::create_on_node(&NODE_0)?;
+/// # Ok::<(), jevmalloc::ctl::Error>(())
+/// ```
+pub struct NumaHooks {
+	/// The installed table.
+	hooks: ExtentHooks<Node>,
+}
+
+/// Callback state of [`NumaHooks`].
+pub(super) struct Node {
+	/// The bound node, or `usize::MAX` before a [`NumaArenas`] slot is used.
+	node: AtomicUsize,
+}
+
+/// One node-bound arena per NUMA node, created on first use.
+///
+/// Arenas of the set are never destroyed, so threads can be routed to them
+/// without `unsafe`. [`NumaArenas::route_current_thread`] associates the
+/// calling thread with the arena of the node it is running on; call it again
+/// after migrating a thread.
+///
+/// ```no_run
+/// use jevmalloc::NumaArenas;
+///
+/// static NUMA: NumaArenas = NumaArenas::new();
+///
+/// let arena = NUMA.route_current_thread()?;
+/// # Ok::<(), jevmalloc::ctl::Error>(())
+/// ```
+pub struct NumaArenas {
+	/// One table per node, bound when its arena is first created.
+	tables: [NumaHooks; NUMA_NODES],
+
+	/// One more than each node's arena index, or zero before creation.
+	arenas: [AtomicUsize; NUMA_NODES],
+}
+
+impl NumaHooks {
+	/// Constructs a table for `node`.
+	///
+	/// # Panics
+	///
+	/// Panics if `node` is 1024 or higher on 64-bit targets, or 512 or higher
+	/// on 32-bit targets.
+	#[must_use]
+	pub const fn new(node: usize) -> Self {
+		assert!(node < MASK_NODES, "NUMA node is out of range");
+
+		Self::with_node(node)
+	}
+
+	/// Constructs a table whose node is `node`, or unbound for `usize::MAX`.
+	const fn with_node(node: usize) -> Self {
+		Self {
+			hooks: ExtentHooks::new(Node { node: AtomicUsize::new(node) }, ExtentCallbacks {
+				alloc: Some(alloc),
+				dalloc: Some(dalloc),
+				destroy: Some(destroy),
+				commit: Some(commit),
+				purge_lazy: Some(purge_lazy),
+				purge_forced: Some(purge_forced),
+				split: Some(split),
+				merge: Some(merge),
+				..ExtentCallbacks::EMPTY
+			}),
+		}
+	}
+
+	/// Returns the node this table binds memory to.
+	#[must_use]
+	pub fn node(&self) -> usize { self.hooks.state().node.load(Ordering::Relaxed) }
+
+	/// Returns the table to install.
+	pub(super) const fn extent_hooks(&self) -> &ExtentHooks<Node> { &self.hooks }
+}
+
+impl fmt::Debug for NumaHooks {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("NumaHooks")
+			.field("node", &self.node())
+			.finish()
+	}
+}
+
+impl NumaArenas {
+	/// Constructs a set with no arenas created yet.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			tables: [const { NumaHooks::with_node(usize::MAX) }; NUMA_NODES],
+			arenas: [const { AtomicUsize::new(0) }; NUMA_NODES],
+		}
+	}
+
+	/// Returns the arena bound to `node`, creating it on first use.
+	///
+	/// # Errors
+	///
+	/// Returns `EINVAL` if `node` is 64 or higher, or an error from
+	/// [`Arena::create_on_node`].
+	pub fn arena(&'static self, node: usize) -> Result<Arena> {
+		let (Some(table), Some(slot)) = (self.tables.get(node), self.arenas.get(node)) else {
+			return Err(Error::invalid_argument());
+		};
+
+		let mut index = slot.load(Ordering::Acquire);
+		if index == 0 {
+			table
+				.hooks
+				.state()
+				.node
+				.store(node, Ordering::Relaxed);
+			let arena = Arena::create_on_node(table)?;
+			index = match slot.compare_exchange(
+				0,
+				arena.index() + 1,
+				Ordering::AcqRel,
+				Ordering::Acquire,
+			) {
+				| Ok(_) => arena.into_index() + 1,
+				| Err(winner) => winner,
+			};
+		}
+
+		// SAFETY: arenas of the set are never reset or destroyed, so the index
+		// stays live and unrecycled.
+		unsafe { Arena::from_index(index - 1) }
+	}
+
+	/// Associates the calling thread with the arena of the node it is running
+	/// on, creating that arena on first use, and returns it.
+	///
+	/// # Errors
+	///
+	/// Returns a system error if the current node cannot be determined, or an
+	/// error from [`NumaArenas::arena`] or the reassignment.
+	pub fn route_current_thread(&'static self) -> Result<Arena> {
+		let arena = self.arena(current_node()?)?;
+
+		// SAFETY: the arena is never reset or destroyed, so allocations made
+		// through the association cannot outlive it. The previous association
+		// is returned as a non-owning handle and simply released.
+		let _previous: Arena = unsafe { arena.set_current() }?;
+
+		Ok(arena)
+	}
+}
+
+impl Default for NumaArenas {
+	fn default() -> Self { Self::new() }
+}
+
+impl fmt::Debug for NumaArenas {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let created = self
+			.arenas
+			.iter()
+			.filter(|slot| slot.load(Ordering::Relaxed) != 0)
+			.count();
+
+		f.debug_struct("NumaArenas")
+			.field("created", &created)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Returns the node the calling thread is running on.
+fn current_node() -> Result<usize> {
+	let mut node: c_uint = 0;
+
+	// SAFETY: `getcpu` writes the node through the valid pointer and accepts
+	// null for the CPU and cache arguments.
+	let status = unsafe {
+		libc::syscall(
+			libc::SYS_getcpu,
+			null_mut::<c_uint>(),
+			ptr::from_mut(&mut node),
+			null_mut::<libc::c_void>(),
+		)
+	};
+	if status != 0 {
+		return Err(Error::last_os_error());
+	}
+
+	usize::try_from(node).map_err(|_| Error::invalid_argument())
+}
+
+/// Checks that the calling process may allocate on `node`.
+pub(super) fn check_node(node: usize) -> Result {
+	if node >= MASK_NODES {
+		return Err(Error::invalid_argument());
+	}
+
+	let mut allowed: NodeMask = [0; MASK_WORDS];
+
+	// SAFETY: the mask is writable for `MASK_NODES` bits, and the mode and
+	// address arguments are unused with `MPOL_F_MEMS_ALLOWED`.
+	let status = unsafe {
+		libc::syscall(
+			libc::SYS_get_mempolicy,
+			null_mut::<libc::c_int>(),
+			allowed.as_mut_ptr(),
+			MASK_NODES as c_ulong,
+			null_mut::<libc::c_void>(),
+			MPOL_F_MEMS_ALLOWED,
+		)
+	};
+	if status != 0 {
+		return Err(Error::last_os_error());
+	}
+
+	let (word, bit) = (node / c_ulong::BITS as usize, node % c_ulong::BITS as usize);
+	if allowed[word] & (1 << bit) == 0 {
+		return Err(Error::invalid_argument());
+	}
+
+	Ok(())
+}
+
+/// Binds `size` bytes of a fresh mapping to `node`.
+fn bind(address: NonNull<u8>, size: usize, node: usize) -> bool {
+	if node >= MASK_NODES {
+		return false;
+	}
+
+	let mut mask: NodeMask = [0; MASK_WORDS];
+	mask[node / c_ulong::BITS as usize] = 1 << (node % c_ulong::BITS as usize);
+
+	// SAFETY: the range is an untouched mapping owned by the table, and the
+	// mask is readable for `MASK_NODES` bits. The kernel ignores one more bit
+	// than `maxnode` by convention.
+	let status = unsafe {
+		libc::syscall(
+			libc::SYS_mbind,
+			address.as_ptr(),
+			size,
+			c_long::from(libc::MPOL_BIND),
+			mask.as_ptr(),
+			MASK_NODES as c_ulong + 1,
+			MPOL_MF_STRICT,
+		)
+	};
+
+	status == 0
+}
+
+/// Maps an extent and binds it to the table's node.
+///
+/// # Safety
+///
+/// Jemalloc calls this with a valid request.
+unsafe fn alloc(node: &Node, request: ExtentAlloc) -> Option<ExtentAllocation> {
+	if request.address.is_some() || !request.alignment.is_power_of_two() {
+		return None;
+	}
+
+	let page = mapping::os_page()?;
+	let address = mapping::map_aligned(request.size, request.alignment.max(page), page, 0)?;
+	if !bind(address, request.size, node.node.load(Ordering::Relaxed)) {
+		// SAFETY: the mapping was created above and never exposed.
+		unsafe { mapping::unmap(address, request.size) };
+		return None;
+	}
+
+	Some(ExtentAllocation { address, zeroed: true, committed: true })
+}
+
+/// Unmaps a deallocated extent.
+///
+/// # Safety
+///
+/// Jemalloc passes an extent this table mapped and gives up every use of it.
+unsafe fn dalloc(_: &Node, extent: Extent) -> ExtentHookResult {
+	// SAFETY: jemalloc relinquishes the extent.
+	ExtentHookResult::from_success(unsafe { mapping::unmap(extent.address, extent.size) })
+}
+
+/// Unmaps an extent when its arena is destroyed.
+///
+/// # Safety
+///
+/// Jemalloc passes an extent this table mapped and gives up every use of it.
+unsafe fn destroy(_: &Node, extent: Extent) {
+	// SAFETY: jemalloc relinquishes the extent.
+	unsafe { mapping::unmap(extent.address, extent.size) };
+}
+
+/// Accepts a commit request; this table never decommits memory.
+///
+/// # Safety
+///
+/// Jemalloc calls this with an extent this table mapped.
+unsafe fn commit(_: &Node, _: ExtentRange) -> ExtentHookResult { ExtentHookResult::Success }
+
+/// Frees pages lazily.
+///
+/// # Safety
+///
+/// Jemalloc passes a range of an extent this table mapped whose contents it
+/// no longer needs.
+unsafe fn purge_lazy(_: &Node, range: ExtentRange) -> ExtentHookResult {
+	// SAFETY: the range lies within a mapping owned by this table, and
+	// jemalloc no longer needs its contents.
+	unsafe { mapping::advise_range(range, libc::MADV_FREE) }
+}
+
+/// Discards pages immediately.
+///
+/// # Safety
+///
+/// Jemalloc passes a range of an extent this table mapped whose contents it
+/// no longer needs.
+unsafe fn purge_forced(_: &Node, range: ExtentRange) -> ExtentHookResult {
+	// SAFETY: as above.
+	unsafe { mapping::advise_range(range, libc::MADV_DONTNEED) }
+}
+
+/// Accepts any split; mappings can be unmapped piecewise.
+///
+/// # Safety
+///
+/// Jemalloc calls this with an extent this table mapped.
+unsafe fn split(_: &Node, _: ExtentSplit) -> ExtentHookResult { ExtentHookResult::Success }
+
+/// Accepts any merge of adjacent mappings, which share the node's policy.
+///
+/// # Safety
+///
+/// Jemalloc calls this with two adjacent extents this table mapped.
+unsafe fn merge(_: &Node, _: ExtentMerge) -> ExtentHookResult { ExtentHookResult::Success }
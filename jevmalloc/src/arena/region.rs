@@ -0,0 +1,516 @@
+//! A ready-made extent hook table that carves extents from caller memory, and
+//! an arena that keeps one in a borrowed region.
+
+#[cfg(test)]
+mod tests;
+
+use core::{
+	cell::UnsafeCell,
+	fmt, hint,
+	marker::PhantomData,
+	mem::{self, MaybeUninit},
+	ptr::{self, NonNull},
+	sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use arrayvec::ArrayVec;
+
+use super::{
+	Arena, Extent, ExtentAlloc, ExtentAllocation, ExtentCallbacks, ExtentHookResult, ExtentHooks,
+	ExtentMerge, ExtentRange, ExtentSplit,
+};
+use crate::ctl::{Error, Result};
+
+/// The number of disjoint free spans a pool can track.
+///
+/// Returning an extent that would need another span fails, and jemalloc keeps
+/// the extent for its own reuse instead.
+const FREE_SPANS: usize = 64;
+
+/// An extent hook table whose arenas draw memory only from caller regions.
+///
+/// The table is a bounded pool: once its regions are exhausted, extent
+/// allocation fails and so do the arena's allocations, without touching any
+/// global limit. Deallocated and destroyed extents return to the pool for
+/// reuse, and jemalloc may split and merge extents freely within it.
+///
+/// The pool never decommits or purges. Regions are used as given, so they must
+/// be readable and writable; jemalloc's arena metadata is carved from them
+/// too, and its first block is a hugepage-aligned 2 MiB.
+///
+/// [`Arena::create_in_region`] places a private table in the region it is
+/// given, which suits one bounded subsystem. A static table shared by several
+/// arenas takes raw regions through
+/// [`Arena::create_in_raw_region`](super::Arena::create_in_raw_region).
+pub struct RegionHooks {
+	/// The installed table.
+	hooks: ExtentHooks<Pool>,
+}
+
+/// An owned arena that allocates only from one borrowed region.
+///
+/// Created by [`Arena::create_in_region`], which places a private
+/// [`RegionHooks`] table at the start of the region. Dropping the handle
+/// destroys the arena and only then ends the borrow. If jemalloc refuses to
+/// destroy it, such as while a thread remains associated with it, the process
+/// aborts rather than hand back memory the arena may still use.
+///
+/// ```no_run
+/// use core::mem::MaybeUninit;
+///
+/// use jevmalloc::Arena;
+///
+/// let mut region = vec![MaybeUninit::uninit(); 16 << 20];
+/// let arena = Arena::create_in_region(&mut region)?;
+/// assert!(arena.available() > 0);
+/// drop(arena);
+///
+/// // The arena is destroyed, so the region can back another one.
+/// let arena = Arena::create_in_region(&mut region)?;
+/// # Ok::<(), jevmalloc::ctl::Error>(())
+/// ```
+#[must_use = "the arena is destroyed when its handle is dropped"]
+#[derive(Debug)]
+pub struct RegionArena<'r> {
+	/// The owned arena, destroyed before the borrow ends.
+	arena: Arena,
+
+	/// The private table at the start of the region.
+	hooks: &'r RegionHooks,
+
+	/// The exclusive borrow of the whole region.
+	region: PhantomData<&'r mut [MaybeUninit<u8>]>,
+}
+
+/// Callback state of [`RegionHooks`].
+pub(super) struct Pool {
+	/// Whether a thread is inside [`Pool::with`].
+	locked: AtomicBool,
+
+	/// Unused memory, guarded by `locked`.
+	free: UnsafeCell<FreeList>,
+
+	/// Total bytes of every region given to the pool.
+	capacity: AtomicUsize,
+}
+
+// SAFETY: the free list is only reached through `Pool::with`, which holds the
+// spin lock for the duration of each access.
+unsafe impl Sync for Pool {}
+
+/// Unused address ranges, ordered by address and never adjacent.
+#[derive(Debug)]
+struct FreeList {
+	/// Disjoint free spans.
+	spans: ArrayVec<Span, FREE_SPANS>,
+}
+
+/// One free address range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Span {
+	/// First free address.
+	start: usize,
+
+	/// One past the last free address.
+	end: usize,
+}
+
+impl RegionHooks {
+	/// Constructs a table with an empty pool.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			hooks: ExtentHooks::new(Pool::new(), ExtentCallbacks {
+				alloc: Some(alloc),
+				dalloc: Some(dalloc),
+				destroy: Some(destroy),
+				commit: Some(commit),
+				split: Some(split),
+				merge: Some(merge),
+				..ExtentCallbacks::EMPTY
+			}),
+		}
+	}
+
+	/// Returns the total size of every region given to the pool.
+	#[must_use]
+	pub fn capacity(&self) -> usize { self.hooks.state().capacity() }
+
+	/// Returns the number of pool bytes not currently held by jemalloc.
+	#[must_use]
+	pub fn available(&self) -> usize { self.hooks.state().available() }
+
+	/// Adds `len` bytes at `start` to the pool.
+	///
+	/// # Errors
+	///
+	/// Returns `ENOSPC` if the pool tracks too many disjoint spans to add
+	/// another.
+	///
+	/// # Safety
+	///
+	/// The range must satisfy the contract of [`Pool::add`].
+	pub(super) unsafe fn add(&self, start: NonNull<u8>, len: usize) -> Result {
+		// SAFETY: the caller upholds the pool's region contract.
+		unsafe { self.hooks.state().add(start, len) }
+	}
+
+	/// Removes `len` bytes at `start` from the pool if they are all free,
+	/// reporting whether it did.
+	pub(super) fn remove(&self, start: NonNull<u8>, len: usize) -> bool {
+		self.hooks.state().remove(start, len)
+	}
+
+	/// Returns the table to install.
+	pub(super) const fn extent_hooks(&self) -> &ExtentHooks<Pool> { &self.hooks }
+}
+
+impl Default for RegionHooks {
+	fn default() -> Self { Self::new() }
+}
+
+impl fmt::Debug for RegionHooks {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("RegionHooks")
+			.field("capacity", &self.capacity())
+			.field("available", &self.available())
+			.finish()
+	}
+}
+
+impl<'r> RegionArena<'r> {
+	/// Places a private table in `region` and creates an arena on it.
+	///
+	/// # Errors
+	///
+	/// Returns `EINVAL` if the region cannot hold the table, or an error if
+	/// jemalloc cannot create the arena.
+	pub(super) fn new(region: &'r mut [MaybeUninit<u8>]) -> Result<Self> {
+		let offset = region
+			.as_ptr()
+			.align_offset(mem::align_of::<RegionHooks>());
+		let (table, rest) = region
+			.get_mut(offset..)
+			.and_then(|region| region.split_at_mut_checked(mem::size_of::<RegionHooks>()))
+			.ok_or_else(Error::invalid_argument)?;
+		#[expect(clippy::cast_ptr_alignment)]
+		let table = table.as_mut_ptr().cast::<RegionHooks>();
+
+		// SAFETY: the bytes are aligned for the table, large enough to hold it,
+		// and exclusively borrowed for `'r`.
+		unsafe { table.write(RegionHooks::new()) };
+
+		// SAFETY: the table was initialized above and is never written again
+		// through the region borrow.
+		let hooks = unsafe { &*table };
+		let len = rest.len();
+
+		// SAFETY: the rest of the region is exclusively borrowed for `'r`, and
+		// the handle destroys the arena before the borrow ends.
+		unsafe { hooks.add(NonNull::from(rest).cast(), len) }?;
+
+		let raw = NonNull::from(hooks.extent_hooks()).cast();
+
+		// SAFETY: the table is `repr(C)` with its raw header first, and its
+		// callbacks are this crate's pool callbacks, which uphold every mapping
+		// contract, synchronize through the pool lock, and never unwind.
+		// Jemalloc uses the table only until the arena is destroyed, which the
+		// handle does before the borrow ends; a failed creation releases it.
+		let arena = unsafe { Arena::create_with_raw_extent_hooks(raw) }?;
+
+		Ok(Self { arena, hooks, region: PhantomData })
+	}
+
+	/// Returns the owned arena, such as for its allocation flags.
+	pub const fn arena(&self) -> &Arena { &self.arena }
+
+	/// Returns the size of the region usable for extents.
+	#[must_use]
+	pub fn capacity(&self) -> usize { self.hooks.capacity() }
+
+	/// Returns the number of region bytes not currently held by jemalloc.
+	#[must_use]
+	pub fn available(&self) -> usize { self.hooks.available() }
+}
+
+impl Drop for RegionArena<'_> {
+	fn drop(&mut self) {
+		// SAFETY: safe code can only create an arena with no live allocations.
+		// Every operation that can violate destruction requirements is unsafe
+		// and carries the corresponding caller contract.
+		if unsafe { self.arena.destroy() }.is_err() {
+			// SAFETY: the arena may still use the region, so the borrow must not
+			// end; aborting has no further requirements.
+			unsafe { libc::abort() };
+		}
+	}
+}
+
+impl Pool {
+	/// Constructs an empty pool.
+	pub(super) const fn new() -> Self {
+		Self {
+			locked: AtomicBool::new(false),
+			free: UnsafeCell::new(FreeList::new()),
+			capacity: AtomicUsize::new(0),
+		}
+	}
+
+	/// Returns the total size of every region given to the pool.
+	pub(super) fn capacity(&self) -> usize { self.capacity.load(Ordering::Relaxed) }
+
+	/// Returns the number of free bytes.
+	pub(super) fn available(&self) -> usize { self.with(|free| free.available()) }
+
+	/// Adds `len` bytes at `start` to the pool.
+	///
+	/// # Errors
+	///
+	/// Returns `EINVAL` if the range wraps the address space, or `ENOSPC` if
+	/// the pool tracks too many disjoint spans to add another.
+	///
+	/// # Safety
+	///
+	/// The range must be readable, writable, disjoint from every other region
+	/// of this pool, and unused by anything else while the pool may hand it
+	/// out.
+	pub(super) unsafe fn add(&self, start: NonNull<u8>, len: usize) -> Result {
+		let start = start.as_ptr().expose_provenance();
+		let end = start
+			.checked_add(len)
+			.ok_or_else(Error::invalid_argument)?;
+
+		if !self.with(|free| free.give(Span { start, end })) {
+			return Err(Error::insufficient_space());
+		}
+
+		self.capacity.fetch_add(len, Ordering::Relaxed);
+
+		Ok(())
+	}
+
+	/// Removes `len` bytes at `start` from the pool if they are all free,
+	/// reporting whether it did.
+	pub(super) fn remove(&self, start: NonNull<u8>, len: usize) -> bool {
+		let removed = self.with(|free| free.take_at(start.as_ptr().addr(), len));
+		if removed {
+			self.capacity.fetch_sub(len, Ordering::Relaxed);
+		}
+
+		removed
+	}
+
+	/// Removes `size` free bytes at `alignment`, or at exactly `address` if
+	/// one is given.
+	pub(super) fn take(
+		&self,
+		address: Option<NonNull<u8>>,
+		size: usize,
+		alignment: usize,
+	) -> Option<NonNull<u8>> {
+		if !alignment.is_power_of_two() {
+			return None;
+		}
+
+		match address {
+			| Some(address) => {
+				let start = address.as_ptr().addr();
+				self.with(|free| free.take_at(start, size))
+					.then_some(address)
+			},
+			| None => {
+				let start = self.with(|free| free.take(size, alignment))?;
+				NonNull::new(ptr::with_exposed_provenance_mut(start))
+			},
+		}
+	}
+
+	/// Returns `size` bytes at `address` to the pool.
+	///
+	/// Reports `false`, keeping the bytes out of the pool, if it cannot track
+	/// another span.
+	pub(super) fn give(&self, address: NonNull<u8>, size: usize) -> bool {
+		let start = address.as_ptr().addr();
+
+		self.with(|free| free.give(Span { start, end: start + size }))
+	}
+
+	/// Runs `f` on the free list under the pool's spin lock.
+	///
+	/// `f` must not panic, or the lock stays held.
+	fn with<R>(&self, f: impl FnOnce(&mut FreeList) -> R) -> R {
+		while self
+			.locked
+			.compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+			.is_err()
+		{
+			hint::spin_loop();
+		}
+
+		// SAFETY: the lock acquired above grants exclusive access to the list
+		// until it is released below.
+		let result = f(unsafe { &mut *self.free.get() });
+		self.locked.store(false, Ordering::Release);
+
+		result
+	}
+}
+
+impl FreeList {
+	/// Constructs an empty list.
+	const fn new() -> Self { Self { spans: ArrayVec::new_const() } }
+
+	/// Returns the total free size.
+	fn available(&self) -> usize {
+		self.spans
+			.iter()
+			.map(|span| span.end - span.start)
+			.sum()
+	}
+
+	/// Removes the first `size` bytes at `alignment` that fit, returning their
+	/// address.
+	fn take(&mut self, size: usize, alignment: usize) -> Option<usize> {
+		(0..self.spans.len()).find_map(|index| {
+			let span = self.spans[index];
+			let start = span.start.checked_next_multiple_of(alignment)?;
+
+			self.carve(index, start, size).then_some(start)
+		})
+	}
+
+	/// Removes `size` bytes at exactly `start`, if they are free.
+	fn take_at(&mut self, start: usize, size: usize) -> bool {
+		let index = self
+			.spans
+			.partition_point(|span| span.end <= start);
+
+		index < self.spans.len() && self.carve(index, start, size)
+	}
+
+	/// Removes `size` bytes at `start` from the span at `index`, if they lie
+	/// within it and any remainder on both sides can be tracked.
+	fn carve(&mut self, index: usize, start: usize, size: usize) -> bool {
+		let span = self.spans[index];
+		let Some(end) = start.checked_add(size) else {
+			return false;
+		};
+		if start < span.start || end > span.end {
+			return false;
+		}
+
+		match (start > span.start, end < span.end) {
+			| (false, false) => {
+				self.spans.remove(index);
+			},
+			| (true, false) => self.spans[index].end = start,
+			| (false, true) => self.spans[index].start = end,
+			| (true, true) => {
+				if self
+					.spans
+					.try_insert(index + 1, Span { start: end, end: span.end })
+					.is_err()
+				{
+					return false;
+				}
+				self.spans[index].end = start;
+			},
+		}
+
+		true
+	}
+
+	/// Returns `span` to the list, merging it with adjacent free spans.
+	///
+	/// Reports `false`, leaving the list unchanged, if the span would need a
+	/// new entry and the list is full.
+	fn give(&mut self, span: Span) -> bool {
+		if span.start == span.end {
+			return true;
+		}
+
+		let index = self
+			.spans
+			.partition_point(|free| free.start < span.start);
+		let before = index
+			.checked_sub(1)
+			.filter(|&before| self.spans[before].end == span.start);
+		let after =
+			(index < self.spans.len() && self.spans[index].start == span.end).then_some(index);
+
+		match (before, after) {
+			| (Some(before), Some(after)) => {
+				self.spans[before].end = self.spans[after].end;
+				self.spans.remove(after);
+			},
+			| (Some(before), None) => self.spans[before].end = span.end,
+			| (None, Some(after)) => self.spans[after].start = span.start,
+			| (None, None) => return self.spans.try_insert(index, span).is_ok(),
+		}
+
+		true
+	}
+}
+
+/// Carves an extent from the pool.
+///
+/// # Safety
+///
+/// Jemalloc calls this with a valid request.
+unsafe fn alloc(pool: &Pool, request: ExtentAlloc) -> Option<ExtentAllocation> {
+	let address = pool.take(request.address, request.size, request.alignment)?;
+
+	if request.zeroed {
+		// SAFETY: the range was just removed from the pool, whose regions are
+		// writable and used by nothing else.
+		unsafe { address.write_bytes(0, request.size) };
+	}
+
+	Some(ExtentAllocation {
+		address,
+		zeroed: request.zeroed,
+		committed: true,
+	})
+}
+
+/// Returns an extent to the pool, or keeps it with jemalloc if the pool cannot
+/// track another span.
+///
+/// # Safety
+///
+/// Jemalloc passes an extent carved from this pool and gives up every use of
+/// it.
+unsafe fn dalloc(pool: &Pool, extent: Extent) -> ExtentHookResult {
+	ExtentHookResult::from_success(pool.give(extent.address, extent.size))
+}
+
+/// Returns an extent to the pool when its arena is destroyed.
+///
+/// If the pool cannot track another span, the extent's bytes stay unusable.
+///
+/// # Safety
+///
+/// Jemalloc passes an extent carved from this pool and gives up every use of
+/// it.
+unsafe fn destroy(pool: &Pool, extent: Extent) { pool.give(extent.address, extent.size); }
+
+/// Accepts a commit request; pool memory is never decommitted.
+///
+/// # Safety
+///
+/// Jemalloc calls this with an extent carved from this pool.
+unsafe fn commit(_: &Pool, _: ExtentRange) -> ExtentHookResult { ExtentHookResult::Success }
+
+/// Accepts any split; the pool tracks only free memory.
+///
+/// # Safety
+///
+/// Jemalloc calls this with an extent carved from this pool.
+unsafe fn split(_: &Pool, _: ExtentSplit) -> ExtentHookResult { ExtentHookResult::Success }
+
+/// Accepts any merge of adjacent extents.
+///
+/// # Safety
+///
+/// Jemalloc calls this with two adjacent extents carved from this pool.
+unsafe fn merge(_: &Pool, _: ExtentMerge) -> ExtentHookResult { ExtentHookResult::Success }
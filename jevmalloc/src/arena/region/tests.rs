@@ -0,0 +1,89 @@
+//! Checks the pool's free-span bookkeeping.
+
+use super::*;
+
+/// Builds a list holding the given spans.
+fn list(spans: &[(usize, usize)]) -> FreeList {
+	let mut free = FreeList::new();
+	for &(start, end) in spans {
+		assert!(free.give(Span { start, end }));
+	}
+
+	free
+}
+
+/// Collects a list's spans as pairs.
+fn spans(free: &FreeList) -> ArrayVec<(usize, usize), FREE_SPANS> {
+	free.spans
+		.iter()
+		.map(|span| (span.start, span.end))
+		.collect()
+}
+
+/// Aligned carving splits a span and keeps both remainders.
+#[test]
+fn take_aligned() {
+	let mut free = list(&[(0x1100, 0x9000)]);
+
+	assert_eq!(free.take(0x2000, 0x1000), Some(0x2000));
+	assert_eq!(spans(&free).as_slice(), [(0x1100, 0x2000), (0x4000, 0x9000)]);
+	assert_eq!(free.take(0x8000, 0x1000), None);
+	assert_eq!(free.available(), 0x7F00 - 0x2000);
+}
+
+/// Returned spans merge with both neighbors.
+#[test]
+fn give_coalesces() {
+	let mut free = list(&[(0x1000, 0x2000), (0x3000, 0x4000)]);
+
+	assert!(free.give(Span { start: 0x2000, end: 0x3000 }));
+	assert_eq!(spans(&free).as_slice(), [(0x1000, 0x4000)]);
+	assert!(free.give(Span { start: 0x5000, end: 0x6000 }));
+	assert!(free.give(Span { start: 0x4000, end: 0x5000 }));
+	assert_eq!(spans(&free).as_slice(), [(0x1000, 0x6000)]);
+}
+
+/// Fixed-address carving succeeds only inside a free span.
+#[test]
+fn take_at_fixed_address() {
+	let mut free = list(&[(0x1000, 0x4000)]);
+
+	assert!(free.take_at(0x2000, 0x1000));
+	assert!(!free.take_at(0x2000, 0x1000));
+	assert!(!free.take_at(0x3000, 0x2000));
+	assert!(free.take_at(0x3000, 0x1000));
+	assert_eq!(spans(&free).as_slice(), [(0x1000, 0x2000)]);
+}
+
+/// A full list refuses work that needs another span.
+#[test]
+fn full_list_refuses_new_spans() {
+	let mut free = FreeList::new();
+	for index in 0..FREE_SPANS {
+		let start = index * 0x2000;
+		assert!(free.give(Span { start, end: start + 0x1000 }));
+	}
+
+	let last = (FREE_SPANS - 1) * 0x2000;
+	assert!(!free.give(Span { start: last + 0x4000, end: last + 0x5000 }));
+	assert!(!free.take_at(0x400, 0x400));
+	assert!(free.take_at(0, 0x400));
+	assert_eq!(free.spans.len(), FREE_SPANS);
+}
+
+/// A region nobody has carved from can be withdrawn whole.
+#[test]
+fn remove_withdraws_region() {
+	let pool = Pool::new();
+	let start = NonNull::new(ptr::without_provenance_mut(0x10_0000)).unwrap();
+
+	// SAFETY: the pool only records the range; nothing is written to it.
+	unsafe { pool.add(start, 0x4000) }.unwrap();
+	let taken = pool.take(None, 0x1000, 0x1000).unwrap();
+	assert!(!pool.remove(start, 0x4000));
+
+	assert!(pool.give(taken, 0x1000));
+	assert!(pool.remove(start, 0x4000));
+	assert_eq!(pool.capacity(), 0);
+	assert_eq!(pool.available(), 0);
+}
@@ -0,0 +1,229 @@
+//! A ready-made extent hook table for arenas that hold key material.
+
+use core::{
+	fmt, mem,
+	ptr::NonNull,
+	sync::atomic::{self, Ordering},
+};
+
+use super::{
+	Extent, ExtentAlloc, ExtentAllocation, ExtentCallbacks, ExtentHookResult, ExtentHooks,
+	ExtentMerge, ExtentRange, ExtentSplit, mapping,
+};
+
+/// An extent hook table that keeps an arena's memory out of swap, core dumps,
+/// and forked children, and wipes it before release.
+///
+/// Each extent is a fresh anonymous mapping advised with `MADV_DONTDUMP` and
+/// `MADV_WIPEONFORK` and locked with `mlock`; allocation fails if any step
+/// fails, so arenas need `RLIMIT_MEMLOCK` headroom or `CAP_IPC_LOCK`. Memory
+/// is overwritten with zeros before every deallocation, decommit, purge, and
+/// destruction. Decommit also unlocks the range and returns its pages to the
+/// kernel; commit locks it again. Purges keep pages locked.
+///
+/// The hooks only see whole extents. Jemalloc reuses freed allocations within
+/// an extent without involving them, and thread caches hold freed allocations,
+/// so allocate with [`MALLOCX_TCACHE_NONE`](crate::ffi::MALLOCX_TCACHE_NONE)
+/// and wipe each secret before freeing it.
+///
+/// ```no_run
+/// use jevmalloc::{Arena, SecretHooks, ffi};
+///
+/// static SECRETS: SecretHooks = SecretHooks::new();
+///
+/// let arena = Arena::create_secret(&SECRETS)?;
+/// let flags = arena.flags() | ffi::MALLOCX_TCACHE_NONE;
+/// # Ok::<(), jevmalloc::ctl::Error>(())
+/// ```
+pub struct SecretHooks {
+	/// The installed table.
+	hooks: ExtentHooks<Secret>,
+}
+
+/// Callback state of [`SecretHooks`], which needs none.
+pub(super) struct Secret;
+
+impl SecretHooks {
+	/// Constructs the table.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			hooks: ExtentHooks::new(Secret, ExtentCallbacks {
+				alloc: Some(alloc),
+				dalloc: Some(dalloc),
+				destroy: Some(destroy),
+				commit: Some(commit),
+				decommit: Some(decommit),
+				purge_lazy: Some(purge),
+				purge_forced: Some(purge),
+				split: Some(split),
+				merge: Some(merge),
+			}),
+		}
+	}
+
+	/// Returns the table to install.
+	pub(super) const fn extent_hooks(&self) -> &ExtentHooks<Secret> { &self.hooks }
+}
+
+impl Default for SecretHooks {
+	fn default() -> Self { Self::new() }
+}
+
+impl fmt::Debug for SecretHooks {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("SecretHooks")
+			.finish_non_exhaustive()
+	}
+}
+
+/// Overwrites `length` bytes at `start` with zeros the compiler cannot elide.
+///
+/// # Safety
+///
+/// The range must be writable, aligned to `usize`, and a multiple of its size,
+/// as every page-aligned range of a mapping is.
+unsafe fn wipe(start: NonNull<u8>, length: usize) {
+	let words = start.cast::<usize>();
+	for index in 0..length / mem::size_of::<usize>() {
+		// SAFETY: the word lies within the caller's range.
+		let word = unsafe { words.add(index) };
+		// SAFETY: the caller guarantees an aligned, writable range.
+		unsafe { word.write_volatile(0) };
+	}
+
+	atomic::compiler_fence(Ordering::SeqCst);
+}
+
+/// Maps, protects, and locks an extent.
+///
+/// # Safety
+///
+/// Jemalloc calls this with a valid request.
+unsafe fn alloc(_: &Secret, request: ExtentAlloc) -> Option<ExtentAllocation> {
+	if request.address.is_some() || !request.alignment.is_power_of_two() {
+		return None;
+	}
+
+	let page = mapping::os_page()?;
+	let address = mapping::map_aligned(request.size, request.alignment.max(page), page, 0)?;
+
+	// SAFETY: the advice keeps the new mapping, owned by this table, out of
+	// core dumps and forked children.
+	let protected = [libc::MADV_DONTDUMP, libc::MADV_WIPEONFORK]
+		.into_iter()
+		.all(|advice| unsafe { mapping::advise(address, request.size, advice) });
+
+	// SAFETY: locking a mapping owned by this table has no memory effect.
+	if !protected || unsafe { libc::mlock(address.as_ptr().cast(), request.size) } != 0 {
+		// SAFETY: the mapping was created above and never exposed.
+		unsafe { mapping::unmap(address, request.size) };
+		return None;
+	}
+
+	Some(ExtentAllocation { address, zeroed: true, committed: true })
+}
+
+/// Wipes and unmaps a deallocated extent.
+///
+/// # Safety
+///
+/// Jemalloc passes an extent this table mapped and gives up every use of it.
+unsafe fn dalloc(_: &Secret, extent: Extent) -> ExtentHookResult {
+	// SAFETY: jemalloc relinquishes the extent.
+	ExtentHookResult::from_success(unsafe { release(extent) })
+}
+
+/// Wipes and unmaps an extent when its arena is destroyed.
+///
+/// # Safety
+///
+/// Jemalloc passes an extent this table mapped and gives up every use of it.
+unsafe fn destroy(_: &Secret, extent: Extent) {
+	// SAFETY: jemalloc relinquishes the extent.
+	unsafe { release(extent) };
+}
+
+/// Wipes a relinquished extent's committed pages and unmaps it.
+///
+/// # Safety
+///
+/// The extent must be a mapping of this table that jemalloc gave up.
+unsafe fn release(extent: Extent) -> bool {
+	if extent.committed {
+		// SAFETY: a committed extent is writable, and jemalloc gave it up.
+		unsafe { wipe(extent.address, extent.size) };
+	}
+
+	// SAFETY: jemalloc gave up the extent.
+	unsafe { mapping::unmap(extent.address, extent.size) }
+}
+
+/// Locks a range again after decommit.
+///
+/// # Safety
+///
+/// Jemalloc passes a range of an extent this table mapped.
+unsafe fn commit(_: &Secret, range: ExtentRange) -> ExtentHookResult {
+	// SAFETY: the range lies within the extent.
+	let start = unsafe { range.address.byte_add(range.offset) };
+
+	// SAFETY: locking part of this table's mapping faults in zeroed pages and
+	// has no other memory effect.
+	ExtentHookResult::from_success(
+		unsafe { libc::mlock(start.as_ptr().cast(), range.length) } == 0,
+	)
+}
+
+/// Wipes, unlocks, and discards a range.
+///
+/// # Safety
+///
+/// Jemalloc passes a committed range of an extent this table mapped whose
+/// contents it no longer needs.
+unsafe fn decommit(_: &Secret, range: ExtentRange) -> ExtentHookResult {
+	// SAFETY: the range lies within the extent.
+	let start = unsafe { range.address.byte_add(range.offset) };
+
+	// SAFETY: the committed range is writable and no longer needed.
+	unsafe { wipe(start, range.length) };
+
+	// SAFETY: the range is part of this table's mapping and already wiped, so
+	// unlocking it exposes nothing.
+	if unsafe { libc::munlock(start.as_ptr().cast(), range.length) } != 0 {
+		return ExtentHookResult::Failure;
+	}
+
+	// SAFETY: the wiped range's contents are no longer needed.
+	unsafe { mapping::advise_range(range, libc::MADV_DONTNEED) }
+}
+
+/// Wipes a range, leaving its pages locked.
+///
+/// # Safety
+///
+/// Jemalloc passes a committed range of an extent this table mapped whose
+/// contents it no longer needs.
+unsafe fn purge(_: &Secret, range: ExtentRange) -> ExtentHookResult {
+	// SAFETY: the range lies within the extent.
+	let start = unsafe { range.address.byte_add(range.offset) };
+
+	// SAFETY: the committed range is writable and no longer needed.
+	unsafe { wipe(start, range.length) };
+
+	ExtentHookResult::Success
+}
+
+/// Accepts any split; mappings can be unmapped piecewise.
+///
+/// # Safety
+///
+/// Jemalloc calls this with an extent this table mapped.
+unsafe fn split(_: &Secret, _: ExtentSplit) -> ExtentHookResult { ExtentHookResult::Success }
+
+/// Accepts any merge of adjacent mappings, which carry the same protections.
+///
+/// # Safety
+///
+/// Jemalloc calls this with two adjacent extents this table mapped.
+unsafe fn merge(_: &Secret, _: ExtentMerge) -> ExtentHookResult { ExtentHookResult::Success }
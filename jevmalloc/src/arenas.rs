@@ -1,17 +1,13 @@
 //! Allocator-wide arena defaults, queries, and reclamation controls.
 
-use core::ffi::CStr;
-
 use libc::{c_char, c_uint};
 
 use crate::{
-	arena::Dss,
+	arena::{ALL_ARENAS, Dss},
 	ctl::{Error, Key, Result, key, raw},
+	opt::{self, PercpuArena},
 };
 
-/// Jemalloc's documented MIB sentinel for all arenas.
-const ALL_ARENAS: usize = 4096;
-
 /// Reclaims unused pages from every initialized arena.
 ///
 /// The operation applies time-based decay before purging all remaining unused
@@ -175,55 +171,143 @@ pub fn quantum() -> Result<usize> {
 	unsafe { raw::get(&key) }
 }
 
-/// Returns jemalloc's configured per-CPU arena mode.
+/// Returns jemalloc's page size in bytes.
 ///
-/// The result is one of `"disabled"`, `"percpu"`, or `"phycpu"`. This wrapper
-/// returns static Rust strings rather than exposing jemalloc's pointer.
+/// # Errors
+///
+/// Returns an error if jemalloc rejects the query.
+pub fn page() -> Result<usize> {
+	let key = key::arenas_page()?;
+
+	// SAFETY: `arenas.page` has the C output type `size_t`.
+	unsafe { raw::get(&key) }
+}
+
+/// Returns the largest size class eligible for thread caching, in bytes.
 ///
 /// # Errors
 ///
-/// Returns an error if jemalloc rejects the query, returns a null pointer, or
-/// reports an undocumented mode.
-pub fn percpu_mode() -> Result<&'static str> {
-	let key = key::opt_percpu_arena()?;
+/// Returns an error if jemalloc rejects the query.
+pub fn tcache_max() -> Result<usize> {
+	let key = key::arenas_tcache_max()?;
 
-	// SAFETY: `opt.percpu_arena` has the C output type `const char *`.
-	let ptr = unsafe { raw::get::<*const c_char>(&key) }?;
-	if ptr.is_null() {
-		return Err(Error::bad_address());
+	// SAFETY: `arenas.tcache_max` has the C output type `size_t`.
+	unsafe { raw::get(&key) }
+}
+
+/// Returns the number of small size classes served from slab bins.
+///
+/// # Errors
+///
+/// Returns an error if jemalloc rejects the query or the count exceeds Rust
+/// `usize`.
+pub fn nbins() -> Result<usize> {
+	let key = key::arenas_nbins()?;
+
+	// SAFETY: `arenas.nbins` has the C output type `unsigned`.
+	let bins = unsafe { raw::get::<c_uint>(&key) }?;
+
+	bins.try_into()
+		.map_err(|_| Error::invalid_argument())
+}
+
+/// Returns the number of large size classes.
+///
+/// # Errors
+///
+/// Returns an error if jemalloc rejects the query or the count exceeds Rust
+/// `usize`.
+pub fn nlextents() -> Result<usize> {
+	let key = key::arenas_nlextents()?;
+
+	// SAFETY: `arenas.nlextents` has the C output type `unsigned`.
+	let lextents = unsafe { raw::get::<c_uint>(&key) }?;
+
+	lextents
+		.try_into()
+		.map_err(|_| Error::invalid_argument())
+}
+
+/// Returns the allocation size of one small size class in bytes.
+///
+/// # Errors
+///
+/// Returns `EINVAL` when `index` is not below [`nbins`], or another error if
+/// jemalloc rejects the query.
+pub fn bin_size(index: usize) -> Result<usize> {
+	if index >= nbins()? {
+		return Err(Error::invalid_argument());
 	}
 
-	// SAFETY: the pointer names a process-lifetime static, terminated mode.
-	let value = unsafe { CStr::from_ptr(ptr) }.to_bytes();
-	match value {
-		| b"disabled" => Ok("disabled"),
-		| b"percpu" => Ok("percpu"),
-		| b"phycpu" => Ok("phycpu"),
-		| _ => Err(Error::invalid_argument()),
+	let mut key = key::arenas_bin_size()?;
+	key[2] = index;
+
+	// SAFETY: `arenas.bin.<i>.size` has the C output type `size_t`, and the
+	// index was checked above because jemalloc also accepts `nbins` itself.
+	unsafe { raw::get(&key) }
+}
+
+/// Returns the allocation size of one large size class in bytes.
+///
+/// # Errors
+///
+/// Returns `EINVAL` when `index` is not below [`nlextents`], or another error
+/// if jemalloc rejects the query.
+pub fn lextent_size(index: usize) -> Result<usize> {
+	if index >= nlextents()? {
+		return Err(Error::invalid_argument());
 	}
+
+	let mut key = key::arenas_lextent_size()?;
+	key[2] = index;
+
+	// SAFETY: `arenas.lextent.<i>.size` has the C output type `size_t`, and
+	// the index was checked above because jemalloc also accepts `nlextents`.
+	unsafe { raw::get(&key) }
 }
 
+/// Returns jemalloc's configured per-CPU arena mode.
+///
+/// # Errors
+///
+/// Returns an error if jemalloc rejects the query, returns a null pointer, or
+/// reports an undocumented mode.
+pub fn percpu_arena() -> Result<PercpuArena> { opt::percpu_arena_mode() }
+
+/// Returns jemalloc's configured per-CPU arena mode as a string.
+///
+/// The result is one of `"disabled"`, `"percpu"`, or `"phycpu"`. This wrapper
+/// returns static Rust strings rather than exposing jemalloc's pointer.
+///
+/// # Errors
+///
+/// Returns an error if jemalloc rejects the query, returns a null pointer, or
+/// reports an undocumented mode.
+pub fn percpu_mode() -> Result<&'static str> { percpu_arena().map(PercpuArena::as_str) }
+
 /// Returns whether jemalloc uses either CPU-affine arena mode.
 ///
 /// Query failures produce `false` because an unavailable mode is treated as
 /// disabled by this convenience predicate.
 #[must_use]
 #[inline]
-pub fn is_affine() -> bool { percpu_mode().is_ok_and(|mode| matches!(mode, "percpu" | "phycpu")) }
+pub fn is_affine() -> bool {
+	percpu_arena().is_ok_and(|mode| matches!(mode, PercpuArena::Percpu | PercpuArena::Phycpu))
+}
 
 /// Returns whether jemalloc assigns arenas per logical CPU.
 ///
 /// Query failures produce `false`.
 #[must_use]
 #[inline]
-pub fn is_percpu() -> bool { percpu_mode().is_ok_and(|mode| mode == "percpu") }
+pub fn is_percpu() -> bool { percpu_arena() == Ok(PercpuArena::Percpu) }
 
 /// Returns whether jemalloc assigns arenas per physical CPU.
 ///
 /// Query failures produce `false`.
 #[must_use]
 #[inline]
-pub fn is_phycpu() -> bool { percpu_mode().is_ok_and(|mode| mode == "phycpu") }
+pub fn is_phycpu() -> bool { percpu_arena() == Ok(PercpuArena::Phycpu) }
 
 /// Invokes an all-arena no-value command.
 fn notify(key: Key) -> Result {
@@ -6,9 +6,16 @@
 //! requires no allocation or encoding conversion. A library supplied through
 //! `JEMALLOC_OVERRIDE` must preserve the bundled implementation's immutable,
 //! process-lifetime storage contract for string controls.
+//!
+//! [`Effective`] gathers these flags with every `opt.*` value, the arena
+//! geometry, the jemalloc version, and this crate's build settings into one
+//! printable report of [`ctl::Value`](crate::ctl::Value)s.
+
+mod effective;
 
 use core::ffi::CStr;
 
+pub use self::effective::{Effective, Entries};
 use crate::ctl::{Result, key, value};
 
 /// Defines a getter for one boolean build configuration.
@@ -88,3 +95,15 @@ bool_getter! {
 	/// Returns whether abort-on-allocation-failure support was enabled.
 	xmalloc => config_xmalloc
 }
+
+/// Returns the jemalloc version string.
+///
+/// The bytes remain valid for the life of the process.
+///
+/// # Errors
+///
+/// Returns an error if jemalloc rejects the query or returns a null pointer.
+pub fn version() -> Result<&'static CStr> {
+	let key = key::version()?;
+	value::get_cstr(&key)
+}
@@ -0,0 +1,174 @@
+//! A flat report of the configuration in force.
+
+use core::{
+	fmt,
+	iter::{Enumerate, FusedIterator},
+	slice,
+};
+
+use crate::{
+	ctl::{
+		Result, Value,
+		walk::{self, REPORTED},
+	},
+	ffi,
+};
+
+/// Every `build.*` key with its value, in report order.
+const BUILD: &[(&str, bool)] = &[
+	("build.prefixed", ffi::PREFIXED),
+	("build.feature.alloc", cfg!(feature = "alloc")),
+	("build.feature.budget", cfg!(feature = "budget")),
+	("build.feature.cache_oblivious", cfg!(feature = "cache_oblivious")),
+	("build.feature.check_safety", cfg!(feature = "check_safety")),
+	("build.feature.check_size_match", cfg!(feature = "check_size_match")),
+	("build.feature.check_use_after_free", cfg!(feature = "check_use_after_free")),
+	("build.feature.fill", cfg!(feature = "fill")),
+	("build.feature.global_hooks", cfg!(feature = "global_hooks")),
+	("build.feature.initial_exec_tls", cfg!(feature = "initial_exec_tls")),
+	("build.feature.log", cfg!(feature = "log")),
+	("build.feature.pageid", cfg!(feature = "pageid")),
+	("build.feature.paranoid", cfg!(feature = "paranoid")),
+	("build.feature.profiling", cfg!(feature = "profiling")),
+	("build.feature.profiling_frameptr", cfg!(feature = "profiling_frameptr")),
+	("build.feature.stats", cfg!(feature = "stats")),
+	("build.feature.std", cfg!(feature = "std")),
+	(
+		"build.feature.unprefixed_malloc_on_supported_platforms",
+		cfg!(feature = "unprefixed_malloc_on_supported_platforms"),
+	),
+];
+
+/// Number of reported keys.
+const LEN: usize = REPORTED.len() + BUILD.len();
+
+/// The configuration in force, captured as ordered key/value pairs.
+///
+/// Keys are the `version`, `config.*`, `opt.*`, and unindexed `arenas.*`
+/// controls of the [`ctl::walk`](crate::ctl::walk) schema, each with its
+/// value in the control's C type, followed by the `build.*` symbol regime and
+/// Cargo features of this crate. [`Display`](fmt::Display) writes one
+/// `key=value` line per entry in a fixed order, so two reports can be
+/// compared with a line diff:
+///
+/// ```
+/// let effective = jevmalloc::config::Effective::collect()?;
+///
+/// assert!(effective.get("arenas.page").is_some());
+/// assert!(effective.iter().all(|(key, _)| !key.is_empty()));
+/// println!("{effective}");
+/// # Ok::<(), jevmalloc::ctl::Error>(())
+/// ```
+///
+/// The report is a snapshot; `arenas.narenas` grows as arenas are created.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Effective {
+	/// Values in report order: [`REPORTED`], then [`BUILD`]. `None` marks a
+	/// control that depends on a capability omitted from the jemalloc build.
+	values: [Option<Value>; LEN],
+}
+
+/// Iterator over the entries of an [`Effective`] report.
+#[derive(Clone, Debug)]
+pub struct Entries<'a> {
+	/// Remaining values with their report positions.
+	values: Enumerate<slice::Iter<'a, Option<Value>>>,
+}
+
+impl Effective {
+	/// Reads every reported value.
+	///
+	/// Controls that depend on a capability missing from the jemalloc build
+	/// are reported as `None`.
+	///
+	/// # Errors
+	///
+	/// Returns the first error other than `ENOENT` that jemalloc reports.
+	pub fn collect() -> Result<Self> {
+		let mut values = [None; LEN];
+		for (index, value) in values.iter_mut().enumerate() {
+			if let Some(read) = Self::read(key(index)) {
+				*value = read?;
+			}
+		}
+
+		Ok(Self { values })
+	}
+
+	/// Reads the current value of one key without collecting the others, or
+	/// returns `None` if the report has no such key. The inner value is `None`
+	/// if the control depends on a capability missing from the jemalloc build.
+	///
+	/// # Errors
+	///
+	/// The inner result holds any error other than `ENOENT` that jemalloc
+	/// reports.
+	#[must_use]
+	pub fn read(key: &str) -> Option<Result<Option<Value>>> {
+		if let Some(&(_, value)) = BUILD.iter().find(|(name, _)| *name == key) {
+			return Some(Ok(Some(Value::Bool(value))));
+		}
+
+		walk::read_reported(key).map(|read| match read {
+			| Err(error) if error.is(libc::ENOENT) => Ok(None),
+			| result => result.map(Some),
+		})
+	}
+
+	/// Returns the value reported under `key`, as described for
+	/// [`Effective::read`].
+	#[must_use]
+	pub fn get(&self, key: &str) -> Option<Option<Value>> {
+		(0..LEN)
+			.find(|&index| self::key(index) == key)
+			.map(|index| self.values[index])
+	}
+
+	/// Iterates over the entries in report order.
+	#[must_use]
+	pub fn iter(&self) -> Entries<'_> { Entries { values: self.values.iter().enumerate() } }
+}
+
+/// Returns the key reported at `index`.
+fn key(index: usize) -> &'static str {
+	match REPORTED.get(index) {
+		| Some(name) => name,
+		| None => BUILD[index - REPORTED.len()].0,
+	}
+}
+
+impl Iterator for Entries<'_> {
+	type Item = (&'static str, Option<Value>);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let (index, value) = self.values.next()?;
+
+		Some((key(index), *value))
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) { self.values.size_hint() }
+}
+
+impl ExactSizeIterator for Entries<'_> {}
+
+impl FusedIterator for Entries<'_> {}
+
+impl<'a> IntoIterator for &'a Effective {
+	type IntoIter = Entries<'a>;
+	type Item = (&'static str, Option<Value>);
+
+	fn into_iter(self) -> Self::IntoIter { self.iter() }
+}
+
+impl fmt::Display for Effective {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (key, value) in self {
+			match value {
+				| Some(value) => writeln!(f, "{key}={value}")?,
+				| None => writeln!(f, "{key}=unavailable")?,
+			}
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,200 @@
+//! Typed, cached descriptors for individual controls.
+
+use core::{fmt, marker::PhantomData};
+
+use super::{Key, Operation, Result, key::Cache, raw};
+
+/// A jemalloc control with a fixed name, C value type, and access mode.
+///
+/// The descriptor caches its MIB process-wide on first use, like the crate's
+/// built-in controls, and exposes only the operations its access mode permits.
+/// Every operation is safe: the type and access contract is asserted once,
+/// when the descriptor is constructed. Define descriptors with
+/// [`control!`](crate::control).
+///
+/// Operations take `&'static self`, as descriptors live in statics, and
+/// their errors carry the control name through
+/// [`Error::context`](super::Error::context).
+///
+/// Only the exact control named is addressed; a name with numeric components
+/// such as `arena.0.dirty_decay_ms` selects that one index.
+pub struct Control<T, A> {
+	/// The dotted control name.
+	name: &'static str,
+
+	/// The lazily translated MIB.
+	key: Cache,
+
+	/// The value type and access mode, which own no data.
+	marker: PhantomData<fn(T) -> (T, A)>,
+}
+
+/// Access mode of a control that can only be read.
+#[derive(Clone, Copy, Debug)]
+pub enum ReadOnly {}
+
+/// Access mode of a control that can only be written.
+#[derive(Clone, Copy, Debug)]
+pub enum WriteOnly {}
+
+/// Access mode of a control that accepts a value and returns the previous one.
+#[derive(Clone, Copy, Debug)]
+pub enum ReadWrite {}
+
+/// Access modes whose controls can be read.
+pub trait Readable: sealed::Access {}
+
+/// Access modes whose controls can be written.
+pub trait Writable: sealed::Access {}
+
+impl Readable for ReadOnly {}
+impl Readable for ReadWrite {}
+impl Writable for WriteOnly {}
+impl Writable for ReadWrite {}
+
+impl<T, A> Control<T, A> {
+	/// Describes the control `name`.
+	///
+	/// # Safety
+	///
+	/// `name` must be the complete name of a control whose C value type is
+	/// exactly `T`, including its size, alignment, and validity requirements,
+	/// and which supports every operation of access mode `A`. Reading and
+	/// writing any valid `T` must be memory-safe with no further precondition,
+	/// so controls whose values are pointers with pointee or lifetime rules,
+	/// and controls such as `arena.<i>.name` that write through their output,
+	/// must not be described.
+	#[must_use]
+	pub const unsafe fn new(name: &'static str) -> Self {
+		Self {
+			name,
+			key: Cache::new(name),
+			marker: PhantomData,
+		}
+	}
+
+	/// Returns the control name.
+	#[must_use]
+	pub const fn name(&self) -> &'static str { self.name }
+
+	/// Returns the cached MIB, translating the name on first use.
+	///
+	/// # Errors
+	///
+	/// Returns an error if jemalloc does not recognize the name.
+	pub fn key(&'static self) -> Result<Key> { self.key.get() }
+}
+
+impl<T, A> fmt::Debug for Control<T, A> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Control")
+			.field("name", &self.name)
+			.finish_non_exhaustive()
+	}
+}
+
+impl<T: Copy, A: Readable> Control<T, A> {
+	/// Reads the current value.
+	///
+	/// # Errors
+	///
+	/// Returns an error if jemalloc does not recognize the name or rejects the
+	/// read, such as `EINVAL` when its value size differs from `T`.
+	pub fn get(&'static self) -> Result<T> {
+		let key = self.key()?;
+
+		// SAFETY: the descriptor's constructor guarantees that the name is a
+		// readable control whose C output type is exactly `T`.
+		unsafe { raw::get(&key) }
+			.map_err(|error| self.key.attribute(error, Operation::Read, &key))
+	}
+}
+
+impl<T: Copy, A: Writable> Control<T, A> {
+	/// Writes `value`.
+	///
+	/// # Errors
+	///
+	/// Returns an error if jemalloc does not recognize the name or rejects the
+	/// value.
+	pub fn set(&'static self, value: T) -> Result {
+		let key = self.key()?;
+
+		// SAFETY: the descriptor's constructor guarantees that the name is a
+		// writable control whose C input type is exactly `T`, and that any
+		// valid `T` is accepted without further preconditions.
+		unsafe { raw::set(&key, &value) }
+			.map_err(|error| self.key.attribute(error, Operation::Write, &key))
+	}
+}
+
+impl<T: Copy> Control<T, ReadWrite> {
+	/// Writes `value` and returns the previous value.
+	///
+	/// # Errors
+	///
+	/// Returns an error if jemalloc does not recognize the name or rejects the
+	/// value.
+	pub fn xchg(&'static self, value: T) -> Result<T> {
+		let key = self.key()?;
+
+		// SAFETY: the descriptor's constructor guarantees that the name is a
+		// read-write control whose C input and output types are exactly `T`.
+		unsafe { raw::xchg(&key, &value) }.map_err(|error| {
+			self.key
+				.attribute(error, Operation::Exchange, &key)
+		})
+	}
+}
+
+/// Defines static [`Control`](crate::ctl::Control) descriptors.
+///
+/// Each definition names a control, its C value type, and its access mode:
+/// [`ReadOnly`](crate::ctl::ReadOnly), [`WriteOnly`](crate::ctl::WriteOnly), or
+/// [`ReadWrite`](crate::ctl::ReadWrite). The `unsafe` keyword marks the
+/// assertion that the type and mode satisfy the contract of
+/// [`Control::new`](crate::ctl::Control::new); every later access is safe.
+///
+/// ```no_run
+/// use jevmalloc::ctl::{ReadOnly, ReadWrite};
+///
+/// jevmalloc::control!(unsafe static NARENAS: u32, ReadOnly = "opt.narenas");
+/// jevmalloc::control!(unsafe static TCACHE: bool, ReadWrite = "thread.tcache.enabled");
+///
+/// let narenas = NARENAS.get()?;
+/// let was_enabled = TCACHE.xchg(false)?;
+/// # Ok::<(), jevmalloc::ctl::Error>(())
+/// ```
+///
+/// The access mode is enforced at compile time:
+///
+/// ```compile_fail
+/// use jevmalloc::ctl::ReadOnly;
+///
+/// jevmalloc::control!(unsafe static NARENAS: u32, ReadOnly = "opt.narenas");
+///
+/// NARENAS.set(4).unwrap();
+/// ```
+#[macro_export]
+macro_rules! control {
+	($(
+		$(#[$meta:meta])*
+		$vis:vis unsafe static $id:ident: $value:ty, $access:ty = $name:literal
+	);* $(;)?) => {$(
+		$(#[$meta])*
+		$vis static $id: $crate::ctl::Control<$value, $access> =
+			// SAFETY: the definition's `unsafe` keyword asserts the contract of
+			// `Control::new` for this name, type, and access mode.
+			unsafe { $crate::ctl::Control::new($name) };
+	)*};
+}
+
+/// Seals the access-mode traits.
+mod sealed {
+	/// Implemented only by this crate's access modes.
+	pub trait Access {}
+
+	impl Access for super::ReadOnly {}
+	impl Access for super::WriteOnly {}
+	impl Access for super::ReadWrite {}
+}
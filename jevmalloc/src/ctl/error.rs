@@ -1,23 +1,104 @@
 //! Error reporting for jemalloc control operations.
 
-use core::{error, fmt, num::NonZeroI32};
+use core::{error, fmt, num::NonZeroI32, ptr};
 
-use libc::c_int;
+use libc::{c_int, c_uint};
+
+use super::key::Cache;
+
+/// Bits of a packed [`Context`] holding the [`Operation`].
+const OPERATION_BITS: u32 = 0b111;
+
+/// Bit of a packed [`Context`] set when the first index was recorded from a
+/// written value rather than the MIB.
+const WRITTEN_INDEX: u32 = 1 << 3;
+
+/// Bit offsets of the two packed [`Context`] indices.
+const INDEX_SHIFTS: [u32; 2] = [4, 18];
+
+/// Largest recordable index; larger ones saturate to it.
+const INDEX_MAX: usize = (1 << 14) - 1;
+
+/// [`INDEX_MAX`] as a packed field mask.
+const INDEX_MAX_BITS: u32 = (1 << 14) - 1;
 
 /// A nonzero errno-style allocator operation error.
 ///
 /// Statuses returned by jemalloc retain their errno values. Wrapper-side
 /// failures use the same representation, so callers can distinguish invalid
 /// input, insufficient storage, and invalid text without a panic.
-#[repr(transparent)]
-#[derive(Copy, Clone, PartialEq, Eq)]
-pub struct Error(NonZeroI32);
+///
+/// A status for a key that selects one of the crate's built-in controls, or
+/// from a [`Control`](super::Control) descriptor, also records its
+/// [`Context`]: the control name with its arena or tcache index, and the
+/// operation that failed. The message then reads like
+/// `arena.7.dss write: Invalid argument (errno 22)`. Attribution depends only
+/// on the key, so keys from [`KeyTemplate`](super::KeyTemplate) and
+/// [`raw::mibs`](super::raw::mibs) that select a built-in control are
+/// attributed too.
+///
+/// The error stays small: the errno, a packed operation and index pair, and
+/// one pointer to the control's name.
+#[derive(Copy, Clone)]
+pub struct Error {
+	/// The errno value.
+	code: NonZeroI32,
+
+	/// The packed operation and indices of `control`.
+	detail: u32,
+
+	/// The failing control, when known.
+	control: Option<&'static Cache>,
+}
+
+/// The control and operation that produced an [`Error`].
+///
+/// Up to two indices are recorded, each saturated to 16383.
+#[derive(Copy, Clone)]
+pub struct Context {
+	/// The failing control.
+	control: &'static Cache,
+
+	/// The operation in the low bits, then the [`WRITTEN_INDEX`] flag and the
+	/// indices at [`INDEX_SHIFTS`].
+	detail: u32,
+}
+
+/// A kind of control operation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Operation {
+	/// Translation of the control name into a MIB.
+	Resolve,
+
+	/// A read of the control's value.
+	Read,
+
+	/// A write of a new value.
+	Write,
+
+	/// A write that also returns a value.
+	Exchange,
+
+	/// A command without input or output values.
+	Notify,
+}
 
 impl Error {
 	/// Returns the numeric errno value.
 	#[must_use]
 	#[inline]
-	pub const fn code(self) -> c_int { self.0.get() }
+	pub const fn code(self) -> c_int { self.code.get() }
+
+	/// Returns the failing control and operation, if the error came from a
+	/// control this crate has resolved.
+	#[must_use]
+	#[inline]
+	pub const fn context(self) -> Option<Context> {
+		match self.control {
+			| Some(control) => Some(Context { control, detail: self.detail }),
+			| None => None,
+		}
+	}
 
 	/// Reports whether this error has the supplied errno value.
 	#[must_use]
@@ -36,22 +117,78 @@ impl Error {
 	#[inline]
 	pub(crate) fn insufficient_space() -> Self { Self::from_code(libc::ENOSPC) }
 
+	/// Constructs the wrapper's exclusive-resource error.
+	#[cfg(any(feature = "profiling", target_os = "linux"))]
+	#[inline]
+	pub(crate) fn busy() -> Self { Self::from_code(libc::EBUSY) }
+
+	/// Constructs the wrapper's temporarily-unavailable error.
+	#[cfg(all(feature = "std", feature = "stats"))]
+	#[inline]
+	pub(crate) fn try_again() -> Self { Self::from_code(libc::EAGAIN) }
+
+	/// Constructs the wrapper's unavailable-control error.
+	#[cfg(not(feature = "stats"))]
+	#[inline]
+	pub(crate) fn not_found() -> Self { Self::from_code(libc::ENOENT) }
+
 	/// Constructs the wrapper's invalid-UTF-8 error.
 	#[inline]
 	pub(crate) fn invalid_utf8() -> Self { Self::from_code(libc::EILSEQ) }
 
+	/// Constructs an error from the calling thread's `errno` after a failed
+	/// system call.
+	#[cfg(target_os = "linux")]
+	#[inline]
+	pub(crate) fn last_os_error() -> Self {
+		// SAFETY: `__errno_location` has no preconditions.
+		let slot = unsafe { libc::__errno_location() };
+
+		// SAFETY: the slot is the calling thread's live, aligned errno.
+		let code = unsafe { *slot };
+
+		Self::from_code(if code == 0 { libc::EIO } else { code })
+	}
+
 	/// Constructs an error from a status known to be nonzero.
 	#[inline]
 	pub(super) fn from_code(code: c_int) -> Self {
-		Self(NonZeroI32::new(code).expect("jemalloc error status must be nonzero"))
+		Self {
+			code: NonZeroI32::new(code).expect("jemalloc error status must be nonzero"),
+			detail: 0,
+			control: None,
+		}
+	}
+
+	/// Attaches the failing control and operation.
+	#[inline]
+	pub(super) const fn with_context(mut self, context: Context) -> Self {
+		self.control = Some(context.control);
+		self.detail = context.detail;
+		self
+	}
+
+	/// Records `index`, a value written to the failing control, as its index,
+	/// as for the tcache identifier of `tcache.flush`.
+	#[inline]
+	pub(crate) fn with_index(mut self, index: c_uint) -> Self {
+		if self.control.is_some() {
+			let index = usize::try_from(index).unwrap_or(INDEX_MAX);
+			self.detail = (self.detail & !(INDEX_MAX_BITS << INDEX_SHIFTS[0]))
+				| WRITTEN_INDEX
+				| pack_index(index, INDEX_SHIFTS[0]);
+		}
+
+		self
 	}
 
 	/// Returns the standard description for a recognized status.
 	fn description(self) -> Option<&'static str> {
-		use libc::{EAGAIN, EFAULT, EILSEQ, EINVAL, ENOENT, ENOSPC, EPERM};
+		use libc::{EAGAIN, EBUSY, EFAULT, EILSEQ, EINVAL, ENOENT, ENOSPC, EPERM};
 
 		match self.code() {
 			| EAGAIN => Some("Resource temporarily unavailable"),
+			| EBUSY => Some("Resource busy"),
 			| EFAULT => Some("Bad address"),
 			| EILSEQ => Some("Invalid byte sequence"),
 			| EINVAL => Some("Invalid argument"),
@@ -63,8 +200,142 @@ impl Error {
 	}
 }
 
+impl Context {
+	/// Records a failed `operation` on `control`, taking the index values of
+	/// its numeric components from `key`.
+	pub(super) fn new(control: &'static Cache, operation: Operation, key: &[usize]) -> Self {
+		let numeric = control
+			.name()
+			.split('.')
+			.zip(key)
+			.filter(|(component, _)| is_index(component));
+		let detail = numeric
+			.zip(INDEX_SHIFTS)
+			.fold(operation as u32, |detail, ((_, &value), shift)| {
+				detail | pack_index(value, shift)
+			});
+
+		Self { control, detail }
+	}
+
+	/// Returns the control name as resolved, with zero in each index
+	/// component. [`Display`](fmt::Display) shows the actual indices.
+	#[must_use]
+	#[inline]
+	pub fn name(self) -> &'static str { self.control.name() }
+
+	/// Returns the failed operation.
+	#[must_use]
+	#[inline]
+	pub const fn operation(self) -> Operation {
+		match self.detail & OPERATION_BITS {
+			| 0 => Operation::Resolve,
+			| 1 => Operation::Read,
+			| 2 => Operation::Write,
+			| 3 => Operation::Exchange,
+			| _ => Operation::Notify,
+		}
+	}
+
+	/// Returns the first index, such as the arena of `arena.<i>.dss` or the
+	/// tcache identifier written to `tcache.flush` and `tcache.destroy`.
+	#[must_use]
+	pub fn index(self) -> Option<usize> {
+		(self.detail & WRITTEN_INDEX != 0 || self.name().split('.').any(is_index))
+			.then(|| self.indices()[0])
+	}
+
+	/// Unpacks both recorded indices.
+	fn indices(self) -> [usize; 2] {
+		INDEX_SHIFTS.map(|shift| ((self.detail >> shift) & INDEX_MAX_BITS) as usize)
+	}
+}
+
+impl Operation {
+	/// Returns the lowercase operation name.
+	#[must_use]
+	pub const fn as_str(self) -> &'static str {
+		match self {
+			| Self::Resolve => "resolve",
+			| Self::Read => "read",
+			| Self::Write => "write",
+			| Self::Exchange => "exchange",
+			| Self::Notify => "notify",
+		}
+	}
+}
+
+/// Packs `index`, saturated to [`INDEX_MAX`], at bit offset `shift`.
+fn pack_index(index: usize, shift: u32) -> u32 {
+	u32::try_from(index.min(INDEX_MAX)).unwrap_or(INDEX_MAX_BITS) << shift
+}
+
+/// Reports whether a name component is a numeric index.
+fn is_index(component: &str) -> bool {
+	!component.is_empty()
+		&& component
+			.bytes()
+			.all(|byte| byte.is_ascii_digit())
+}
+
 impl error::Error for Error {}
 
+impl PartialEq for Error {
+	fn eq(&self, other: &Self) -> bool {
+		self.code == other.code && self.context() == other.context()
+	}
+}
+
+impl Eq for Error {}
+
+impl PartialEq for Context {
+	fn eq(&self, other: &Self) -> bool {
+		ptr::eq(self.control, other.control) && self.detail == other.detail
+	}
+}
+
+impl Eq for Context {}
+
+impl fmt::Debug for Context {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Context")
+			.field("name", &self.name())
+			.field("operation", &self.operation())
+			.field("index", &self.index())
+			.finish()
+	}
+}
+
+impl fmt::Display for Context {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut indices = self.indices().into_iter();
+		for (position, component) in self.name().split('.').enumerate() {
+			if position > 0 {
+				f.write_str(".")?;
+			}
+
+			match is_index(component)
+				.then(|| indices.next())
+				.flatten()
+			{
+				| Some(index) => write!(f, "{index}")?,
+				| None => f.write_str(component)?,
+			}
+		}
+
+		write!(f, " {}", self.operation())?;
+		if self.detail & WRITTEN_INDEX != 0 {
+			write!(f, " of {}", self.indices()[0])?;
+		}
+
+		Ok(())
+	}
+}
+
+impl fmt::Display for Operation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(self.as_str()) }
+}
+
 impl fmt::Debug for Error {
 	#[inline]
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { fmt::Display::fmt(self, f) }
@@ -73,6 +344,10 @@ impl fmt::Debug for Error {
 impl fmt::Display for Error {
 	#[inline(never)]
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if let Some(context) = self.context() {
+			write!(f, "{context}: ")?;
+		}
+
 		match self.description() {
 			| Some(description) => write!(f, "{description} (errno {})", self.code()),
 			| None => write!(f, "Unknown jemalloc error (errno {})", self.code()),
@@ -90,13 +365,62 @@ mod tests {
 
 	use super::{super::Result, *};
 
-	/// Confirms that the nonzero code preserves the `Result` niche.
+	/// Confirms that the error stays an errno, a packed word, and one pointer,
+	/// and that the nonzero code preserves the `Result` niche.
 	#[test]
-	fn result_uses_the_error_niche() {
+	fn error_stays_compact() {
 		use core::mem::size_of;
 
+		assert_eq!(
+			size_of::<Error>(),
+			size_of::<c_int>() + size_of::<u32>() + size_of::<usize>()
+		);
+		assert!(size_of::<Error>() <= 16);
 		assert_eq!(size_of::<Result<()>>(), size_of::<Error>());
-		assert_eq!(size_of::<Error>(), size_of::<c_int>());
+	}
+
+	/// Renders the failing control with its actual indices.
+	#[test]
+	fn context_names_the_control() {
+		static DSS: Cache = Cache::new("arena.0.dss");
+		static NMALLOC: Cache = Cache::new("stats.arenas.0.bins.0.nmalloc");
+		static DUMP: Cache = Cache::new("prof.dump");
+
+		let key = [1, 7, 3];
+		let context = Context::new(&DSS, Operation::Write, &key);
+		let error = Error::from_code(libc::EINVAL).with_context(context);
+
+		assert!(error.is(libc::EINVAL));
+		assert_eq!(context.index(), Some(7));
+		assert_eq!(context.operation(), Operation::Write);
+		assert_eq!(error.to_string(), "arena.7.dss write: Invalid argument (errno 22)");
+
+		let context = Context::new(&NMALLOC, Operation::Read, &[1, 2, 4096, 3, 12, 5]);
+		assert_eq!(context.to_string(), "stats.arenas.4096.bins.12.nmalloc read");
+		assert_eq!(Context::new(&DUMP, Operation::Notify, &[4, 1]).index(), None);
+
+		let context = Context::new(&NMALLOC, Operation::Read, &[1, 2, 1 << 20, 3, 0, 5]);
+		assert_eq!(context.index(), Some(INDEX_MAX));
+	}
+
+	/// Records a written tcache identifier only on attributed errors.
+	#[test]
+	fn written_index_is_reported() {
+		static FLUSH: Cache = Cache::new("tcache.flush");
+
+		let context = Context::new(&FLUSH, Operation::Write, &[5, 1]);
+		let error = Error::from_code(libc::EFAULT)
+			.with_context(context)
+			.with_index(9);
+
+		assert_eq!(error.context().and_then(Context::index), Some(9));
+		assert_eq!(error.to_string(), "tcache.flush write of 9: Bad address (errno 14)");
+		assert_eq!(
+			Error::from_code(libc::EFAULT)
+				.with_index(9)
+				.context(),
+			None
+		);
 	}
 
 	/// Confirms that unknown statuses retain their numeric value in
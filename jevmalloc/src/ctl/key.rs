@@ -6,7 +6,7 @@ mod cache;
 #[cfg(test)]
 mod tests;
 
-use self::cache::Cache;
+pub(super) use self::cache::{Cache, describe};
 use super::{Error, Result, raw};
 
 /// A resolved jemalloc Management Information Base key.
@@ -21,153 +21,283 @@ pub const NAME_MAX: usize = 128;
 /// Number of inline numeric components reserved for a MIB key.
 pub const KEY_SEGS: usize = 8;
 
-/// Defines one process-wide cache and accessor for a fixed control name.
-macro_rules! define_key {
-	($accessor:ident, $name:literal) => {
-		#[doc = concat!("Returns the cached MIB for `", $name, "`.")]
-		pub(crate) fn $accessor() -> Result<Key> {
-			static KEY: Cache = Cache::new();
-			KEY.get($name)
+/// Defines one process-wide cache and accessor per fixed control name, and the
+/// registry of every built-in control.
+macro_rules! define_keys {
+	($(
+		$(#[$meta:meta])*
+		$accessor:ident => $name:literal;
+	)*) => {
+		$(
+			$(#[$meta])*
+			#[doc = concat!("Returns the cached MIB for `", $name, "`.")]
+			pub(crate) fn $accessor() -> Result<Key> { caches::$accessor().get() }
+		)*
+
+		/// The cache behind each accessor, under the accessor's name.
+		mod caches {
+			use super::Cache;
+
+			$(
+				$(#[$meta])*
+				#[doc = concat!("Returns the cache for `", $name, "`.")]
+				pub(super) fn $accessor() -> &'static Cache {
+					static KEY: Cache = Cache::new($name);
+					&KEY
+				}
+			)*
 		}
+
+		/// Every built-in control, in definition order.
+		pub(super) static BUILTINS: &[fn() -> &'static Cache] = &[$(
+			$(#[$meta])*
+			caches::$accessor,
+		)*];
 	};
 }
 
-define_key!(epoch, "epoch");
-define_key!(background_thread, "background_thread");
-
-define_key!(config_cache_oblivious, "config.cache_oblivious");
-define_key!(config_debug, "config.debug");
-define_key!(config_fill, "config.fill");
-define_key!(config_lazy_lock, "config.lazy_lock");
-define_key!(config_malloc_conf, "config.malloc_conf");
-define_key!(config_prof, "config.prof");
-define_key!(config_prof_libgcc, "config.prof_libgcc");
-define_key!(config_prof_libunwind, "config.prof_libunwind");
-define_key!(config_stats, "config.stats");
-define_key!(config_utrace, "config.utrace");
-define_key!(config_xmalloc, "config.xmalloc");
-
-define_key!(opt_abort, "opt.abort");
-define_key!(opt_confirm_conf, "opt.confirm_conf");
-define_key!(opt_abort_conf, "opt.abort_conf");
-define_key!(opt_cache_oblivious, "opt.cache_oblivious");
-define_key!(opt_metadata_thp, "opt.metadata_thp");
-define_key!(opt_trust_madvise, "opt.trust_madvise");
-define_key!(opt_retain, "opt.retain");
-define_key!(opt_dss, "opt.dss");
-define_key!(opt_narenas, "opt.narenas");
-define_key!(opt_oversize_threshold, "opt.oversize_threshold");
-define_key!(opt_percpu_arena, "opt.percpu_arena");
-define_key!(opt_background_thread, "opt.background_thread");
-define_key!(opt_max_background_threads, "opt.max_background_threads");
-define_key!(opt_dirty_decay_ms, "opt.dirty_decay_ms");
-define_key!(opt_muzzy_decay_ms, "opt.muzzy_decay_ms");
-define_key!(opt_lg_extent_max_active_fit, "opt.lg_extent_max_active_fit");
-define_key!(opt_stats_print, "opt.stats_print");
-define_key!(opt_stats_print_opts, "opt.stats_print_opts");
-define_key!(opt_stats_interval, "opt.stats_interval");
-define_key!(opt_stats_interval_opts, "opt.stats_interval_opts");
-define_key!(opt_junk, "opt.junk");
-define_key!(opt_zero, "opt.zero");
-define_key!(opt_utrace, "opt.utrace");
-define_key!(opt_xmalloc, "opt.xmalloc");
-define_key!(opt_tcache, "opt.tcache");
-define_key!(opt_tcache_max, "opt.tcache_max");
-define_key!(opt_thp, "opt.thp");
-define_key!(opt_prof_bt_max, "opt.prof_bt_max");
-define_key!(opt_prof, "opt.prof");
-define_key!(opt_prof_prefix, "opt.prof_prefix");
-define_key!(opt_prof_active, "opt.prof_active");
-define_key!(opt_prof_thread_active_init, "opt.prof_thread_active_init");
-define_key!(opt_lg_prof_sample, "opt.lg_prof_sample");
-define_key!(opt_prof_accum, "opt.prof_accum");
-define_key!(opt_prof_pid_namespace, "opt.prof_pid_namespace");
-define_key!(opt_lg_prof_interval, "opt.lg_prof_interval");
-define_key!(opt_prof_gdump, "opt.prof_gdump");
-define_key!(opt_prof_final, "opt.prof_final");
-define_key!(opt_prof_leak, "opt.prof_leak");
-define_key!(opt_prof_leak_error, "opt.prof_leak_error");
-define_key!(opt_zero_realloc, "opt.zero_realloc");
-define_key!(opt_debug_double_free_max_scan, "opt.debug_double_free_max_scan");
-define_key!(opt_disable_large_size_classes, "opt.disable_large_size_classes");
-define_key!(opt_process_madvise_max_batch, "opt.process_madvise_max_batch");
-
-define_key!(arena_purge, "arena.0.purge");
-define_key!(arena_decay, "arena.0.decay");
-define_key!(arena_reset, "arena.0.reset");
-define_key!(arena_destroy, "arena.0.destroy");
-define_key!(arena_name, "arena.0.name");
-define_key!(arena_dss, "arena.0.dss");
-define_key!(arena_muzzy_decay, "arena.0.muzzy_decay_ms");
-define_key!(arena_dirty_decay, "arena.0.dirty_decay_ms");
-define_key!(arena_retain_grow_limit, "arena.0.retain_grow_limit");
-define_key!(arena_extent_hooks, "arena.0.extent_hooks");
-define_key!(arenas_create, "arenas.create");
-define_key!(arenas_lookup, "arenas.lookup");
-define_key!(arenas_muzzy_decay, "arenas.muzzy_decay_ms");
-define_key!(arenas_dirty_decay, "arenas.dirty_decay_ms");
-define_key!(arenas_limit, "arenas.narenas");
-define_key!(arenas_quantum, "arenas.quantum");
-define_key!(thread_idle, "thread.idle");
-define_key!(thread_arena, "thread.arena");
-define_key!(thread_tcache_flush, "thread.tcache.flush");
-define_key!(thread_tcache_enabled, "thread.tcache.enabled");
-define_key!(thread_tcache_max, "thread.tcache.max");
-define_key!(
-	thread_tcache_ncached_max_read_sizeclass,
-	"thread.tcache.ncached_max.read_sizeclass"
-);
-define_key!(thread_tcache_ncached_max_write, "thread.tcache.ncached_max.write");
-define_key!(tcache_create, "tcache.create");
-define_key!(tcache_flush, "tcache.flush");
-define_key!(tcache_destroy, "tcache.destroy");
-
-#[cfg(feature = "profiling")]
-define_key!(prof_reset, "prof.reset");
-#[cfg(feature = "profiling")]
-define_key!(prof_dump, "prof.dump");
-#[cfg(feature = "profiling")]
-define_key!(prof_gdump, "prof.gdump");
-#[cfg(feature = "profiling")]
-define_key!(prof_active, "prof.active");
-#[cfg(feature = "profiling")]
-define_key!(prof_interval, "prof.interval");
-#[cfg(feature = "profiling")]
-define_key!(thread_prof_active, "thread.prof.active");
-
-#[cfg(feature = "stats")]
-define_key!(stats_mutexes_reset, "stats.mutexes.reset");
-#[cfg(feature = "stats")]
-define_key!(stats_allocated, "stats.allocated");
-#[cfg(feature = "stats")]
-define_key!(stats_active, "stats.active");
-#[cfg(feature = "stats")]
-define_key!(stats_metadata, "stats.metadata");
-#[cfg(feature = "stats")]
-define_key!(stats_metadata_thp, "stats.metadata_thp");
-#[cfg(feature = "stats")]
-define_key!(stats_resident, "stats.resident");
-#[cfg(feature = "stats")]
-define_key!(stats_mapped, "stats.mapped");
-#[cfg(feature = "stats")]
-define_key!(stats_retained, "stats.retained");
-#[cfg(feature = "stats")]
-define_key!(stats_zero_reallocs, "stats.zero_reallocs");
-#[cfg(feature = "stats")]
-define_key!(stats_background_thread_num_threads, "stats.background_thread.num_threads");
-#[cfg(feature = "stats")]
-define_key!(stats_background_thread_num_runs, "stats.background_thread.num_runs");
-#[cfg(feature = "stats")]
-define_key!(stats_background_thread_run_interval, "stats.background_thread.run_interval");
-#[cfg(feature = "stats")]
-define_key!(thread_peak_reset, "thread.peak.reset");
-#[cfg(feature = "stats")]
-define_key!(thread_peak_read, "thread.peak.read");
-#[cfg(feature = "stats")]
-define_key!(thread_allocated, "thread.allocated");
-#[cfg(feature = "stats")]
-define_key!(thread_deallocated, "thread.deallocated");
-#[cfg(feature = "stats")]
-define_key!(thread_allocatedp, "thread.allocatedp");
-#[cfg(feature = "stats")]
-define_key!(thread_deallocatedp, "thread.deallocatedp");
+define_keys! {
+	epoch => "epoch";
+	version => "version";
+	background_thread => "background_thread";
+
+	config_cache_oblivious => "config.cache_oblivious";
+	config_debug => "config.debug";
+	config_fill => "config.fill";
+	config_lazy_lock => "config.lazy_lock";
+	config_malloc_conf => "config.malloc_conf";
+	config_prof => "config.prof";
+	config_prof_libgcc => "config.prof_libgcc";
+	config_prof_libunwind => "config.prof_libunwind";
+	config_stats => "config.stats";
+	config_utrace => "config.utrace";
+	config_xmalloc => "config.xmalloc";
+
+	opt_abort => "opt.abort";
+	opt_confirm_conf => "opt.confirm_conf";
+	opt_abort_conf => "opt.abort_conf";
+	opt_cache_oblivious => "opt.cache_oblivious";
+	opt_metadata_thp => "opt.metadata_thp";
+	opt_trust_madvise => "opt.trust_madvise";
+	opt_retain => "opt.retain";
+	opt_dss => "opt.dss";
+	opt_narenas => "opt.narenas";
+	opt_oversize_threshold => "opt.oversize_threshold";
+	opt_percpu_arena => "opt.percpu_arena";
+	opt_background_thread => "opt.background_thread";
+	opt_max_background_threads => "opt.max_background_threads";
+	opt_dirty_decay_ms => "opt.dirty_decay_ms";
+	opt_muzzy_decay_ms => "opt.muzzy_decay_ms";
+	opt_lg_extent_max_active_fit => "opt.lg_extent_max_active_fit";
+	opt_stats_print => "opt.stats_print";
+	opt_stats_print_opts => "opt.stats_print_opts";
+	opt_stats_interval => "opt.stats_interval";
+	opt_stats_interval_opts => "opt.stats_interval_opts";
+	opt_junk => "opt.junk";
+	opt_zero => "opt.zero";
+	opt_utrace => "opt.utrace";
+	opt_xmalloc => "opt.xmalloc";
+	opt_tcache => "opt.tcache";
+	opt_tcache_max => "opt.tcache_max";
+	opt_thp => "opt.thp";
+	opt_prof_bt_max => "opt.prof_bt_max";
+	opt_prof => "opt.prof";
+	opt_prof_prefix => "opt.prof_prefix";
+	opt_prof_active => "opt.prof_active";
+	opt_prof_thread_active_init => "opt.prof_thread_active_init";
+	opt_lg_prof_sample => "opt.lg_prof_sample";
+	opt_prof_accum => "opt.prof_accum";
+	opt_prof_pid_namespace => "opt.prof_pid_namespace";
+	opt_lg_prof_interval => "opt.lg_prof_interval";
+	opt_prof_gdump => "opt.prof_gdump";
+	opt_prof_final => "opt.prof_final";
+	opt_prof_leak => "opt.prof_leak";
+	opt_prof_leak_error => "opt.prof_leak_error";
+	opt_prof_stats => "opt.prof_stats";
+	opt_zero_realloc => "opt.zero_realloc";
+	opt_debug_double_free_max_scan => "opt.debug_double_free_max_scan";
+	opt_disable_large_size_classes => "opt.disable_large_size_classes";
+	opt_process_madvise_max_batch => "opt.process_madvise_max_batch";
+	opt_mutex_max_spin => "opt.mutex_max_spin";
+	opt_remote_free_max => "opt.remote_free_max";
+	opt_tcache_gc_incr_bytes => "opt.tcache_gc_incr_bytes";
+	opt_tcache_gc_delay_bytes => "opt.tcache_gc_delay_bytes";
+	opt_lg_tcache_flush_small_div => "opt.lg_tcache_flush_small_div";
+	opt_lg_tcache_flush_large_div => "opt.lg_tcache_flush_large_div";
+	opt_lg_san_uaf_align => "opt.lg_san_uaf_align";
+	opt_san_guard_small => "opt.san_guard_small";
+	opt_san_guard_large => "opt.san_guard_large";
+	opt_prof_sys_thread_name => "opt.prof_sys_thread_name";
+	opt_prof_time_resolution => "opt.prof_time_resolution";
+	opt_experimental_infallible_new => "opt.experimental_infallible_new";
+	opt_hpa => "opt.hpa";
+	opt_hpa_slab_max_alloc => "opt.hpa_slab_max_alloc";
+	opt_hpa_hugification_threshold => "opt.hpa_hugification_threshold";
+	opt_hpa_hugify_delay_ms => "opt.hpa_hugify_delay_ms";
+	opt_hpa_min_purge_interval_ms => "opt.hpa_min_purge_interval_ms";
+	opt_hpa_dirty_mult => "opt.hpa_dirty_mult";
+	opt_hpa_sec_nshards => "opt.hpa_sec_nshards";
+	opt_hpa_sec_max_alloc => "opt.hpa_sec_max_alloc";
+	opt_hpa_sec_max_bytes => "opt.hpa_sec_max_bytes";
+	opt_hpa_sec_bytes_after_flush => "opt.hpa_sec_bytes_after_flush";
+	opt_hpa_sec_batch_fill_extra => "opt.hpa_sec_batch_fill_extra";
+
+	arena_purge => "arena.0.purge";
+	arena_decay => "arena.0.decay";
+	arena_reset => "arena.0.reset";
+	arena_destroy => "arena.0.destroy";
+	arena_name => "arena.0.name";
+	arena_dss => "arena.0.dss";
+	arena_muzzy_decay => "arena.0.muzzy_decay_ms";
+	arena_dirty_decay => "arena.0.dirty_decay_ms";
+	arena_retain_grow_limit => "arena.0.retain_grow_limit";
+	arena_extent_hooks => "arena.0.extent_hooks";
+	arenas_create => "arenas.create";
+	arenas_lookup => "arenas.lookup";
+	arenas_muzzy_decay => "arenas.muzzy_decay_ms";
+	arenas_dirty_decay => "arenas.dirty_decay_ms";
+	arenas_limit => "arenas.narenas";
+	arenas_quantum => "arenas.quantum";
+	arenas_page => "arenas.page";
+	arenas_tcache_max => "arenas.tcache_max";
+	arenas_nbins => "arenas.nbins";
+	arenas_nlextents => "arenas.nlextents";
+	arenas_bin_size => "arenas.bin.0.size";
+	arenas_lextent_size => "arenas.lextent.0.size";
+	thread_idle => "thread.idle";
+	thread_arena => "thread.arena";
+	thread_tcache_flush => "thread.tcache.flush";
+	thread_tcache_enabled => "thread.tcache.enabled";
+	thread_tcache_max => "thread.tcache.max";
+	thread_tcache_ncached_max_read_sizeclass => "thread.tcache.ncached_max.read_sizeclass";
+	thread_tcache_ncached_max_write => "thread.tcache.ncached_max.write";
+	tcache_create => "tcache.create";
+	tcache_flush => "tcache.flush";
+	tcache_destroy => "tcache.destroy";
+
+	#[cfg(feature = "profiling")]
+	prof_reset => "prof.reset";
+	#[cfg(feature = "profiling")]
+	prof_dump => "prof.dump";
+	#[cfg(feature = "profiling")]
+	prof_gdump => "prof.gdump";
+	#[cfg(feature = "profiling")]
+	prof_active => "prof.active";
+	#[cfg(feature = "profiling")]
+	prof_interval => "prof.interval";
+	#[cfg(feature = "profiling")]
+	thread_prof_active => "thread.prof.active";
+	#[cfg(feature = "profiling")]
+	experimental_hooks_prof_backtrace => "experimental.hooks.prof_backtrace";
+	#[cfg(feature = "profiling")]
+	experimental_hooks_prof_dump => "experimental.hooks.prof_dump";
+	#[cfg(feature = "profiling")]
+	experimental_hooks_prof_sample => "experimental.hooks.prof_sample";
+	#[cfg(feature = "profiling")]
+	experimental_hooks_prof_sample_free => "experimental.hooks.prof_sample_free";
+	#[cfg(feature = "profiling")]
+	prof_stats_bins_live => "prof.stats.bins.0.live";
+	#[cfg(feature = "profiling")]
+	prof_stats_bins_accum => "prof.stats.bins.0.accum";
+	#[cfg(feature = "profiling")]
+	prof_stats_lextents_live => "prof.stats.lextents.0.live";
+	#[cfg(feature = "profiling")]
+	prof_stats_lextents_accum => "prof.stats.lextents.0.accum";
+
+	#[cfg(feature = "stats")]
+	stats_mutexes_reset => "stats.mutexes.reset";
+	#[cfg(feature = "stats")]
+	stats_allocated => "stats.allocated";
+	#[cfg(feature = "stats")]
+	stats_active => "stats.active";
+	#[cfg(feature = "stats")]
+	stats_metadata => "stats.metadata";
+	#[cfg(feature = "stats")]
+	stats_metadata_thp => "stats.metadata_thp";
+	#[cfg(feature = "stats")]
+	stats_resident => "stats.resident";
+	#[cfg(feature = "stats")]
+	stats_mapped => "stats.mapped";
+	#[cfg(feature = "stats")]
+	stats_retained => "stats.retained";
+	#[cfg(feature = "stats")]
+	stats_zero_reallocs => "stats.zero_reallocs";
+	#[cfg(feature = "stats")]
+	stats_background_thread_num_threads => "stats.background_thread.num_threads";
+	#[cfg(feature = "stats")]
+	stats_background_thread_num_runs => "stats.background_thread.num_runs";
+	#[cfg(feature = "stats")]
+	stats_background_thread_run_interval => "stats.background_thread.run_interval";
+	#[cfg(feature = "stats")]
+	stats_arenas_uptime => "stats.arenas.0.uptime";
+	#[cfg(feature = "stats")]
+	stats_arenas_nthreads => "stats.arenas.0.nthreads";
+	#[cfg(feature = "stats")]
+	stats_arenas_pactive => "stats.arenas.0.pactive";
+	#[cfg(feature = "stats")]
+	stats_arenas_pdirty => "stats.arenas.0.pdirty";
+	#[cfg(feature = "stats")]
+	stats_arenas_pmuzzy => "stats.arenas.0.pmuzzy";
+	#[cfg(feature = "stats")]
+	stats_arenas_mapped => "stats.arenas.0.mapped";
+	#[cfg(feature = "stats")]
+	stats_arenas_retained => "stats.arenas.0.retained";
+	#[cfg(feature = "stats")]
+	stats_arenas_base => "stats.arenas.0.base";
+	#[cfg(feature = "stats")]
+	stats_arenas_internal => "stats.arenas.0.internal";
+	#[cfg(feature = "stats")]
+	stats_arenas_resident => "stats.arenas.0.resident";
+	#[cfg(feature = "stats")]
+	stats_arenas_tcache_bytes => "stats.arenas.0.tcache_bytes";
+	#[cfg(feature = "stats")]
+	stats_arenas_dirty_purged => "stats.arenas.0.dirty_purged";
+	#[cfg(feature = "stats")]
+	stats_arenas_muzzy_purged => "stats.arenas.0.muzzy_purged";
+	#[cfg(feature = "stats")]
+	stats_arenas_small_allocated => "stats.arenas.0.small.allocated";
+	#[cfg(feature = "stats")]
+	stats_arenas_small_nmalloc => "stats.arenas.0.small.nmalloc";
+	#[cfg(feature = "stats")]
+	stats_arenas_small_ndalloc => "stats.arenas.0.small.ndalloc";
+	#[cfg(feature = "stats")]
+	stats_arenas_small_nrequests => "stats.arenas.0.small.nrequests";
+	#[cfg(feature = "stats")]
+	stats_arenas_large_allocated => "stats.arenas.0.large.allocated";
+	#[cfg(feature = "stats")]
+	stats_arenas_large_nmalloc => "stats.arenas.0.large.nmalloc";
+	#[cfg(feature = "stats")]
+	stats_arenas_large_ndalloc => "stats.arenas.0.large.ndalloc";
+	#[cfg(feature = "stats")]
+	stats_arenas_large_nrequests => "stats.arenas.0.large.nrequests";
+	#[cfg(feature = "stats")]
+	stats_arenas_bins_nmalloc => "stats.arenas.0.bins.0.nmalloc";
+	#[cfg(feature = "stats")]
+	stats_arenas_bins_ndalloc => "stats.arenas.0.bins.0.ndalloc";
+	#[cfg(feature = "stats")]
+	stats_arenas_bins_nrequests => "stats.arenas.0.bins.0.nrequests";
+	#[cfg(feature = "stats")]
+	stats_arenas_bins_curregs => "stats.arenas.0.bins.0.curregs";
+	#[cfg(feature = "stats")]
+	stats_arenas_bins_curslabs => "stats.arenas.0.bins.0.curslabs";
+	#[cfg(feature = "stats")]
+	stats_arenas_lextents_nmalloc => "stats.arenas.0.lextents.0.nmalloc";
+	#[cfg(feature = "stats")]
+	stats_arenas_lextents_ndalloc => "stats.arenas.0.lextents.0.ndalloc";
+	#[cfg(feature = "stats")]
+	stats_arenas_lextents_nrequests => "stats.arenas.0.lextents.0.nrequests";
+	#[cfg(feature = "stats")]
+	stats_arenas_lextents_curlextents => "stats.arenas.0.lextents.0.curlextents";
+	#[cfg(feature = "stats")]
+	thread_peak_reset => "thread.peak.reset";
+	#[cfg(feature = "stats")]
+	thread_peak_read => "thread.peak.read";
+	#[cfg(feature = "stats")]
+	thread_allocated => "thread.allocated";
+	#[cfg(feature = "stats")]
+	thread_deallocated => "thread.deallocated";
+	#[cfg(feature = "stats")]
+	thread_allocatedp => "thread.allocatedp";
+	#[cfg(feature = "stats")]
+	thread_deallocatedp => "thread.deallocatedp";
+}
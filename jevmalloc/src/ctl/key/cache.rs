@@ -1,15 +1,24 @@
 use core::sync::atomic::{AtomicUsize, Ordering};
 
-use super::{Error, KEY_SEGS, Key, Result, raw};
+use super::{BUILTINS, Error, KEY_SEGS, Key, Result, raw};
+use crate::ctl::{Context, Operation};
+
+/// Length recorded for a name the linked jemalloc does not define.
+const MISSING: usize = usize::MAX;
 
 /// Caches one fixed control name as an inline MIB.
 ///
-/// A zero length means unpublished. Initializers write every segment before a
-/// release store publishes the nonzero length. Concurrent first callers may
-/// translate the same immutable name more than once, but all segment writes are
-/// atomic and identical, so no caller blocks or observes a partial key.
-pub(super) struct Cache {
-	/// Published key length, or zero before successful translation.
+/// A zero length means unpublished, and [`MISSING`] records a name that failed
+/// to translate with `ENOENT`, so it is not translated again. Initializers
+/// write every segment before a release store publishes the nonzero length.
+/// Concurrent first callers may translate the same immutable name more than
+/// once, but all segment writes are atomic and identical, so no caller blocks
+/// or observes a partial key.
+pub(in crate::ctl) struct Cache {
+	/// The control name.
+	name: &'static str,
+
+	/// Published key length, zero before translation, or [`MISSING`].
 	len: AtomicUsize,
 
 	/// Numeric MIB components.
@@ -18,21 +27,36 @@ pub(super) struct Cache {
 
 impl Cache {
 	/// Constructs an empty cache for a static control name.
-	pub(super) const fn new() -> Self {
+	pub(in crate::ctl) const fn new(name: &'static str) -> Self {
 		Self {
+			name,
 			len: AtomicUsize::new(0),
 			segments: [const { AtomicUsize::new(0) }; KEY_SEGS],
 		}
 	}
 
-	/// Returns the cached MIB, translating `name` on the first successful call.
-	pub(super) fn get(&self, name: &str) -> Result<Key> {
-		let len = self.len.load(Ordering::Acquire);
-		if len > 0 {
-			return self.load(len);
+	/// Returns the control name.
+	pub(in crate::ctl) const fn name(&self) -> &'static str { self.name }
+
+	/// Returns the cached MIB, translating the name on the first call that
+	/// succeeds or finds the name missing.
+	pub(in crate::ctl) fn get(&'static self) -> Result<Key> {
+		match self.len.load(Ordering::Acquire) {
+			| 0 => {},
+			| MISSING => {
+				let error = Error::from_code(libc::ENOENT);
+				return Err(self.attribute(error, Operation::Resolve, &[]));
+			},
+			| len => return self.load(len),
 		}
 
-		let key = raw::mibs(name)?;
+		let key = raw::mibs(self.name).map_err(|error| {
+			if error.is(libc::ENOENT) {
+				self.len.store(MISSING, Ordering::Release);
+			}
+
+			self.attribute(error, Operation::Resolve, &[])
+		})?;
 		for (slot, segment) in self.segments.iter().zip(key.iter().copied()) {
 			slot.store(segment, Ordering::Relaxed);
 		}
@@ -42,6 +66,16 @@ impl Cache {
 		Ok(key)
 	}
 
+	/// Attributes `error`, from `operation` on `key`, to this control.
+	pub(in crate::ctl) fn attribute(
+		&'static self,
+		error: Error,
+		operation: Operation,
+		key: &[usize],
+	) -> Error {
+		error.with_context(Context::new(self, operation, key))
+	}
+
 	/// Copies a published MIB out of the atomic storage.
 	fn load(&self, len: usize) -> Result<Key> {
 		let mut key = Key::new();
@@ -52,4 +86,33 @@ impl Cache {
 
 		Ok(key)
 	}
+
+	/// Reports whether `key` selects this control, allowing any value in the
+	/// name's numeric index components.
+	fn matches(&'static self, key: &[usize]) -> bool {
+		let Ok(own) = self.get() else {
+			return false;
+		};
+
+		own.len() == key.len()
+			&& self.name.split('.').zip(own.iter().zip(key)).all(
+				|(component, (segment, value))| {
+					component
+						.bytes()
+						.all(|byte| byte.is_ascii_digit())
+						|| segment == value
+				},
+			)
+	}
+}
+
+/// Returns the built-in control that `key` selects, if any.
+///
+/// Every built-in name is translated on first use, so the answer depends only
+/// on `key` and never on which controls have been used before.
+pub(in crate::ctl) fn describe(key: &[usize]) -> Option<&'static Cache> {
+	BUILTINS
+		.iter()
+		.map(|cache| cache())
+		.find(|cache| cache.matches(key))
 }
@@ -1,6 +1,6 @@
 #![cfg(test)]
 
-//! Checks publication and reuse of a cached key.
+//! Checks publication and reuse of a cached key, and the built-in names.
 
 extern crate std as rust_std;
 
@@ -12,26 +12,53 @@ use super::*;
 /// Confirms that the published MIB matches a fresh translation.
 #[test]
 fn cached_key_matches_translation() {
-	static CACHE: Cache = Cache::new();
+	static CACHE: Cache = Cache::new("epoch");
 
-	let first = CACHE.get("epoch").unwrap();
-	let second = CACHE.get("epoch").unwrap();
+	let first = CACHE.get().unwrap();
+	let second = CACHE.get().unwrap();
 
 	assert_eq!(first, raw::mibs("epoch").unwrap());
 	assert_eq!(second, first);
 }
 
+/// Remembers that a name does not resolve and keeps reporting `ENOENT`.
+#[test]
+fn missing_name_is_remembered() {
+	static CACHE: Cache = Cache::new("no_such_control");
+
+	for _ in 0..2 {
+		let error = CACHE.get().unwrap_err();
+		assert!(error.is(libc::ENOENT));
+		assert_eq!(error.context().map(crate::ctl::Context::name), Some("no_such_control"));
+	}
+}
+
+/// Confirms that every built-in option name resolves in the linked jemalloc,
+/// so an `opt` getter whose key is misspelled or missing from the library
+/// fails here by name.
+#[test]
+fn builtin_option_names_resolve() {
+	let names = BUILTINS
+		.iter()
+		.map(|cache| cache().name())
+		.filter(|name| name.starts_with("opt."));
+
+	for name in names {
+		raw::mibs(name).unwrap_or_else(|error| panic!("`{name}` does not resolve: {error}"));
+	}
+}
+
 /// Exercises concurrent first publication of one immutable key.
 #[test]
 fn publishes_concurrently() {
-	static CACHE: Cache = Cache::new();
+	static CACHE: Cache = Cache::new("epoch");
 	static START: Barrier = Barrier::new(4);
 
 	thread::scope(|scope| {
 		for _ in 0..4 {
 			scope.spawn(|| {
 				START.wait();
-				assert_eq!(CACHE.get("epoch").unwrap(), raw::mibs("epoch").unwrap());
+				assert_eq!(CACHE.get().unwrap(), raw::mibs("epoch").unwrap());
 			});
 		}
 	});
@@ -1,21 +1,33 @@
 //! MIB access primitives for jemalloc's control interface.
 //!
 //! [`Key`] stores a translated Management Information Base path, and
-//! [`raw::mibs`] resolves an ad hoc control name. Typed controls elsewhere in
-//! the crate cache their built-in keys process-wide, then operate through
-//! `mallctlbymib`. The generic operations in [`raw`] are unsafe because a MIB
-//! carries neither type information nor a command's semantic preconditions.
+//! [`raw::mibs`] resolves an ad hoc control name. A [`KeyTemplate`] resolves a
+//! name with index placeholders once and fills in checked indices, and
+//! [`walk`] reads every known readable control in turn. Typed
+//! controls elsewhere in the crate cache their built-in keys process-wide,
+//! then operate through `mallctlbymib`. The generic operations in [`raw`] are
+//! unsafe because a MIB carries neither type information nor a command's
+//! semantic preconditions. [`Control`] descriptors, defined with
+//! [`control!`](crate::control), attach a C value type and access mode to a
+//! name once so that later access is safe.
 
+mod control;
 mod error;
 #[expect(clippy::redundant_pub_crate)]
 pub(crate) mod key;
 pub mod raw;
+mod template;
 #[expect(clippy::redundant_pub_crate)]
 pub(crate) mod value;
+#[expect(clippy::redundant_pub_crate)]
+pub(crate) mod walk;
 
 pub use self::{
-	error::Error,
+	control::{Control, ReadOnly, ReadWrite, Readable, Writable, WriteOnly},
+	error::{Context, Error, Operation},
 	key::{KEY_SEGS, Key, NAME_MAX},
+	template::KeyTemplate,
+	walk::{Name, Value, Walk, walk},
 };
 
 /// The result of a jemalloc control operation.
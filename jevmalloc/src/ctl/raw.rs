@@ -12,7 +12,7 @@ use core::{
 
 use libc::{c_char, c_int, c_void, size_t};
 
-use super::{Error, KEY_SEGS, Key, NAME_MAX, Result};
+use super::{Error, KEY_SEGS, Key, NAME_MAX, Operation, Result, key};
 use crate::ffi;
 
 /// Resolves a jemalloc control name into a reusable MIB key.
@@ -110,7 +110,7 @@ pub unsafe fn get<T: Copy>(key: &Key) -> Result<T> {
 		)
 	};
 
-	into_result(status)?;
+	check(status, key, Operation::Read)?;
 
 	assert_eq!(value_len, expected, "jemalloc returned an unexpected control value size");
 
@@ -159,7 +159,7 @@ pub unsafe fn get_in_place<T: Copy>(key: &Key, value: &mut T) -> Result {
 		)
 	};
 
-	into_result(status)?;
+	check(status, key, Operation::Read)?;
 
 	assert_eq!(value_len, expected, "jemalloc returned an unexpected control value size");
 
@@ -202,7 +202,7 @@ pub unsafe fn set<T>(key: &Key, value: &T) -> Result {
 		)
 	};
 
-	into_result(status)
+	check(status, key, Operation::Write)
 }
 
 /// Supplies a new value to `key` and returns the control-defined output.
@@ -280,7 +280,7 @@ pub unsafe fn update<I, O: Copy>(key: &Key, value: &I) -> Result<O> {
 		)
 	};
 
-	into_result(status)?;
+	check(status, key, Operation::Exchange)?;
 
 	assert_eq!(output_len, expected, "jemalloc returned an unexpected control value size");
 
@@ -312,7 +312,7 @@ pub unsafe fn notify(key: &Key) -> Result {
 		ffi::mallctlbymib(key.as_ptr(), key.len(), null_mut(), null_mut(), null_mut(), 0)
 	};
 
-	into_result(status)
+	check(status, key, Operation::Notify)
 }
 
 /// Converts a jemalloc return status into a control result.
@@ -324,6 +324,27 @@ fn into_result(code: c_int) -> Result {
 	}
 }
 
+/// Converts the status of an operation on `key` into a control result,
+/// attributing a failure to the control `key` selects when this crate has
+/// resolved it.
+#[inline]
+fn check(code: c_int, key: &Key, operation: Operation) -> Result {
+	match code {
+		| 0 => Ok(()),
+		| code => Err(failure(code, key, operation)),
+	}
+}
+
+/// Builds the error for a failed operation on `key`.
+#[cold]
+fn failure(code: c_int, key: &Key, operation: Operation) -> Error {
+	let error = Error::from_code(code);
+	match key::describe(key) {
+		| Some(control) => control.attribute(error, operation, key),
+		| None => error,
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	//! Checks name validation before the FFI boundary.
@@ -0,0 +1,161 @@
+//! Resolved MIBs for control names with index placeholders.
+
+use arrayvec::ArrayVec;
+
+use super::{Error, KEY_SEGS, Key, NAME_MAX, Result, raw};
+use crate::{
+	arena::{ALL_ARENAS, ARENA_INDEX_LIMIT, DESTROYED_ARENAS},
+	arenas,
+};
+
+/// A control name resolved once, with index placeholders left open.
+///
+/// A placeholder is a whole name component in angle brackets, as in
+/// `stats.arenas.<i>.bins.<j>.nmalloc`. [`KeyTemplate::new`] translates the
+/// name once and records the valid range of each placeholder from the
+/// component before it:
+///
+/// - after `arena`: an ordinary arena index below [`ARENA_INDEX_LIMIT`], or
+///   [`ALL_ARENAS`];
+/// - after `arenas`: the same, or [`DESTROYED_ARENAS`];
+/// - after `bin` or `bins`: a small size class below [`arenas::nbins`];
+/// - after `lextent` or `lextents`: a large size class below
+///   [`arenas::nlextents`].
+///
+/// [`KeyTemplate::key`] then validates concrete indices and fills them in
+/// without another name translation.
+///
+/// ```
+/// use jevmalloc::ctl::KeyTemplate;
+///
+/// let nmalloc = KeyTemplate::new("stats.arenas.<i>.bins.<j>.nmalloc")?;
+/// let key = nmalloc.key(&[0, 3])?;
+///
+/// assert_eq!((key[2], key[4]), (0, 3));
+/// assert!(nmalloc.key(&[0, usize::MAX]).is_err());
+/// # Ok::<(), jevmalloc::ctl::Error>(())
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyTemplate {
+	/// The MIB with every placeholder resolved as zero.
+	key: Key,
+
+	/// The open components, in name order.
+	slots: ArrayVec<Slot, KEY_SEGS>,
+}
+
+/// One open MIB component and its accepted indices.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Slot {
+	/// Position of the component within the MIB.
+	position: usize,
+
+	/// Exclusive upper bound of ordinary indices.
+	limit: usize,
+
+	/// Sentinel indices accepted in addition to the ordinary range.
+	sentinels: &'static [usize],
+}
+
+impl KeyTemplate {
+	/// Resolves `name`, leaving each `<…>` component open.
+	///
+	/// # Errors
+	///
+	/// Returns `EINVAL` if the name is invalid for [`raw::mibs`] or a
+	/// placeholder follows a component with no known index range, or another
+	/// error if jemalloc does not recognize the name or rejects a size-class
+	/// count query.
+	pub fn new(name: &str) -> Result<Self> {
+		let mut buf = [0_u8; NAME_MAX];
+		let mut len = 0;
+		let mut slots = ArrayVec::new();
+		let mut previous = "";
+
+		for (position, component) in name.split('.').enumerate() {
+			let placeholder =
+				component.len() > 2 && component.starts_with('<') && component.ends_with('>');
+			let text = if placeholder {
+				slots
+					.try_push(Slot::after(previous, position)?)
+					.map_err(|_| Error::invalid_argument())?;
+				"0"
+			} else {
+				component
+			};
+
+			let separator = usize::from(position > 0);
+			let end = len + separator + text.len();
+			if end >= NAME_MAX {
+				return Err(Error::invalid_argument());
+			}
+			if separator > 0 {
+				buf[len] = b'.';
+			}
+			buf[len + separator..end].copy_from_slice(text.as_bytes());
+			len = end;
+			previous = component;
+		}
+
+		let resolved = core::str::from_utf8(&buf[..len]).map_err(|_| Error::invalid_utf8())?;
+		let key = raw::mibs(resolved)?;
+
+		Ok(Self { key, slots })
+	}
+
+	/// Returns the number of placeholders.
+	#[must_use]
+	pub fn arity(&self) -> usize { self.slots.len() }
+
+	/// Returns the exclusive upper bound of ordinary indices at `placeholder`,
+	/// or `None` if the template has fewer placeholders.
+	///
+	/// Sentinels such as [`ALL_ARENAS`] are accepted beyond this bound.
+	#[must_use]
+	pub fn limit(&self, placeholder: usize) -> Option<usize> {
+		self.slots.get(placeholder).map(|slot| slot.limit)
+	}
+
+	/// Returns the MIB for one index per placeholder, in name order.
+	///
+	/// # Errors
+	///
+	/// Returns `EINVAL` if the number of indices differs from
+	/// [`arity`](Self::arity) or an index is outside its placeholder's range.
+	pub fn key(&self, indices: &[usize]) -> Result<Key> {
+		if indices.len() != self.slots.len() {
+			return Err(Error::invalid_argument());
+		}
+
+		let mut key = self.key.clone();
+		for (slot, &index) in self.slots.iter().zip(indices) {
+			if !slot.accepts(index) {
+				return Err(Error::invalid_argument());
+			}
+
+			key[slot.position] = index;
+		}
+
+		Ok(key)
+	}
+}
+
+impl Slot {
+	/// Determines the range of a placeholder from the component before it.
+	fn after(previous: &str, position: usize) -> Result<Self> {
+		let (limit, sentinels): (usize, &'static [usize]) = match previous {
+			| "arena" => (ARENA_INDEX_LIMIT, &[ALL_ARENAS]),
+			| "arenas" => (ARENA_INDEX_LIMIT, &[ALL_ARENAS, DESTROYED_ARENAS]),
+			| "bin" | "bins" => (arenas::nbins()?, &[]),
+			| "lextent" | "lextents" => (arenas::nlextents()?, &[]),
+			| _ => return Err(Error::invalid_argument()),
+		};
+
+		Ok(Self { position, limit, sentinels })
+	}
+
+	/// Reports whether `index` is in range or a sentinel.
+	fn accepts(&self, index: usize) -> bool {
+		index < self.limit || self.sentinels.contains(&index)
+	}
+}
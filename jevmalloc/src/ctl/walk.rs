@@ -0,0 +1,628 @@
+//! Enumeration of every readable control, in the manner of `sysctl -a`.
+
+use core::{ffi::CStr, fmt, iter::FusedIterator};
+
+use libc::c_uint;
+
+use super::{KEY_SEGS, Key, KeyTemplate, NAME_MAX, Result, raw, value};
+use crate::arena::ARENA_INDEX_LIMIT;
+
+/// C type of a control in the schema.
+#[derive(Clone, Copy)]
+enum Type {
+	/// A C `bool`.
+	Bool,
+
+	/// A `size_t`.
+	Size,
+
+	/// An `ssize_t`.
+	SSize,
+
+	/// A `uint64_t`.
+	U64,
+
+	/// An `int64_t`.
+	I64,
+
+	/// An `unsigned` or `uint32_t`.
+	Unsigned,
+
+	/// An immutable process-lifetime `const char *`.
+	Str,
+}
+
+/// Defines the walked controls and their C types.
+macro_rules! schema {
+	($($name:literal => $ty:ident),* $(,)?) => {
+		/// Every walked control, in walk order.
+		const SCHEMA: &[(&str, Type)] = &[$(($name, Type::$ty)),*];
+	};
+}
+
+schema! {
+	"version" => Str,
+	"epoch" => U64,
+	"background_thread" => Bool,
+	"max_background_threads" => Size,
+
+	"config.cache_oblivious" => Bool,
+	"config.debug" => Bool,
+	"config.fill" => Bool,
+	"config.lazy_lock" => Bool,
+	"config.malloc_conf" => Str,
+	"config.prof" => Bool,
+	"config.prof_libgcc" => Bool,
+	"config.prof_libunwind" => Bool,
+	"config.stats" => Bool,
+	"config.utrace" => Bool,
+	"config.xmalloc" => Bool,
+
+	"opt.abort" => Bool,
+	"opt.confirm_conf" => Bool,
+	"opt.abort_conf" => Bool,
+	"opt.cache_oblivious" => Bool,
+	"opt.metadata_thp" => Str,
+	"opt.trust_madvise" => Bool,
+	"opt.retain" => Bool,
+	"opt.dss" => Str,
+	"opt.narenas" => Unsigned,
+	"opt.oversize_threshold" => Size,
+	"opt.percpu_arena" => Str,
+	"opt.background_thread" => Bool,
+	"opt.max_background_threads" => Size,
+	"opt.dirty_decay_ms" => SSize,
+	"opt.muzzy_decay_ms" => SSize,
+	"opt.lg_extent_max_active_fit" => Size,
+	"opt.stats_print" => Bool,
+	"opt.stats_print_opts" => Str,
+	"opt.stats_interval" => I64,
+	"opt.stats_interval_opts" => Str,
+	"opt.junk" => Str,
+	"opt.zero" => Bool,
+	"opt.utrace" => Bool,
+	"opt.xmalloc" => Bool,
+	"opt.tcache" => Bool,
+	"opt.tcache_max" => Size,
+	"opt.thp" => Str,
+	"opt.prof_bt_max" => Unsigned,
+	"opt.prof" => Bool,
+	"opt.prof_prefix" => Str,
+	"opt.prof_active" => Bool,
+	"opt.prof_thread_active_init" => Bool,
+	"opt.lg_prof_sample" => Size,
+	"opt.prof_accum" => Bool,
+	"opt.prof_pid_namespace" => Bool,
+	"opt.lg_prof_interval" => SSize,
+	"opt.prof_gdump" => Bool,
+	"opt.prof_final" => Bool,
+	"opt.prof_leak" => Bool,
+	"opt.prof_leak_error" => Bool,
+	"opt.prof_stats" => Bool,
+	"opt.prof_sys_thread_name" => Bool,
+	"opt.prof_time_resolution" => Str,
+	"opt.zero_realloc" => Str,
+	"opt.debug_double_free_max_scan" => Unsigned,
+	"opt.disable_large_size_classes" => Bool,
+	"opt.process_madvise_max_batch" => Size,
+	"opt.mutex_max_spin" => I64,
+	"opt.remote_free_max" => Size,
+	"opt.tcache_gc_incr_bytes" => Size,
+	"opt.tcache_gc_delay_bytes" => Size,
+	"opt.lg_tcache_flush_small_div" => Unsigned,
+	"opt.lg_tcache_flush_large_div" => Unsigned,
+	"opt.lg_san_uaf_align" => SSize,
+	"opt.san_guard_small" => Size,
+	"opt.san_guard_large" => Size,
+	"opt.experimental_infallible_new" => Bool,
+	"opt.hpa" => Bool,
+	"opt.hpa_slab_max_alloc" => Size,
+	"opt.hpa_hugification_threshold" => Size,
+	"opt.hpa_hugify_delay_ms" => U64,
+	"opt.hpa_min_purge_interval_ms" => U64,
+	"opt.hpa_dirty_mult" => Unsigned,
+	"opt.hpa_sec_nshards" => Size,
+	"opt.hpa_sec_max_alloc" => Size,
+	"opt.hpa_sec_max_bytes" => Size,
+	"opt.hpa_sec_bytes_after_flush" => Size,
+	"opt.hpa_sec_batch_fill_extra" => Size,
+
+	"thread.arena" => Unsigned,
+	"thread.allocated" => U64,
+	"thread.deallocated" => U64,
+	"thread.peak.read" => U64,
+	"thread.tcache.enabled" => Bool,
+	"thread.tcache.max" => Size,
+	"thread.prof.active" => Bool,
+
+	"arena.<i>.dss" => Str,
+	"arena.<i>.dirty_decay_ms" => SSize,
+	"arena.<i>.muzzy_decay_ms" => SSize,
+	"arena.<i>.retain_grow_limit" => Size,
+
+	"arenas.narenas" => Unsigned,
+	"arenas.dirty_decay_ms" => SSize,
+	"arenas.muzzy_decay_ms" => SSize,
+	"arenas.quantum" => Size,
+	"arenas.page" => Size,
+	"arenas.tcache_max" => Size,
+	"arenas.nbins" => Unsigned,
+	"arenas.nhbins" => Unsigned,
+	"arenas.bin.<i>.size" => Size,
+	"arenas.bin.<i>.nregs" => Unsigned,
+	"arenas.bin.<i>.slab_size" => Size,
+	"arenas.bin.<i>.nshards" => Unsigned,
+	"arenas.nlextents" => Unsigned,
+	"arenas.lextent.<i>.size" => Size,
+
+	"prof.thread_active_init" => Bool,
+	"prof.active" => Bool,
+	"prof.gdump" => Bool,
+	"prof.interval" => U64,
+	"prof.lg_sample" => Size,
+
+	"stats.allocated" => Size,
+	"stats.active" => Size,
+	"stats.metadata" => Size,
+	"stats.metadata_thp" => Size,
+	"stats.resident" => Size,
+	"stats.mapped" => Size,
+	"stats.retained" => Size,
+	"stats.zero_reallocs" => Size,
+	"stats.background_thread.num_threads" => Size,
+	"stats.background_thread.num_runs" => U64,
+	"stats.background_thread.run_interval" => U64,
+
+	"stats.arenas.<i>.nthreads" => Unsigned,
+	"stats.arenas.<i>.uptime" => U64,
+	"stats.arenas.<i>.dss" => Str,
+	"stats.arenas.<i>.dirty_decay_ms" => SSize,
+	"stats.arenas.<i>.muzzy_decay_ms" => SSize,
+	"stats.arenas.<i>.pactive" => Size,
+	"stats.arenas.<i>.pdirty" => Size,
+	"stats.arenas.<i>.pmuzzy" => Size,
+	"stats.arenas.<i>.mapped" => Size,
+	"stats.arenas.<i>.retained" => Size,
+	"stats.arenas.<i>.extent_avail" => Size,
+	"stats.arenas.<i>.dirty_npurge" => U64,
+	"stats.arenas.<i>.dirty_nmadvise" => U64,
+	"stats.arenas.<i>.dirty_purged" => U64,
+	"stats.arenas.<i>.muzzy_npurge" => U64,
+	"stats.arenas.<i>.muzzy_nmadvise" => U64,
+	"stats.arenas.<i>.muzzy_purged" => U64,
+	"stats.arenas.<i>.base" => Size,
+	"stats.arenas.<i>.internal" => Size,
+	"stats.arenas.<i>.metadata_thp" => Size,
+	"stats.arenas.<i>.tcache_bytes" => Size,
+	"stats.arenas.<i>.tcache_stashed_bytes" => Size,
+	"stats.arenas.<i>.resident" => Size,
+	"stats.arenas.<i>.abandoned_vm" => Size,
+	"stats.arenas.<i>.hpa_sec_bytes" => Size,
+	"stats.arenas.<i>.small.allocated" => Size,
+	"stats.arenas.<i>.small.nmalloc" => U64,
+	"stats.arenas.<i>.small.ndalloc" => U64,
+	"stats.arenas.<i>.small.nrequests" => U64,
+	"stats.arenas.<i>.small.nfills" => U64,
+	"stats.arenas.<i>.small.nflushes" => U64,
+	"stats.arenas.<i>.large.allocated" => Size,
+	"stats.arenas.<i>.large.nmalloc" => U64,
+	"stats.arenas.<i>.large.ndalloc" => U64,
+	"stats.arenas.<i>.large.nrequests" => U64,
+	"stats.arenas.<i>.large.nfills" => U64,
+	"stats.arenas.<i>.large.nflushes" => U64,
+	"stats.arenas.<i>.bins.<j>.nmalloc" => U64,
+	"stats.arenas.<i>.bins.<j>.ndalloc" => U64,
+	"stats.arenas.<i>.bins.<j>.nrequests" => U64,
+	"stats.arenas.<i>.bins.<j>.curregs" => Size,
+	"stats.arenas.<i>.bins.<j>.nfills" => U64,
+	"stats.arenas.<i>.bins.<j>.nflushes" => U64,
+	"stats.arenas.<i>.bins.<j>.nslabs" => U64,
+	"stats.arenas.<i>.bins.<j>.nreslabs" => U64,
+	"stats.arenas.<i>.bins.<j>.curslabs" => Size,
+	"stats.arenas.<i>.bins.<j>.nonfull_slabs" => Size,
+	"stats.arenas.<i>.lextents.<j>.nmalloc" => U64,
+	"stats.arenas.<i>.lextents.<j>.ndalloc" => U64,
+	"stats.arenas.<i>.lextents.<j>.nrequests" => U64,
+	"stats.arenas.<i>.lextents.<j>.curlextents" => Size,
+}
+
+/// Number of schema controls in the effective-configuration report.
+const REPORTED_LEN: usize = {
+	let mut len = 0;
+	let mut entry = 0;
+	while entry < SCHEMA.len() {
+		if reported(SCHEMA[entry].0) {
+			len += 1;
+		}
+
+		entry += 1;
+	}
+
+	len
+};
+
+/// The schema controls in the effective-configuration report, in walk order:
+/// `version`, then every `config.*`, `opt.*`, and unindexed `arenas.*`
+/// control.
+pub(crate) const REPORTED: [&str; REPORTED_LEN] = {
+	let mut names = [""; REPORTED_LEN];
+	let mut len = 0;
+	let mut entry = 0;
+	while entry < SCHEMA.len() {
+		if reported(SCHEMA[entry].0) {
+			names[len] = SCHEMA[entry].0;
+			len += 1;
+		}
+
+		entry += 1;
+	}
+
+	names
+};
+
+/// One control value in its C type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Value {
+	/// A C `bool`.
+	Bool(bool),
+
+	/// A `size_t`.
+	Size(usize),
+
+	/// An `ssize_t`.
+	SSize(isize),
+
+	/// A `uint64_t`.
+	U64(u64),
+
+	/// An `int64_t`, as used by `opt.stats_interval` and
+	/// `opt.mutex_max_spin`.
+	I64(i64),
+
+	/// An `unsigned`, or a `uint32_t` such as `arenas.bin.<i>.nregs`.
+	Unsigned(c_uint),
+
+	/// A process-lifetime string.
+	Str(&'static CStr),
+}
+
+/// A concrete control name produced by [`walk`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Name {
+	/// Name bytes, all ASCII.
+	bytes: [u8; NAME_MAX],
+
+	/// Number of used bytes.
+	len: usize,
+}
+
+/// Iterator over every readable control and its current value.
+#[derive(Clone, Debug)]
+pub struct Walk {
+	/// Arena slots to visit, from `arenas.narenas`.
+	narenas: usize,
+
+	/// Index of the current schema entry.
+	entry: usize,
+
+	/// Index state within the current entry, once its name is resolved.
+	cursor: Option<Cursor>,
+}
+
+/// Progress through the indices of one schema entry.
+#[derive(Clone, Debug)]
+struct Cursor {
+	/// The resolved entry name.
+	template: KeyTemplate,
+
+	/// Exclusive bound of each placeholder.
+	limits: [usize; KEY_SEGS],
+
+	/// Indices of the next control, or `None` once exhausted.
+	next: Option<[usize; KEY_SEGS]>,
+}
+
+/// Enumerates the known jemalloc control tree, like `sysctl -a`.
+///
+/// A static schema records the name and C type of every readable control
+/// that can be read without side effects or caller-supplied storage. Indexed
+/// names are expanded for every arena slot below `arenas.narenas`, every
+/// small class below `arenas.nbins`, and every large class below
+/// `arenas.nlextents`; merged and destroyed-arena statistics are not
+/// included. Controls missing from the build or version (`ENOENT`), and
+/// arena slots without a live arena (`ENOENT` or `EFAULT`), are skipped.
+///
+/// Values are read lazily as the iterator advances, so statistics reflect
+/// the epoch current at each read; call
+/// [`stats::refresh_epoch`](crate::stats::refresh_epoch) first for fresh
+/// totals.
+///
+/// ```
+/// let mut walk = jevmalloc::ctl::walk()?;
+/// let (name, value) = walk.next().unwrap()?;
+///
+/// assert_eq!(name.as_str(), "version");
+/// println!("{name}: {value}");
+/// # Ok::<(), jevmalloc::ctl::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if `arenas.narenas` cannot be read.
+pub fn walk() -> Result<Walk> {
+	let narenas = crate::arenas::limit()?;
+
+	Ok(Walk { narenas, entry: 0, cursor: None })
+}
+
+impl Iterator for Walk {
+	type Item = Result<(Name, Value)>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			let &(pattern, ty) = SCHEMA.get(self.entry)?;
+			let Some(cursor) = &mut self.cursor else {
+				match KeyTemplate::new(pattern) {
+					| Ok(template) => self.cursor = Some(Cursor::new(template, self.narenas)),
+					| Err(error) => {
+						self.entry += 1;
+						if !skipped(error) {
+							return Some(Err(error));
+						}
+					},
+				}
+
+				continue;
+			};
+
+			let Some(indices) = cursor.advance() else {
+				self.cursor = None;
+				self.entry += 1;
+				continue;
+			};
+
+			let arity = cursor.template.arity();
+			let read = cursor
+				.template
+				.key(&indices[..arity])
+				.and_then(|key| read(&key, ty));
+
+			match read {
+				| Ok(value) => return Some(Ok((Name::new(pattern, &indices[..arity]), value))),
+				| Err(error) if skipped(error) => {},
+				| Err(error) => return Some(Err(error)),
+			}
+		}
+	}
+}
+
+impl FusedIterator for Walk {}
+
+impl Cursor {
+	/// Starts at the first index of every placeholder.
+	fn new(template: KeyTemplate, narenas: usize) -> Self {
+		let mut limits = [0; KEY_SEGS];
+		for (placeholder, limit) in limits
+			.iter_mut()
+			.enumerate()
+			.take(template.arity())
+		{
+			*limit = match template.limit(placeholder) {
+				| Some(ARENA_INDEX_LIMIT) => narenas.min(ARENA_INDEX_LIMIT),
+				| bound => bound.unwrap_or(0),
+			};
+		}
+
+		let empty = limits[..template.arity()].contains(&0);
+		let next = (!empty).then_some([0; KEY_SEGS]);
+
+		Self { template, limits, next }
+	}
+
+	/// Returns the current indices and steps to the next combination, with
+	/// the last placeholder varying fastest.
+	fn advance(&mut self) -> Option<[usize; KEY_SEGS]> {
+		let current = self.next?;
+		let mut following = current;
+		self.next = None;
+		for placeholder in (0..self.template.arity()).rev() {
+			following[placeholder] += 1;
+			if following[placeholder] < self.limits[placeholder] {
+				self.next = Some(following);
+				break;
+			}
+
+			following[placeholder] = 0;
+		}
+
+		Some(current)
+	}
+}
+
+/// Reports whether an error means the control is absent rather than broken.
+fn skipped(error: super::Error) -> bool { error.is(libc::ENOENT) || error.is(libc::EFAULT) }
+
+/// Reads one [`REPORTED`] control by name, or returns `None` if `name` is not
+/// one of them.
+pub(crate) fn read_reported(name: &str) -> Option<Result<Value>> {
+	let &(name, ty) = SCHEMA
+		.iter()
+		.find(|&&(pattern, _)| pattern == name && reported(pattern))?;
+
+	Some(raw::mibs(name).and_then(|key| read(&key, ty)))
+}
+
+/// Reports whether a schema control belongs in the effective-configuration
+/// report.
+const fn reported(name: &str) -> bool {
+	let name = name.as_bytes();
+	if has_prefix(name, b"arenas.") {
+		let mut position = 0;
+		while position < name.len() {
+			if name[position] == b'<' {
+				return false;
+			}
+
+			position += 1;
+		}
+
+		return true;
+	}
+
+	has_prefix(name, b"version") || has_prefix(name, b"config.") || has_prefix(name, b"opt.")
+}
+
+/// Reports whether `name` starts with `prefix`, in a constant context.
+const fn has_prefix(name: &[u8], prefix: &[u8]) -> bool {
+	if name.len() < prefix.len() {
+		return false;
+	}
+
+	let mut position = 0;
+	while position < prefix.len() {
+		if name[position] != prefix[position] {
+			return false;
+		}
+
+		position += 1;
+	}
+
+	true
+}
+
+/// Reads a schema control in its C type.
+fn read(key: &Key, ty: Type) -> Result<Value> {
+	let value = match ty {
+		| Type::Bool => Value::Bool(value::get_bool(key)?),
+		| Type::Str => Value::Str(value::get_cstr(key)?),
+
+		// SAFETY: the schema pairs every `Size` control with `size_t`.
+		| Type::Size => Value::Size(unsafe { raw::get(key) }?),
+
+		// SAFETY: the schema pairs every `SSize` control with `ssize_t`.
+		| Type::SSize => Value::SSize(unsafe { raw::get(key) }?),
+
+		// SAFETY: the schema pairs every `U64` control with `uint64_t`.
+		| Type::U64 => Value::U64(unsafe { raw::get(key) }?),
+
+		// SAFETY: the schema pairs every `I64` control with `int64_t`.
+		| Type::I64 => Value::I64(unsafe { raw::get(key) }?),
+
+		// SAFETY: the schema pairs every `Unsigned` control with `unsigned` or
+		// `uint32_t`, which have the same representation on supported targets.
+		| Type::Unsigned => Value::Unsigned(unsafe { raw::get(key) }?),
+	};
+
+	Ok(value)
+}
+
+impl Name {
+	/// Substitutes `indices` for the placeholders of `pattern`.
+	fn new(pattern: &str, indices: &[usize]) -> Self {
+		let mut name = Self { bytes: [0; NAME_MAX], len: 0 };
+		let mut indices = indices.iter();
+		for (position, component) in pattern.split('.').enumerate() {
+			if position > 0 {
+				name.push(b".");
+			}
+
+			match component
+				.starts_with('<')
+				.then(|| indices.next())
+				.flatten()
+			{
+				| Some(&index) => name.push_index(index),
+				| None => name.push(component.as_bytes()),
+			}
+		}
+
+		name
+	}
+
+	/// Returns the name as a string.
+	#[must_use]
+	pub fn as_str(&self) -> &str {
+		// Names are assembled from ASCII schema components and decimal digits.
+		core::str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+	}
+
+	/// Appends bytes that are known to fit.
+	fn push(&mut self, bytes: &[u8]) {
+		self.bytes[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+		self.len += bytes.len();
+	}
+
+	/// Appends the decimal digits of `index`.
+	fn push_index(&mut self, mut index: usize) {
+		let mut digits = [0; 20];
+		let mut start = digits.len();
+		loop {
+			start -= 1;
+			digits[start] = b"0123456789"[index % 10];
+			index /= 10;
+			if index == 0 {
+				break;
+			}
+		}
+
+		self.push(&digits[start..]);
+	}
+}
+
+impl fmt::Debug for Name {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { fmt::Debug::fmt(self.as_str(), f) }
+}
+
+impl fmt::Display for Name {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(self.as_str()) }
+}
+
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			| Self::Bool(value) => write!(f, "{value}"),
+			| Self::Size(value) => write!(f, "{value}"),
+			| Self::SSize(value) => write!(f, "{value}"),
+			| Self::U64(value) => write!(f, "{value}"),
+			| Self::I64(value) => write!(f, "{value}"),
+			| Self::Unsigned(value) => write!(f, "{value}"),
+			| Self::Str(value) => {
+				for chunk in value.to_bytes().utf8_chunks() {
+					f.write_str(chunk.valid())?;
+					if !chunk.invalid().is_empty() {
+						f.write_str("\u{fffd}")?;
+					}
+				}
+
+				Ok(())
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	//! Checks name expansion without reading controls.
+
+	use super::*;
+
+	/// Substitutes decimal indices for placeholders in order.
+	#[test]
+	fn names_expand_placeholders() {
+		let name = Name::new("stats.arenas.<i>.bins.<j>.nmalloc", &[12, 0]);
+		assert_eq!(name.as_str(), "stats.arenas.12.bins.0.nmalloc");
+		assert_eq!(Name::new("opt.narenas", &[]).as_str(), "opt.narenas");
+		assert_eq!(Name::new("arena.<i>.dss", &[4095]).as_str(), "arena.4095.dss");
+	}
+
+	/// Reports the configuration subset of the schema.
+	#[test]
+	fn report_selects_configuration() {
+		assert_eq!(REPORTED[0], "version");
+		assert!(REPORTED.contains(&"opt.narenas"));
+		assert!(REPORTED.contains(&"arenas.nlextents"));
+		assert!(!REPORTED.contains(&"arenas.bin.<i>.size"));
+		assert!(!REPORTED.contains(&"stats.allocated"));
+		assert!(read_reported("epoch").is_none());
+	}
+}
@@ -13,10 +13,16 @@
 //! Each operation invokes its optional observation hook before normalizing the
 //! caller's layout. Allocation then uses jemalloc's extended API, omitting the
 //! alignment flag only when [`layout_flags`] proves the size class implies it.
+//! With the `budget` feature, each operation also updates the [`budget`]
+//! counter, and requests beyond its hard limit fail before reaching jemalloc.
 
+#[cfg(feature = "budget")]
+pub mod budget;
 pub mod hook;
 pub mod layout;
 
+#[cfg(feature = "budget")]
+use core::ptr::null_mut;
 use core::{
 	alloc::{GlobalAlloc, Layout},
 	cmp,
@@ -80,10 +86,20 @@ unsafe impl GlobalAlloc for Jemalloc {
 		let layout = unsafe { adjust_layout(layout) };
 		let flags = layout_flags(layout);
 
+		#[cfg(feature = "budget")]
+		if !budget::reserve(layout.size()) {
+			return null_mut();
+		}
+
 		// SAFETY: the normalized size is nonzero, and the flags encode its
 		// valid power-of-two alignment.
 		let ptr = unsafe { ffi::mallocx(layout.size(), flags) };
 
+		#[cfg(feature = "budget")]
+		if ptr.is_null() {
+			budget::release(layout.size());
+		}
+
 		// SAFETY: null is accepted; otherwise `mallocx` returned a live
 		// allocation described by this layout and flags.
 		unsafe { debug_validate(ptr, layout, flags) };
@@ -117,10 +133,20 @@ unsafe impl GlobalAlloc for Jemalloc {
 		let layout = unsafe { adjust_layout(layout) };
 		let flags = layout_flags(layout) | MALLOCX_ZERO;
 
+		#[cfg(feature = "budget")]
+		if !budget::reserve(layout.size()) {
+			return null_mut();
+		}
+
 		// SAFETY: the normalized size is nonzero, and the flags encode its
 		// valid power-of-two alignment plus zero initialization.
 		let ptr = unsafe { ffi::mallocx(layout.size(), flags) };
 
+		#[cfg(feature = "budget")]
+		if ptr.is_null() {
+			budget::release(layout.size());
+		}
+
 		// SAFETY: null is accepted; otherwise `mallocx` returned a live
 		// allocation described by this layout and flags.
 		unsafe { debug_validate(ptr, layout, flags) };
@@ -151,6 +177,11 @@ unsafe impl GlobalAlloc for Jemalloc {
 			hook(layout, ptr, new_size);
 		}
 
+		#[cfg(feature = "budget")]
+		// SAFETY: the caller guarantees that `layout` describes a live, and
+		// therefore nonzero, allocation.
+		let old_size = unsafe { adjust_layout(layout) }.size();
+
 		// SAFETY: `GlobalAlloc::realloc` requires `new_size` to be nonzero
 		// and valid when rounded up to the original alignment.
 		let layout = unsafe { Layout::from_size_align_unchecked(new_size, layout.align()) };
@@ -159,10 +190,25 @@ unsafe impl GlobalAlloc for Jemalloc {
 		let layout = unsafe { adjust_layout(layout) };
 		let flags = layout_flags(layout);
 
+		#[cfg(feature = "budget")]
+		let growth = layout.size().saturating_sub(old_size);
+
+		#[cfg(feature = "budget")]
+		if !budget::reserve(growth) {
+			return null_mut();
+		}
+
 		// SAFETY: the caller guarantees `ptr` is live from this allocator;
 		// the size is nonzero and the flags preserve its alignment.
 		let ptr = unsafe { ffi::rallocx(ptr.cast::<c_void>(), layout.size(), flags) };
 
+		#[cfg(feature = "budget")]
+		if ptr.is_null() {
+			budget::release(growth);
+		} else {
+			budget::release(old_size.saturating_sub(layout.size()));
+		}
+
 		// SAFETY: null is accepted; otherwise `rallocx` returned a live
 		// allocation described by this layout and flags.
 		unsafe { debug_validate(ptr, layout, flags) };
@@ -208,6 +254,9 @@ unsafe impl GlobalAlloc for Jemalloc {
 		// SAFETY: `ptr` remains live, and its normalized size and alignment
 		// flags match the allocation request.
 		unsafe { ffi::sdallocx(ptr, layout.size(), flags) };
+
+		#[cfg(feature = "budget")]
+		budget::release(layout.size());
 	}
 }
 
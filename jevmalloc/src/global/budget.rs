@@ -0,0 +1,267 @@
+//! Soft and hard limits on bytes held through [`Jemalloc`]'s `GlobalAlloc`.
+//!
+//! With the `budget` feature, every global allocation, reallocation, and
+//! deallocation is counted by its normalized request size. Allocations made
+//! directly through jemalloc, an [`Arena`], or the C allocator entry points are
+//! not counted.
+//!
+//! Requests below 64 KiB are not written to one shared counter. Their deltas
+//! collect in one of 64 cache-line-sized shards, chosen by the 2 MiB block of
+//! the calling thread's stack, and are published to the process-wide total
+//! once a shard holds 64 KiB either way. Requests of 64 KiB or more are
+//! published directly. So the hot path touches a shard that few threads share
+//! and only reads the total, at the cost of precision: up to 4 MiB of small
+//! requests can be unpublished at any moment.
+//!
+//! A request that would raise the total above the hard limit fails with a
+//! null pointer instead of reaching jemalloc, so Rust reports an allocation
+//! error rather than the process growing until the kernel intervenes. Small
+//! requests are checked against the published total plus their own shard, so
+//! the limit can be exceeded by the unpublished bytes of other shards.
+//! Concurrent requests near the limit reserve optimistically, so one can fail
+//! while another's reservation is briefly outstanding.
+//!
+//! Crossing the soft limit invokes the installed handler once on the
+//! allocating thread, when the crossing is published. The handler fires again
+//! only after usage drops back below the soft limit and crosses it anew. Both
+//! limits can change at any time.
+//!
+//! [`Jemalloc`]: crate::Jemalloc
+//! [`Arena`]: crate::Arena
+
+use core::{
+	mem::transmute_copy,
+	ptr::{self, null_mut},
+	sync::atomic::{AtomicBool, AtomicIsize, AtomicPtr, AtomicUsize, Ordering},
+};
+
+/// Receives the counted usage when it first reaches the soft limit.
+///
+/// The handler runs inside `GlobalAlloc::alloc` on the allocating thread. It
+/// may allocate, since the crossing it reports is already recorded. It is
+/// called through a function that cannot unwind, so a panicking handler
+/// aborts the process.
+pub type SoftLimitFn = fn(usize);
+
+/// Deltas of at least this many bytes are published directly, and a shard is
+/// published once it holds this much either way.
+const BATCH: usize = 64 * 1024;
+
+/// [`BATCH`] as a signed delta.
+const BATCH_DELTA: isize = 64 * 1024;
+
+/// Number of pending-delta shards.
+const SHARDS: usize = 64;
+
+/// Unpublished small-request deltas of the threads that map to one shard.
+#[repr(align(128))]
+struct Shard {
+	/// The pending delta in bytes.
+	delta: AtomicIsize,
+}
+
+/// Bytes held through the global allocator, as published by large requests
+/// and flushed shards. A deallocation can be published before its allocation,
+/// so the total is transiently negative at worst.
+static USED: AtomicIsize = AtomicIsize::new(0);
+
+/// Small-request deltas not yet added to [`USED`].
+static PENDING: [Shard; SHARDS] = [const { Shard { delta: AtomicIsize::new(0) } }; SHARDS];
+
+/// The soft limit, or `usize::MAX` when unset.
+static SOFT_LIMIT: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// The hard limit, or `usize::MAX` when unset.
+static HARD_LIMIT: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Whether the current soft-limit crossing has already been reported.
+static ABOVE_SOFT_LIMIT: AtomicBool = AtomicBool::new(false);
+
+/// The soft-limit handler address, or null when none is installed.
+static SOFT_LIMIT_HANDLER: AtomicPtr<()> = AtomicPtr::new(null_mut());
+
+/// Returns the bytes currently held through the global allocator.
+///
+/// This sums every shard, so it is exact when no counted operation is in
+/// flight.
+#[must_use]
+pub fn used() -> usize {
+	let used = PENDING
+		.iter()
+		.fold(USED.load(Ordering::Relaxed), |used, shard| {
+			used.wrapping_add(shard.delta.load(Ordering::Relaxed))
+		});
+
+	bytes(used)
+}
+
+/// Returns the soft limit, if one is set.
+#[must_use]
+#[inline]
+pub fn soft_limit() -> Option<usize> { load_limit(&SOFT_LIMIT) }
+
+/// Replaces the soft limit.
+///
+/// Passing `None` disables the soft limit. If usage already meets the new
+/// limit, the next counted allocation invokes the handler.
+pub fn set_soft_limit(limit: Option<usize>) {
+	store_limit(&SOFT_LIMIT, limit);
+	ABOVE_SOFT_LIMIT.store(false, Ordering::Relaxed);
+}
+
+/// Returns the hard limit, if one is set.
+#[must_use]
+#[inline]
+pub fn hard_limit() -> Option<usize> { load_limit(&HARD_LIMIT) }
+
+/// Replaces the hard limit.
+///
+/// Passing `None` disables the hard limit. Lowering it below current usage
+/// releases nothing; it only causes growth to fail until usage drops.
+pub fn set_hard_limit(limit: Option<usize>) { store_limit(&HARD_LIMIT, limit); }
+
+/// Replaces the soft-limit handler and returns the previous one.
+pub fn set_soft_limit_handler(handler: Option<SoftLimitFn>) -> Option<SoftLimitFn> {
+	let handler = handler.map_or(null_mut(), |handler| handler as *mut ());
+
+	// SAFETY: the slot holds only null or `SoftLimitFn` addresses stored above.
+	unsafe { load_handler(SOFT_LIMIT_HANDLER.swap(handler, Ordering::AcqRel)) }
+}
+
+/// Counts `size` additional bytes unless doing so exceeds the hard limit.
+///
+/// Returns `false`, leaving the count unchanged, when the request must fail.
+#[inline]
+pub(super) fn reserve(size: usize) -> bool {
+	let delta = signed(size);
+	if size >= BATCH {
+		return publish(delta, size);
+	}
+
+	let shard = shard();
+	let pending = shard
+		.fetch_add(delta, Ordering::Relaxed)
+		.wrapping_add(delta);
+
+	if pending >= BATCH_DELTA {
+		return publish(shard.swap(0, Ordering::Relaxed), size);
+	}
+
+	let used = USED
+		.load(Ordering::Relaxed)
+		.saturating_add(pending);
+	if bytes(used) > HARD_LIMIT.load(Ordering::Relaxed) {
+		shard.fetch_sub(delta, Ordering::Relaxed);
+		return false;
+	}
+
+	true
+}
+
+/// Stops counting `size` bytes previously passed to [`reserve`].
+#[inline]
+pub(super) fn release(size: usize) {
+	let delta = signed(size);
+	let used = if size >= BATCH {
+		USED.fetch_sub(delta, Ordering::Relaxed)
+			.wrapping_sub(delta)
+	} else {
+		let shard = shard();
+		let pending = shard
+			.fetch_sub(delta, Ordering::Relaxed)
+			.wrapping_sub(delta);
+
+		if pending > -BATCH_DELTA {
+			return;
+		}
+
+		let pending = shard.swap(0, Ordering::Relaxed);
+		USED.fetch_add(pending, Ordering::Relaxed)
+			.wrapping_add(pending)
+	};
+
+	if bytes(used) < SOFT_LIMIT.load(Ordering::Relaxed)
+		&& ABOVE_SOFT_LIMIT.load(Ordering::Relaxed)
+	{
+		ABOVE_SOFT_LIMIT.store(false, Ordering::Relaxed);
+	}
+}
+
+/// Adds `delta`, which includes a new request of `size` bytes, to the
+/// published total and applies both limits to the result.
+///
+/// Returns `false` after withdrawing the request when it exceeds the hard
+/// limit.
+fn publish(delta: isize, size: usize) -> bool {
+	let used = bytes(
+		USED.fetch_add(delta, Ordering::Relaxed)
+			.wrapping_add(delta),
+	);
+
+	if used > HARD_LIMIT.load(Ordering::Relaxed) {
+		USED.fetch_sub(signed(size), Ordering::Relaxed);
+		return false;
+	}
+
+	if used >= SOFT_LIMIT.load(Ordering::Relaxed)
+		&& !ABOVE_SOFT_LIMIT.load(Ordering::Relaxed)
+		&& !ABOVE_SOFT_LIMIT.swap(true, Ordering::Relaxed)
+	{
+		notify(used);
+	}
+
+	true
+}
+
+/// Invokes the installed soft-limit handler, if any.
+///
+/// The C ABI makes an unwinding handler abort the process rather than unwind
+/// into the global allocator's caller.
+extern "C" fn notify(used: usize) {
+	// SAFETY: the slot holds only null or `SoftLimitFn` addresses.
+	if let Some(handler) = unsafe { load_handler(SOFT_LIMIT_HANDLER.load(Ordering::Acquire)) } {
+		handler(used);
+	}
+}
+
+/// Returns the calling thread's shard, chosen by the 2 MiB block of its stack.
+#[inline]
+fn shard() -> &'static AtomicIsize {
+	let marker = 0_u8;
+	let block = ptr::from_ref(&marker).addr() >> 21;
+
+	&PENDING[block % SHARDS].delta
+}
+
+/// Converts a request size, which `Layout` bounds by `isize::MAX`, to a delta.
+#[inline]
+fn signed(size: usize) -> isize { isize::try_from(size).unwrap_or(isize::MAX) }
+
+/// Converts a total to bytes, reading a transiently negative one as zero.
+#[inline]
+fn bytes(used: isize) -> usize { usize::try_from(used).unwrap_or(0) }
+
+/// Reads a limit slot, mapping the unset sentinel to `None`.
+fn load_limit(slot: &AtomicUsize) -> Option<usize> {
+	Some(slot.load(Ordering::Relaxed)).filter(|&limit| limit != usize::MAX)
+}
+
+/// Writes a limit slot, mapping `None` to the unset sentinel.
+fn store_limit(slot: &AtomicUsize, limit: Option<usize>) {
+	slot.store(limit.unwrap_or(usize::MAX), Ordering::Relaxed);
+}
+
+/// Converts a stored handler address back into its function pointer.
+///
+/// # Safety
+///
+/// `ptr` must be null or the address of a [`SoftLimitFn`].
+unsafe fn load_handler(ptr: *mut ()) -> Option<SoftLimitFn> {
+	if ptr.is_null() {
+		return None;
+	}
+
+	// SAFETY: the caller guarantees that the non-null address is a
+	// `SoftLimitFn`, which has the representation of a data pointer.
+	Some(unsafe { transmute_copy::<*mut (), SoftLimitFn>(&ptr) })
+}
@@ -13,6 +13,7 @@
 //! [`Jemalloc`] implements [`GlobalAlloc`] and can service the process-wide
 //! `#[global_allocator]` slot. Typed allocator operations are grouped by scope
 //! in [`Arena`], [`arenas`], [`config`], [`opt`], [`stats`], and [`thread`].
+//! [`message`] redirects jemalloc's diagnostic output.
 //! The [`ctl`] module exposes MIB-based control-interface primitives, while
 //! [`ffi`] re-exports the underlying C bindings.
 //!
@@ -33,11 +34,17 @@
 
 #![no_std]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
 pub mod arena;
 pub mod arenas;
 pub mod config;
 pub mod ctl;
 pub mod global;
+pub mod message;
 pub mod opt;
 #[cfg(feature = "profiling")]
 pub mod profiling;
@@ -51,11 +58,14 @@ pub mod thread;
 /// contracts documented there.
 pub use ::jevmalloc_sys as ffi;
 pub use arena::{
-	ARENA_INDEX_LIMIT, ARENA_NAME_LEN, Arena, ArenaDestroyError, ArenaName, Dss,
-	EMPTY_RAW_EXTENT_HOOKS, Extent, ExtentAlloc, ExtentAllocFn, ExtentAllocation,
-	ExtentCallbacks, ExtentDallocFn, ExtentDestroyFn, ExtentHookResult, ExtentHooks, ExtentMerge,
-	ExtentMergeFn, ExtentRange, ExtentRangeFn, ExtentSplit, ExtentSplitFn, RawExtentHooks,
+	ALL_ARENAS, ARENA_INDEX_LIMIT, ARENA_NAME_LEN, Arena, ArenaDestroyError, ArenaName,
+	DESTROYED_ARENAS, Dss, EMPTY_RAW_EXTENT_HOOKS, Extent, ExtentAlloc, ExtentAllocFn,
+	ExtentAllocation, ExtentCallbacks, ExtentDallocFn, ExtentDestroyFn, ExtentHookResult,
+	ExtentHooks, ExtentMerge, ExtentMergeFn, ExtentRange, ExtentRangeFn, ExtentSplit,
+	ExtentSplitFn, RawExtentHooks, RegionArena, RegionHooks,
 };
+#[cfg(target_os = "linux")]
+pub use arena::{FileHooks, HugePageHooks, NumaArenas, NumaHooks, SecretHooks};
 
 pub use self::ctl::{Error, Result};
 /// Re-exports the allocator layout utilities.
@@ -0,0 +1,143 @@
+//! Interception of jemalloc's diagnostic output.
+//!
+//! Jemalloc writes warnings, safety-check failures, and the reports requested
+//! by `opt.stats_print` and `opt.stats_interval` through its `malloc_message`
+//! function pointer, which writes to standard error by default.
+//! [`set_message_handler`] routes that output to a Rust function instead.
+//!
+//! Output arrives as byte fragments. A warning is normally one fragment, but
+//! reports are split at arbitrary buffer boundaries, and neither is
+//! guaranteed to be valid UTF-8. Explicit [`stats::print`] and
+//! [`stats::print_raw`] calls supply their own writer and bypass the handler.
+//!
+//! With the `log` feature, [`log_message`] forwards fragments to the `log`
+//! crate.
+//!
+//! [`stats::print`]: crate::stats::print
+//! [`stats::print_raw`]: crate::stats::print_raw
+
+use core::{
+	ffi::{CStr, c_char, c_void},
+	mem::transmute_copy,
+	ptr::null_mut,
+	sync::atomic::{AtomicPtr, Ordering},
+};
+
+use crate::ffi;
+
+/// Receives one fragment of jemalloc's diagnostic output.
+///
+/// The handler can run on any thread, concurrently, and while jemalloc holds
+/// internal locks. It must not allocate through jemalloc, which can deadlock
+/// or recurse, and it must not unwind; a panic aborts the process.
+pub type MessageFn = fn(&[u8]);
+
+/// The writer installed in `malloc_message` while a handler is set.
+const WRITER: unsafe extern "C" fn(*mut c_void, *const c_char) = message;
+
+/// The installed handler address, or null when none is installed.
+static HANDLER: AtomicPtr<()> = AtomicPtr::new(null_mut());
+
+/// Replaces the diagnostic-output handler and returns the previous one.
+///
+/// Passing `None` restores jemalloc's default writer to standard error. A
+/// message already in flight when the handler is removed is written to
+/// standard error.
+pub fn set_message_handler(handler: Option<MessageFn>) -> Option<MessageFn> {
+	let address = handler.map_or(null_mut(), |handler| handler as *mut ());
+
+	// SAFETY: `HANDLER` holds only null or `MessageFn` addresses stored here.
+	let previous = unsafe { load_handler(HANDLER.swap(address, Ordering::AcqRel)) };
+
+	let writer = handler.map_or(null_mut(), |_| WRITER as *mut ());
+	let slot = &raw mut ffi::malloc_message;
+
+	// SAFETY: `malloc_message` is a pointer-sized, pointer-aligned C object
+	// that no Rust reference aliases. The atomic store cannot tear, and
+	// jemalloc reads it with a single pointer load before each message.
+	let slot = unsafe { AtomicPtr::from_ptr(slot.cast()) };
+	slot.store(writer, Ordering::Release);
+
+	previous
+}
+
+/// Forwards one fragment from jemalloc to the installed handler.
+///
+/// # Safety
+///
+/// `text` must point to a NUL-terminated string that remains live for the
+/// call.
+unsafe extern "C" fn message(_opaque: *mut c_void, text: *const c_char) {
+	// SAFETY: jemalloc supplies a live NUL-terminated string.
+	let fragment = unsafe { CStr::from_ptr(text) }.to_bytes();
+
+	// SAFETY: `HANDLER` holds only null or `MessageFn` addresses.
+	match unsafe { load_handler(HANDLER.load(Ordering::Acquire)) } {
+		| Some(handler) => handler(fragment),
+		| None => write_stderr(fragment),
+	}
+}
+
+/// Writes a fragment to standard error, as jemalloc's default writer does.
+fn write_stderr(mut fragment: &[u8]) {
+	while !fragment.is_empty() {
+		// SAFETY: the pointer and length describe the live `fragment` slice.
+		let written =
+			unsafe { libc::write(libc::STDERR_FILENO, fragment.as_ptr().cast(), fragment.len()) };
+		let Ok(written) = usize::try_from(written) else {
+			return;
+		};
+
+		fragment = &fragment[written..];
+	}
+}
+
+/// Converts a stored handler address back into its function pointer.
+///
+/// # Safety
+///
+/// `ptr` must be null or the address of a [`MessageFn`].
+unsafe fn load_handler(ptr: *mut ()) -> Option<MessageFn> {
+	if ptr.is_null() {
+		return None;
+	}
+
+	// SAFETY: the caller guarantees that the non-null address is a
+	// `MessageFn`, which has the representation of a data pointer.
+	Some(unsafe { transmute_copy::<*mut (), MessageFn>(&ptr) })
+}
+
+/// Forwards a fragment to the `log` crate under the `jemalloc` target.
+///
+/// Fragments carrying jemalloc's `<jemalloc>:` prefix are logged at warning
+/// level and all others, such as report text, at info level. One trailing
+/// newline is removed, and invalid UTF-8 is replaced with U+FFFD without
+/// allocating. Install it with `set_message_handler(Some(log_message))`; the
+/// active logger must itself satisfy [`MessageFn`]'s no-allocation rule.
+#[cfg(feature = "log")]
+pub fn log_message(fragment: &[u8]) {
+	/// Displays bytes with invalid UTF-8 sequences replaced.
+	struct Lossy<'a>(&'a [u8]);
+
+	impl core::fmt::Display for Lossy<'_> {
+		fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+			for chunk in self.0.utf8_chunks() {
+				f.write_str(chunk.valid())?;
+				if !chunk.invalid().is_empty() {
+					f.write_str("\u{fffd}")?;
+				}
+			}
+
+			Ok(())
+		}
+	}
+
+	let fragment = fragment.strip_suffix(b"\n").unwrap_or(fragment);
+	let level = if fragment.starts_with(b"<jemalloc>:") {
+		log::Level::Warn
+	} else {
+		log::Level::Info
+	};
+
+	log::log!(target: "jemalloc", level, "{}", Lossy(fragment));
+}
@@ -2,17 +2,44 @@
 //!
 //! These getters expose the documented `opt.*` controls. Jemalloc resolves the
 //! options during initialization, so this interface is read-only. Getters that
-//! depend on fill, utrace, xmalloc, or profiling support return `ENOENT` when
-//! that capability was omitted from the jemalloc build. String values borrow
-//! process-lifetime storage and require no allocation or encoding conversion.
-//! A library supplied through `JEMALLOC_OVERRIDE` must preserve the bundled
-//! implementation's immutable, process-lifetime storage contract for them.
+//! depend on fill, utrace, xmalloc, profiling, use-after-free detection, or C++
+//! support return `ENOENT` when that capability was omitted from the jemalloc
+//! build; this crate always builds jemalloc without C++ support. String values
+//! borrow process-lifetime storage and require no allocation or encoding
+//! conversion. A library supplied through `JEMALLOC_OVERRIDE` must preserve the
+//! bundled implementation's immutable, process-lifetime storage contract for
+//! them.
+//!
+//! [`MallocConf`] builds the linked `malloc_conf` string at compile time, and
+//! [`malloc_conf!`](crate::malloc_conf) exports it under the symbol jemalloc
+//! reads.
+//!
+//! [`parse`] splits an option string as jemalloc does. With the `std` feature,
+//! [`audit`] compares every option requested through the configure-time
+//! string, the linked `malloc_conf` symbol, and the environment with the
+//! effective values, and reports the ones that did not take effect.
+
+#[cfg(feature = "std")]
+mod audit;
+mod conf;
+mod mode;
+mod parse;
 
 use core::ffi::CStr;
 
 use libc::c_uint;
 
-use crate::ctl::{Result, key, raw, value};
+#[cfg(feature = "std")]
+pub use self::audit::{Audit, Finding, Outcome, Source, audit};
+pub use self::{
+	conf::{ConfString, MALLOC_CONF_CAPACITY, MallocConf},
+	mode::{Junk, MetadataThp, PercpuArena, Thp, ZeroRealloc},
+	parse::{ConfEntries, ConfEntry, ConfError, ConfErrorKind, parse},
+};
+use crate::{
+	arena::Dss,
+	ctl::{Error, Result, key, raw, value},
+};
 
 /// Defines a getter for one boolean startup option.
 macro_rules! bool_getter {
@@ -47,6 +74,24 @@ macro_rules! scalar_getter {
 	};
 }
 
+/// Defines a parsed getter for one enumerated string startup option.
+macro_rules! typed_getter {
+	($(#[$meta:meta])* $name:ident => $raw:ident: $value:ty) => {
+		$(#[$meta])*
+		///
+		/// # Errors
+		///
+		/// Returns an error if jemalloc rejects or does not provide the query,
+		/// or reports a value this crate does not recognize.
+		pub fn $name() -> Result<$value> {
+			$raw()?
+				.to_str()
+				.map_err(|_| Error::invalid_argument())?
+				.parse()
+		}
+	};
+}
+
 /// Defines a getter for one process-lifetime string startup option.
 macro_rules! cstr_getter {
 	($(#[$meta:meta])* $name:ident => $key:ident) => {
@@ -90,6 +135,11 @@ cstr_getter! {
 	metadata_thp => opt_metadata_thp
 }
 
+typed_getter! {
+	/// Returns the parsed transparent-huge-page mode for allocator metadata.
+	metadata_thp_mode => metadata_thp: MetadataThp
+}
+
 bool_getter! {
 	/// Returns whether jemalloc trusts `MADV_DONTNEED` to zero pages.
 	trust_madvise => opt_trust_madvise
@@ -105,6 +155,11 @@ cstr_getter! {
 	dss => opt_dss
 }
 
+typed_getter! {
+	/// Returns the parsed dynamic storage allocation precedence.
+	dss_mode => dss: Dss
+}
+
 scalar_getter! {
 	/// Returns the maximum number of automatically managed arenas.
 	narenas => opt_narenas: c_uint
@@ -120,6 +175,11 @@ cstr_getter! {
 	percpu_arena => opt_percpu_arena
 }
 
+typed_getter! {
+	/// Returns the parsed per-CPU arena mode.
+	percpu_arena_mode => percpu_arena: PercpuArena
+}
+
 bool_getter! {
 	/// Returns whether background worker threads are enabled at initialization.
 	background_thread => opt_background_thread
@@ -170,6 +230,11 @@ cstr_getter! {
 	junk => opt_junk
 }
 
+typed_getter! {
+	/// Returns the parsed allocation junk-filling mode.
+	junk_mode => junk: Junk
+}
+
 bool_getter! {
 	/// Returns whether newly allocated memory is zero-filled by default.
 	zero => opt_zero
@@ -200,6 +265,11 @@ cstr_getter! {
 	thp => opt_thp
 }
 
+typed_getter! {
+	/// Returns the parsed transparent-huge-page mode for user allocation mappings.
+	thp_mode => thp: Thp
+}
+
 scalar_getter! {
 	/// Returns the maximum number of frames captured in a profile backtrace.
 	prof_bt_max => opt_prof_bt_max: c_uint
@@ -265,11 +335,21 @@ bool_getter! {
 	prof_leak_error => opt_prof_leak_error
 }
 
+bool_getter! {
+	/// Returns whether per-size-class sampled profiling statistics are kept.
+	prof_stats => opt_prof_stats
+}
+
 cstr_getter! {
 	/// Returns the configured behavior for reallocating a non-null pointer to zero.
 	zero_realloc => opt_zero_realloc
 }
 
+typed_getter! {
+	/// Returns the parsed behavior for reallocating a non-null pointer to zero.
+	zero_realloc_mode => zero_realloc: ZeroRealloc
+}
+
 scalar_getter! {
 	/// Returns the maximum tcache entries scanned for debug double-free detection.
 	debug_double_free_max_scan => opt_debug_double_free_max_scan: c_uint
@@ -284,3 +364,121 @@ scalar_getter! {
 	/// Returns the maximum number of extents in one `process_madvise` batch.
 	process_madvise_max_batch => opt_process_madvise_max_batch: usize
 }
+
+scalar_getter! {
+	/// Returns the number of spin iterations before a contended mutex blocks.
+	mutex_max_spin => opt_mutex_max_spin: i64
+}
+
+scalar_getter! {
+	/// Returns the maximum number of remote frees batched before a flush.
+	remote_free_max => opt_remote_free_max: usize
+}
+
+scalar_getter! {
+	/// Returns the allocation-activity byte interval between thread-cache
+	/// garbage collection passes.
+	tcache_gc_incr_bytes => opt_tcache_gc_incr_bytes: usize
+}
+
+scalar_getter! {
+	/// Returns the allocation-activity byte delay before an unused cache bin is garbage collected.
+	tcache_gc_delay_bytes => opt_tcache_gc_delay_bytes: usize
+}
+
+scalar_getter! {
+	/// Returns the base-two logarithm of the divisor applied when flushing small cache bins.
+	lg_tcache_flush_small_div => opt_lg_tcache_flush_small_div: c_uint
+}
+
+scalar_getter! {
+	/// Returns the base-two logarithm of the divisor applied when flushing large cache bins.
+	lg_tcache_flush_large_div => opt_lg_tcache_flush_large_div: c_uint
+}
+
+scalar_getter! {
+	/// Returns the base-two logarithm of the alignment sampled for
+	/// use-after-free detection, or `-1` when disabled.
+	lg_san_uaf_align => opt_lg_san_uaf_align: isize
+}
+
+scalar_getter! {
+	/// Returns the interval, in small-extent allocations, at which extents receive guard pages.
+	san_guard_small => opt_san_guard_small: usize
+}
+
+scalar_getter! {
+	/// Returns the interval, in large-extent allocations, at which extents receive guard pages.
+	san_guard_large => opt_san_guard_large: usize
+}
+
+bool_getter! {
+	/// Returns whether profiling records thread names reported by the operating system.
+	prof_sys_thread_name => opt_prof_sys_thread_name
+}
+
+cstr_getter! {
+	/// Returns the timestamp resolution used for profiling records.
+	prof_time_resolution => opt_prof_time_resolution
+}
+
+bool_getter! {
+	/// Returns whether a failing C++ `operator new` aborts instead of throwing.
+	experimental_infallible_new => opt_experimental_infallible_new
+}
+
+bool_getter! {
+	/// Returns whether the huge-page allocator is enabled.
+	hpa => opt_hpa
+}
+
+scalar_getter! {
+	/// Returns the largest allocation, in bytes, served from huge-page allocator slabs.
+	hpa_slab_max_alloc => opt_hpa_slab_max_alloc: usize
+}
+
+scalar_getter! {
+	/// Returns the active bytes at which a huge-page allocator slab is hugified.
+	hpa_hugification_threshold => opt_hpa_hugification_threshold: usize
+}
+
+scalar_getter! {
+	/// Returns the delay, in milliseconds, before an eligible slab is hugified.
+	hpa_hugify_delay_ms => opt_hpa_hugify_delay_ms: u64
+}
+
+scalar_getter! {
+	/// Returns the minimum interval, in milliseconds, between huge-page allocator purges.
+	hpa_min_purge_interval_ms => opt_hpa_min_purge_interval_ms: u64
+}
+
+scalar_getter! {
+	/// Returns the dirty-page limit as a 16.16 fixed-point fraction of active
+	/// pages, or `u32::MAX` when unlimited.
+	hpa_dirty_mult => opt_hpa_dirty_mult: u32
+}
+
+scalar_getter! {
+	/// Returns the number of small-extent cache shards in front of the huge-page allocator.
+	hpa_sec_nshards => opt_hpa_sec_nshards: usize
+}
+
+scalar_getter! {
+	/// Returns the largest extent, in bytes, kept by the small-extent cache.
+	hpa_sec_max_alloc => opt_hpa_sec_max_alloc: usize
+}
+
+scalar_getter! {
+	/// Returns the byte limit of each small-extent cache shard.
+	hpa_sec_max_bytes => opt_hpa_sec_max_bytes: usize
+}
+
+scalar_getter! {
+	/// Returns the bytes a small-extent cache shard keeps after a flush.
+	hpa_sec_bytes_after_flush => opt_hpa_sec_bytes_after_flush: usize
+}
+
+scalar_getter! {
+	/// Returns the number of extra extents fetched when a small-extent cache shard refills.
+	hpa_sec_batch_fill_extra => opt_hpa_sec_batch_fill_extra: usize
+}
@@ -0,0 +1,361 @@
+//! Comparison of requested startup options with their effective values.
+
+use alloc::{
+	borrow::ToOwned,
+	format,
+	string::{String, ToString},
+	vec::Vec,
+};
+use core::ffi::CStr;
+use std::env;
+
+use super::{ConfError, parse};
+use crate::{
+	config::{self, Effective},
+	ctl::{Error, Result, Value, raw},
+	ffi,
+};
+
+/// Keys jemalloc accepts in an option string that have no `opt.*` control, so
+/// their effect cannot be read back.
+const CONF_ONLY: &[&str] = &[
+	"bin_shards",
+	"hpa_hugification_threshold_ratio",
+	"lg_tcache_max",
+	"lg_tcache_nslots_mul",
+	"prof_log",
+	"prof_recent_alloc_max",
+	"remote_free_max_batch",
+	"slab_sizes",
+	"tcache_nslots_large",
+	"tcache_nslots_small_max",
+	"tcache_nslots_small_min",
+];
+
+/// Options whose control reports an unsigned 16.16 fixed-point number, with
+/// `u32::MAX` standing for a requested `-1`.
+const FIXED_POINT: &[&str] = &["hpa_dirty_mult"];
+
+/// An option string jemalloc reads during initialization.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Source {
+	/// The string embedded at configure time, as reported by
+	/// [`config::malloc_conf`].
+	Builtin,
+
+	/// The linked `malloc_conf` symbol, such as one exported by
+	/// [`malloc_conf!`](crate::malloc_conf).
+	Linked,
+
+	/// The `MALLOC_CONF` environment variable, named `_RJEM_MALLOC_CONF` when
+	/// symbols are prefixed.
+	Environment,
+}
+
+/// What became of one requested option.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+	/// The effective value equals the requested value.
+	Applied,
+
+	/// A later pair for the same key, from the given source, replaced this
+	/// one.
+	Overridden(Source),
+
+	/// The effective value differs from the requested value, because jemalloc
+	/// rejected, clamped, or could not honor it.
+	Ignored,
+
+	/// The option depends on a capability omitted from the jemalloc build,
+	/// such as profiling, so jemalloc ignored it.
+	Unsupported,
+
+	/// Jemalloc accepts no option of this name; the key is misspelled or not
+	/// an option of the linked jemalloc version.
+	Unknown,
+
+	/// Jemalloc accepts the key, but this audit cannot read its effective
+	/// value, as for `slab_sizes` or `bin_shards`, which have no `opt.*`
+	/// control.
+	Unverified,
+}
+
+/// The audit result for one requested `key:value` pair.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+	/// The string that requested the option.
+	pub source: Source,
+
+	/// The option name.
+	pub key: String,
+
+	/// The requested value, as written.
+	pub requested: String,
+
+	/// The effective value, when it can be read.
+	pub effective: Option<String>,
+
+	/// How the request compares with the effective value.
+	pub outcome: Outcome,
+}
+
+/// Requested options compared with the values jemalloc resolved.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Audit {
+	/// One finding per requested pair, in the order jemalloc applies them.
+	pub findings: Vec<Finding>,
+
+	/// Syntax errors, each of which ends jemalloc's reading of its string.
+	pub errors: Vec<(Source, ConfError)>,
+}
+
+/// Compares every requested option with its effective `opt.*` value.
+///
+/// The sources are read in jemalloc's order: the configure-time string, the
+/// linked `malloc_conf` symbol, then the environment variable. A later pair
+/// for a key overrides earlier ones. The `/etc/malloc.conf` symbolic link is
+/// not read. The environment is read now, so it can differ from what jemalloc
+/// saw at initialization if the variable changed since.
+///
+/// Values are compared in the control's type: booleans must be `true` or
+/// `false`, integers accept jemalloc's octal, decimal, and hexadecimal forms,
+/// fixed-point options such as `hpa_dirty_mult` accept decimal fractions and
+/// `-1`, and strings must match exactly.
+///
+/// # Errors
+///
+/// Returns an error if a source cannot be read or jemalloc rejects an option
+/// query other than for a missing capability.
+pub fn audit() -> Result<Audit> {
+	let mut requested = Vec::new();
+	let mut errors = Vec::new();
+	for (source, conf) in sources()? {
+		for item in parse(&conf) {
+			match item {
+				| Ok(entry) =>
+					requested.push((source, entry.key.to_owned(), entry.value.to_owned())),
+				| Err(error) => errors.push((source, error)),
+			}
+		}
+	}
+
+	let mut findings = Vec::with_capacity(requested.len());
+	for (index, (source, key, value)) in requested.iter().enumerate() {
+		let (effective, outcome) = inspect(key, value)?;
+		let later = requested[index + 1..]
+			.iter()
+			.rev()
+			.find(|(_, later, _)| later == key);
+
+		findings.push(Finding {
+			source: *source,
+			key: key.clone(),
+			requested: value.clone(),
+			effective,
+			outcome: later.map_or(outcome, |(by, ..)| Outcome::Overridden(*by)),
+		});
+	}
+
+	Ok(Audit { findings, errors })
+}
+
+impl Audit {
+	/// Reports whether every string parsed cleanly and no request was
+	/// ignored, unsupported, or unknown.
+	#[must_use]
+	pub fn is_clean(&self) -> bool {
+		self.errors.is_empty()
+			&& self
+				.findings
+				.iter()
+				.all(|finding| !finding.outcome.is_problem())
+	}
+}
+
+impl Outcome {
+	/// Reports whether the request did not take effect for a reason other
+	/// than a later override.
+	#[must_use]
+	pub const fn is_problem(self) -> bool {
+		matches!(self, Self::Ignored | Self::Unsupported | Self::Unknown)
+	}
+}
+
+/// Reads the option strings of every source.
+fn sources() -> Result<[(Source, String); 3]> {
+	let builtin = config::malloc_conf()?;
+
+	// SAFETY: `malloc_conf` is an initialized, pointer-sized object that no
+	// one writes after startup.
+	let linked = unsafe { ffi::malloc_conf };
+
+	// SAFETY: jemalloc and every exporter define the symbol as null or a
+	// process-lifetime NUL-terminated string.
+	let linked = linked.map(|text| unsafe { CStr::from_ptr(text) });
+
+	let name = ffi::MALLOC_CONF_ENV
+		.to_str()
+		.map_err(|_| Error::invalid_utf8())?;
+	let environment =
+		env::var_os(name).map_or_else(String::new, |value| value.to_string_lossy().into_owned());
+
+	Ok([
+		(Source::Builtin, builtin.to_string_lossy().into_owned()),
+		(
+			Source::Linked,
+			linked.map_or_else(String::new, |text| text.to_string_lossy().into_owned()),
+		),
+		(Source::Environment, environment),
+	])
+}
+
+/// Reads the effective value of `key` and compares it with `requested`.
+fn inspect(key: &str, requested: &str) -> Result<(Option<String>, Outcome)> {
+	let name = format!("opt.{key}");
+	let Some(effective) = Effective::read(&name) else {
+		let outcome = if CONF_ONLY.contains(&key) || raw::mibs(&name).is_ok() {
+			Outcome::Unverified
+		} else {
+			Outcome::Unknown
+		};
+
+		return Ok((None, outcome));
+	};
+
+	let Some(effective) = effective? else {
+		return Ok((None, Outcome::Unsupported));
+	};
+
+	let outcome = if matches(key, effective, requested) {
+		Outcome::Applied
+	} else {
+		Outcome::Ignored
+	};
+
+	Ok((Some(effective.to_string()), outcome))
+}
+
+/// Reports whether `requested` denotes the effective value of `key`.
+fn matches(key: &str, effective: Value, requested: &str) -> bool {
+	if let Value::Unsigned(value) = effective
+		&& FIXED_POINT.contains(&key)
+	{
+		return parse_fixed_point(requested) == Some(value);
+	}
+
+	let integer = |value: i128| parse_integer(requested) == Some(value);
+	match effective {
+		| Value::Bool(value) => requested == if value { "true" } else { "false" },
+		| Value::Size(value) => i128::try_from(value).is_ok_and(integer),
+		| Value::SSize(value) => i128::try_from(value).is_ok_and(integer),
+		| Value::U64(value) => integer(value.into()),
+		| Value::I64(value) => integer(value.into()),
+		| Value::Unsigned(value) => integer(value.into()),
+		| Value::Str(value) => value.to_bytes() == requested.as_bytes(),
+	}
+}
+
+/// Parses a fixed-point option as jemalloc does: `-1`, or a nonnegative
+/// decimal number whose fraction is truncated to 16 bits.
+fn parse_fixed_point(text: &str) -> Option<u32> {
+	if text == "-1" {
+		return Some(u32::MAX);
+	}
+
+	let (integer, fraction) = text.split_once('.').unwrap_or((text, ""));
+	let all_digits = |digits: &str| digits.bytes().all(|byte| byte.is_ascii_digit());
+	if integer.is_empty() || !all_digits(integer) || !all_digits(fraction) {
+		return None;
+	}
+
+	let integer = u32::try_from(integer.parse::<u64>().ok()?).ok()?;
+	let (numerator, denominator) =
+		fraction
+			.bytes()
+			.take(12)
+			.fold((0_u64, 1_u64), |(numerator, denominator), digit| {
+				(numerator * 10 + u64::from(digit - b'0'), denominator * 10)
+			});
+	let fraction = u32::try_from((numerator << 16) / denominator).ok()?;
+
+	integer
+		.checked_mul(1 << 16)?
+		.checked_add(fraction)
+}
+
+/// Parses an integer with the base prefixes accepted by `strtoimax`.
+fn parse_integer(text: &str) -> Option<i128> {
+	let (negative, digits) = match text.as_bytes().first() {
+		| Some(b'-') => (true, &text[1..]),
+		| Some(b'+') => (false, &text[1..]),
+		| _ => (false, text),
+	};
+
+	let (radix, digits) = if let Some(hex) = digits
+		.strip_prefix("0x")
+		.or_else(|| digits.strip_prefix("0X"))
+	{
+		(16, hex)
+	} else if digits.len() > 1 && digits.starts_with('0') {
+		(8, &digits[1..])
+	} else {
+		(10, digits)
+	};
+
+	if !digits
+		.bytes()
+		.next()
+		.is_some_and(|byte| byte.is_ascii_alphanumeric())
+	{
+		return None;
+	}
+
+	let magnitude = i128::from_str_radix(digits, radix).ok()?;
+	Some(if negative { -magnitude } else { magnitude })
+}
+
+#[cfg(test)]
+mod tests {
+	//! Checks value comparison in each control type.
+
+	use super::*;
+
+	/// Accepts jemalloc's integer spellings.
+	#[test]
+	fn integers_follow_strtoimax() {
+		assert_eq!(parse_integer("42"), Some(42));
+		assert_eq!(parse_integer("0x1F"), Some(31));
+		assert_eq!(parse_integer("010"), Some(8));
+		assert_eq!(parse_integer("-1"), Some(-1));
+		assert_eq!(parse_integer("0"), Some(0));
+		assert_eq!(parse_integer(""), None);
+		assert_eq!(parse_integer("--1"), None);
+		assert_eq!(parse_integer("4k"), None);
+	}
+
+	/// Compares requests in the type of the control.
+	#[test]
+	fn values_match_by_type() {
+		assert!(matches("abort", Value::Bool(true), "true"));
+		assert!(!matches("abort", Value::Bool(true), "1"));
+		assert!(matches("narenas", Value::Unsigned(4096), "0x1000"));
+		assert!(matches("dirty_decay_ms", Value::SSize(-1), "-1"));
+		assert!(matches("tcache_max", Value::Size(1 << 20), "1048576"));
+		assert!(matches("thp", Value::Str(c"never"), "never"));
+		assert!(!matches("thp", Value::Str(c"never"), "Never"));
+	}
+
+	/// Reads fixed-point options as jemalloc's 16.16 representation.
+	#[test]
+	fn fixed_point_options_match() {
+		assert!(matches("hpa_dirty_mult", Value::Unsigned(0x4000), "0.25"));
+		assert!(matches("hpa_dirty_mult", Value::Unsigned(0x1_0000), "1"));
+		assert!(matches("hpa_dirty_mult", Value::Unsigned(u32::MAX), "-1"));
+		assert!(!matches("hpa_dirty_mult", Value::Unsigned(0x4000), "0.5"));
+		assert_eq!(parse_fixed_point("0.1"), Some(6553));
+		assert_eq!(parse_fixed_point("1.5"), Some(0x1_8000));
+		assert_eq!(parse_fixed_point("-2"), None);
+		assert_eq!(parse_fixed_point(".5"), None);
+		assert_eq!(parse_fixed_point("65536"), None);
+	}
+}
@@ -0,0 +1,590 @@
+//! Compile-time construction of the linked `malloc_conf` string.
+
+use core::str;
+
+use super::{MetadataThp, PercpuArena, Thp};
+
+/// Defines a `const` setter for one option field.
+///
+/// Each setter rejects a second assignment, and an optional predicate rejects
+/// out-of-range values. Both checks panic, which is a compile error when the
+/// builder is evaluated in a constant.
+macro_rules! setters {
+	($(
+		$(#[$meta:meta])*
+		$name:ident: $value:ty $(where |$arg:ident| $check:expr => $message:literal)?;
+	)*) => {$(
+		$(#[$meta])*
+		#[must_use]
+		pub const fn $name(mut self, value: $value) -> Self {
+			assert!(
+				self.$name.is_none(),
+				concat!("`", stringify!($name), "` is set more than once")
+			);
+			$(
+				let $arg = value;
+				assert!($check, $message);
+			)?
+			self.$name = Some(value);
+			self
+		}
+	)*};
+}
+
+/// Storage for a rendered option string, including its NUL terminator.
+pub const MALLOC_CONF_CAPACITY: usize = 1024;
+
+/// Option bytes accepted by `stats_print_opts`.
+const STATS_PRINT_OPTIONS: &[u8] = b"Jgmdablxeh";
+
+/// A `const` builder for jemalloc's startup option string.
+///
+/// Each setter records one `opt.*` value, and [`render`] produces the
+/// comma-separated `name:value` string that jemalloc parses. Setting an
+/// option twice, passing an out-of-range value, or combining options that
+/// contradict one another panics; in a constant, such as the one
+/// [`malloc_conf!`] evaluates, that panic is a compile error:
+///
+/// ```compile_fail
+/// use jevmalloc::opt::MallocConf;
+///
+/// jevmalloc::malloc_conf!(MallocConf::new().tcache(false).tcache_max(4096));
+/// ```
+///
+/// Profiling options exist only with the `profiling` feature and require
+/// [`prof(true)`](Self::prof).
+///
+/// [`render`]: Self::render
+/// [`malloc_conf!`]: crate::malloc_conf
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MallocConf {
+	/// `abort`.
+	abort: Option<bool>,
+
+	/// `abort_conf`.
+	abort_conf: Option<bool>,
+
+	/// `narenas`.
+	narenas: Option<u32>,
+
+	/// `percpu_arena`.
+	percpu_arena: Option<PercpuArena>,
+
+	/// `dirty_decay_ms`.
+	dirty_decay_ms: Option<i64>,
+
+	/// `muzzy_decay_ms`.
+	muzzy_decay_ms: Option<i64>,
+
+	/// `retain`.
+	retain: Option<bool>,
+
+	/// `thp`.
+	thp: Option<Thp>,
+
+	/// `metadata_thp`.
+	metadata_thp: Option<MetadataThp>,
+
+	/// `background_thread`.
+	background_thread: Option<bool>,
+
+	/// `max_background_threads`.
+	max_background_threads: Option<usize>,
+
+	/// `tcache`.
+	tcache: Option<bool>,
+
+	/// `tcache_max`.
+	tcache_max: Option<usize>,
+
+	/// `stats_print`.
+	stats_print: Option<bool>,
+
+	/// `stats_print_opts`.
+	stats_print_opts: Option<&'static str>,
+
+	/// `prof`.
+	#[cfg(feature = "profiling")]
+	prof: Option<bool>,
+
+	/// `prof_active`.
+	#[cfg(feature = "profiling")]
+	prof_active: Option<bool>,
+
+	/// `prof_thread_active_init`.
+	#[cfg(feature = "profiling")]
+	prof_thread_active_init: Option<bool>,
+
+	/// `prof_prefix`.
+	#[cfg(feature = "profiling")]
+	prof_prefix: Option<&'static str>,
+
+	/// `lg_prof_sample`.
+	#[cfg(feature = "profiling")]
+	lg_prof_sample: Option<u32>,
+
+	/// `lg_prof_interval`.
+	#[cfg(feature = "profiling")]
+	lg_prof_interval: Option<i32>,
+
+	/// `prof_accum`.
+	#[cfg(feature = "profiling")]
+	prof_accum: Option<bool>,
+
+	/// `prof_gdump`.
+	#[cfg(feature = "profiling")]
+	prof_gdump: Option<bool>,
+
+	/// `prof_final`.
+	#[cfg(feature = "profiling")]
+	prof_final: Option<bool>,
+
+	/// `prof_leak`.
+	#[cfg(feature = "profiling")]
+	prof_leak: Option<bool>,
+}
+
+/// A rendered, NUL-terminated option string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConfString {
+	/// Rendered bytes followed by zero padding.
+	bytes: [u8; MALLOC_CONF_CAPACITY],
+
+	/// Length of the rendered text, excluding the terminator.
+	len: usize,
+}
+
+impl MallocConf {
+	setters! {
+		/// Sets whether most warnings abort the process.
+		abort: bool;
+
+		/// Sets whether invalid runtime configuration aborts the process.
+		abort_conf: bool;
+
+		/// Sets the maximum number of automatically managed arenas.
+		narenas: u32 where |narenas| narenas > 0 => "`narenas` must be positive";
+
+		/// Sets the per-CPU arena mode.
+		percpu_arena: PercpuArena;
+
+		/// Sets the dirty-page decay interval in milliseconds, or `-1` to
+		/// disable decay.
+		dirty_decay_ms: i64 where |ms| ms >= -1 => "`dirty_decay_ms` must be at least -1";
+
+		/// Sets the muzzy-page decay interval in milliseconds, or `-1` to
+		/// disable decay.
+		muzzy_decay_ms: i64 where |ms| ms >= -1 => "`muzzy_decay_ms` must be at least -1";
+
+		/// Sets whether unused virtual memory is retained for later reuse.
+		retain: bool;
+
+		/// Sets the transparent-huge-page mode for application memory.
+		thp: Thp where |mode| !matches!(mode, Thp::NotSupported)
+			=> "`thp` cannot be set to `not supported`";
+
+		/// Sets the transparent-huge-page mode for allocator metadata.
+		metadata_thp: MetadataThp;
+
+		/// Sets whether background worker threads start enabled.
+		background_thread: bool;
+
+		/// Sets the maximum number of background worker threads.
+		max_background_threads: usize
+			where |threads| threads > 0 => "`max_background_threads` must be positive";
+
+		/// Sets whether automatic thread caches are enabled.
+		tcache: bool;
+
+		/// Sets the largest size class served from thread caches.
+		tcache_max: usize;
+
+		/// Sets whether statistics are printed at exit.
+		stats_print: bool;
+
+		/// Sets the options applied to printed statistics reports.
+		stats_print_opts: &'static str where |opts| valid_stats_print_opts(opts)
+			=> "`stats_print_opts` accepts only the characters `Jgmdablxeh`";
+	}
+
+	#[cfg(feature = "profiling")]
+	setters! {
+		/// Sets whether heap profiling is enabled.
+		prof: bool;
+
+		/// Sets whether sampling is active once profiling is enabled.
+		prof_active: bool;
+
+		/// Sets the initial sampling state of new threads.
+		prof_thread_active_init: bool;
+
+		/// Sets the path prefix of profile dumps.
+		prof_prefix: &'static str where |prefix| valid_string(prefix)
+			=> "`prof_prefix` must be nonempty and contain no `,`, `:`, or NUL";
+
+		/// Sets the base-2 logarithm of the mean sampling interval in bytes.
+		lg_prof_sample: u32 where |lg| lg < 64 => "`lg_prof_sample` must be below 64";
+
+		/// Sets the base-2 logarithm of the interval between automatic dumps,
+		/// or `-1` to disable them.
+		lg_prof_interval: i32 where |lg| lg >= -1 && lg < 64
+			=> "`lg_prof_interval` must be between -1 and 63";
+
+		/// Sets whether cumulative profiles are retained.
+		prof_accum: bool;
+
+		/// Sets whether a profile is dumped when total virtual memory peaks.
+		prof_gdump: bool;
+
+		/// Sets whether a final profile is dumped at exit.
+		prof_final: bool;
+
+		/// Sets whether leaks are reported at exit.
+		prof_leak: bool;
+	}
+
+	/// Creates a builder with every option left at jemalloc's default.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			abort: None,
+			abort_conf: None,
+			narenas: None,
+			percpu_arena: None,
+			dirty_decay_ms: None,
+			muzzy_decay_ms: None,
+			retain: None,
+			thp: None,
+			metadata_thp: None,
+			background_thread: None,
+			max_background_threads: None,
+			tcache: None,
+			tcache_max: None,
+			stats_print: None,
+			stats_print_opts: None,
+			#[cfg(feature = "profiling")]
+			prof: None,
+			#[cfg(feature = "profiling")]
+			prof_active: None,
+			#[cfg(feature = "profiling")]
+			prof_thread_active_init: None,
+			#[cfg(feature = "profiling")]
+			prof_prefix: None,
+			#[cfg(feature = "profiling")]
+			lg_prof_sample: None,
+			#[cfg(feature = "profiling")]
+			lg_prof_interval: None,
+			#[cfg(feature = "profiling")]
+			prof_accum: None,
+			#[cfg(feature = "profiling")]
+			prof_gdump: None,
+			#[cfg(feature = "profiling")]
+			prof_final: None,
+			#[cfg(feature = "profiling")]
+			prof_leak: None,
+		}
+	}
+
+	/// Validates option combinations and renders the option string.
+	///
+	/// # Panics
+	///
+	/// Panics if the options contradict one another or the string exceeds
+	/// [`MALLOC_CONF_CAPACITY`].
+	#[must_use]
+	pub const fn render(&self) -> ConfString {
+		self.validate();
+
+		let mut out = ConfString { bytes: [0; MALLOC_CONF_CAPACITY], len: 0 };
+		out.bool("abort", self.abort);
+		out.bool("abort_conf", self.abort_conf);
+		if let Some(narenas) = self.narenas {
+			out.unsigned("narenas", narenas as u64);
+		}
+		if let Some(mode) = self.percpu_arena {
+			out.string("percpu_arena", mode.as_str());
+		}
+		out.signed("dirty_decay_ms", self.dirty_decay_ms);
+		out.signed("muzzy_decay_ms", self.muzzy_decay_ms);
+		out.bool("retain", self.retain);
+		if let Some(mode) = self.thp {
+			out.string("thp", mode.as_str());
+		}
+		if let Some(mode) = self.metadata_thp {
+			out.string("metadata_thp", mode.as_str());
+		}
+		out.bool("background_thread", self.background_thread);
+		if let Some(threads) = self.max_background_threads {
+			out.unsigned("max_background_threads", threads as u64);
+		}
+		out.bool("tcache", self.tcache);
+		if let Some(size) = self.tcache_max {
+			out.unsigned("tcache_max", size as u64);
+		}
+		out.bool("stats_print", self.stats_print);
+		if let Some(opts) = self.stats_print_opts {
+			out.string("stats_print_opts", opts);
+		}
+
+		#[cfg(feature = "profiling")]
+		self.render_prof(&mut out);
+
+		out
+	}
+
+	/// Renders the profiling options.
+	#[cfg(feature = "profiling")]
+	const fn render_prof(&self, out: &mut ConfString) {
+		out.bool("prof", self.prof);
+		out.bool("prof_active", self.prof_active);
+		out.bool("prof_thread_active_init", self.prof_thread_active_init);
+		if let Some(prefix) = self.prof_prefix {
+			out.string("prof_prefix", prefix);
+		}
+		if let Some(lg) = self.lg_prof_sample {
+			out.unsigned("lg_prof_sample", lg as u64);
+		}
+		if let Some(lg) = self.lg_prof_interval {
+			out.signed("lg_prof_interval", Some(lg as i64));
+		}
+		out.bool("prof_accum", self.prof_accum);
+		out.bool("prof_gdump", self.prof_gdump);
+		out.bool("prof_final", self.prof_final);
+		out.bool("prof_leak", self.prof_leak);
+	}
+
+	/// Rejects options that contradict one another.
+	const fn validate(&self) {
+		assert!(
+			!(matches!(self.background_thread, Some(false))
+				&& self.max_background_threads.is_some()),
+			"`max_background_threads` requires background threads"
+		);
+		assert!(
+			!(matches!(self.tcache, Some(false)) && self.tcache_max.is_some()),
+			"`tcache_max` requires thread caches"
+		);
+
+		#[cfg(feature = "profiling")]
+		assert!(
+			matches!(self.prof, Some(true))
+				|| (self.prof_active.is_none()
+					&& self.prof_thread_active_init.is_none()
+					&& self.prof_prefix.is_none()
+					&& self.lg_prof_sample.is_none()
+					&& self.lg_prof_interval.is_none()
+					&& self.prof_accum.is_none()
+					&& self.prof_gdump.is_none()
+					&& self.prof_final.is_none()
+					&& self.prof_leak.is_none()),
+			"profiling options require `prof(true)`"
+		);
+	}
+}
+
+impl ConfString {
+	/// Returns the rendered option string.
+	#[must_use]
+	pub const fn as_str(&self) -> &str {
+		match str::from_utf8(self.bytes.split_at(self.len).0) {
+			| Ok(text) => text,
+			| Err(_) => unreachable!(),
+		}
+	}
+
+	/// Returns the rendered bytes followed by their NUL terminator.
+	#[must_use]
+	pub const fn as_bytes_with_nul(&self) -> &[u8] { self.bytes.split_at(self.len + 1).0 }
+
+	/// Appends a boolean option when it is set.
+	const fn bool(&mut self, name: &str, value: Option<bool>) {
+		match value {
+			| Some(true) => self.string(name, "true"),
+			| Some(false) => self.string(name, "false"),
+			| None => {},
+		}
+	}
+
+	/// Appends a signed option when it is set.
+	const fn signed(&mut self, name: &str, value: Option<i64>) {
+		let Some(value) = value else {
+			return;
+		};
+
+		self.key(name);
+		if value < 0 {
+			self.push(b"-");
+		}
+		self.digits(value.unsigned_abs());
+	}
+
+	/// Appends an unsigned option.
+	const fn unsigned(&mut self, name: &str, value: u64) {
+		self.key(name);
+		self.digits(value);
+	}
+
+	/// Appends a string option.
+	const fn string(&mut self, name: &str, value: &str) {
+		self.key(name);
+		self.push(value.as_bytes());
+	}
+
+	/// Appends the separator, if needed, and `name:`.
+	const fn key(&mut self, name: &str) {
+		if self.len > 0 {
+			self.push(b",");
+		}
+		self.push(name.as_bytes());
+		self.push(b":");
+	}
+
+	/// Appends the decimal digits of `value`.
+	const fn digits(&mut self, mut value: u64) {
+		let mut digits = [0_u8; 20];
+		let mut start = digits.len();
+		loop {
+			start -= 1;
+			digits[start] = b'0' + (value % 10) as u8;
+			value /= 10;
+			if value == 0 {
+				break;
+			}
+		}
+
+		self.push(digits.split_at(start).1);
+	}
+
+	/// Appends raw bytes, always leaving room for the terminator.
+	const fn push(&mut self, bytes: &[u8]) {
+		assert!(
+			self.len + bytes.len() < MALLOC_CONF_CAPACITY,
+			"the `malloc_conf` string exceeds `MALLOC_CONF_CAPACITY`"
+		);
+
+		let mut i = 0;
+		while i < bytes.len() {
+			self.bytes[self.len + i] = bytes[i];
+			i += 1;
+		}
+		self.len += bytes.len();
+	}
+}
+
+/// Exports a [`MallocConf`] as the `malloc_conf` string jemalloc reads before
+/// `main`.
+///
+/// The builder is evaluated in a constant, so invalid options fail to
+/// compile. The symbol name follows the linked jemalloc's prefix regime. Use
+/// the macro at most once per binary; a second definition is a link error.
+///
+/// ```
+/// use jevmalloc::opt::MallocConf;
+///
+/// jevmalloc::malloc_conf!(MallocConf::new().narenas(2).retain(true));
+///
+/// # fn main() {
+/// assert_eq!(jevmalloc::opt::narenas().unwrap(), 2);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! malloc_conf {
+	($conf:expr $(,)?) => {
+		const _: () = {
+			/// The rendered option string.
+			static CONF: $crate::opt::ConfString = $crate::opt::MallocConf::render(&$conf);
+
+			$crate::ffi::__export_malloc_conf!(CONF.as_bytes_with_nul());
+		};
+	};
+}
+
+/// Reports whether every byte is a recognized statistics print option.
+const fn valid_stats_print_opts(opts: &str) -> bool {
+	let bytes = opts.as_bytes();
+	let mut i = 0;
+	while i < bytes.len() {
+		let mut j = 0;
+		while j < STATS_PRINT_OPTIONS.len() && STATS_PRINT_OPTIONS[j] != bytes[i] {
+			j += 1;
+		}
+		if j == STATS_PRINT_OPTIONS.len() {
+			return false;
+		}
+		i += 1;
+	}
+
+	true
+}
+
+/// Reports whether a string value is nonempty and free of separators.
+#[cfg(feature = "profiling")]
+const fn valid_string(value: &str) -> bool {
+	let bytes = value.as_bytes();
+	let mut i = 0;
+	while i < bytes.len() {
+		if matches!(bytes[i], b',' | b':' | 0) {
+			return false;
+		}
+		i += 1;
+	}
+
+	!bytes.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+	//! Checks rendering and validation of the option string.
+
+	use super::*;
+
+	/// Renders options in a stable order with signed and unsigned values.
+	#[test]
+	fn renders_options_in_order() {
+		const CONF: ConfString = MallocConf::new()
+			.tcache_max(4096)
+			.narenas(3)
+			.dirty_decay_ms(-1)
+			.thp(Thp::Never)
+			.abort(true)
+			.stats_print_opts("Jm")
+			.render();
+
+		assert_eq!(
+			CONF.as_str(),
+			"abort:true,narenas:3,dirty_decay_ms:-1,thp:never,tcache_max:4096,stats_print_opts:\
+			 Jm"
+		);
+		assert_eq!(CONF.as_bytes_with_nul().last(), Some(&0));
+	}
+
+	/// Renders nothing for an empty builder.
+	#[test]
+	fn empty_builder_renders_empty_string() {
+		assert_eq!(MallocConf::new().render().as_bytes_with_nul(), b"\0");
+	}
+
+	/// Rejects contradictory options.
+	#[test]
+	#[should_panic = "`tcache_max` requires thread caches"]
+	fn rejects_contradictions() {
+		let conf = MallocConf::new().tcache(false).tcache_max(1);
+		assert_eq!(conf.render().as_str(), "");
+	}
+
+	/// Rejects repeated options.
+	#[test]
+	#[should_panic = "`narenas` is set more than once"]
+	fn rejects_repeats() {
+		assert_eq!(MallocConf::new().narenas(1).narenas(2), MallocConf::new());
+	}
+
+	/// Rejects profiling options without profiling enabled.
+	#[cfg(feature = "profiling")]
+	#[test]
+	#[should_panic = "profiling options require `prof(true)`"]
+	fn rejects_prof_options_without_prof() {
+		let conf = MallocConf::new().prof_active(true);
+		assert_eq!(conf.render().as_str(), "");
+	}
+}
@@ -0,0 +1,171 @@
+//! Typed values of enumerated startup options.
+
+use core::{fmt, str::FromStr};
+
+use crate::ctl::{Error, Result};
+
+/// Implements jemalloc's spelling of each variant of an option enum.
+macro_rules! spellings {
+	($name:ident { $($variant:ident => $text:literal),* $(,)? }) => {
+		impl $name {
+			/// Returns the option value accepted by jemalloc.
+			pub(crate) const fn as_str(self) -> &'static str {
+				match self {
+					$(| Self::$variant => $text,)*
+				}
+			}
+		}
+
+		impl FromStr for $name {
+			type Err = Error;
+
+			fn from_str(value: &str) -> Result<Self> {
+				match value {
+					$(| $text => Ok(Self::$variant),)*
+					| _ => Err(Error::invalid_argument()),
+				}
+			}
+		}
+
+		impl fmt::Display for $name {
+			fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(self.as_str()) }
+		}
+	};
+}
+
+/// Transparent-huge-page mode for application memory.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Thp {
+	/// Leave the system's huge-page setting unchanged.
+	Default,
+
+	/// Advise the kernel to back arenas with huge pages.
+	Always,
+
+	/// Advise the kernel not to back arenas with huge pages.
+	Never,
+
+	/// The kernel lacks transparent huge pages, or jemalloc could not read
+	/// `/sys/kernel/mm/transparent_hugepage/enabled`. Jemalloc reports this
+	/// mode but does not accept it as an option.
+	NotSupported,
+}
+
+/// Transparent-huge-page mode for allocator metadata.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MetadataThp {
+	/// Never use huge pages for metadata.
+	Disabled,
+
+	/// Use huge pages for metadata once it grows large enough.
+	Auto,
+
+	/// Always use huge pages for metadata.
+	Always,
+}
+
+/// Per-CPU arena assignment mode.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PercpuArena {
+	/// Assign threads to arenas round-robin.
+	Disabled,
+
+	/// Use one arena per CPU.
+	Percpu,
+
+	/// Use one arena per physical CPU, sharing it between hyperthreads.
+	Phycpu,
+}
+
+/// Behavior of `realloc(ptr, 0)` for a non-null `ptr`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ZeroRealloc {
+	/// Free `ptr` and return a minimal allocation.
+	Alloc,
+
+	/// Free `ptr` and return null.
+	Free,
+
+	/// Abort the process.
+	Abort,
+}
+
+/// Junk filling of allocated and freed memory.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Junk {
+	/// Never fill memory.
+	Disabled,
+
+	/// Fill new allocations with `0xa5`.
+	Alloc,
+
+	/// Fill freed memory with `0x5a`.
+	Free,
+
+	/// Fill both new allocations and freed memory.
+	Both,
+}
+
+spellings!(Thp {
+	Default => "default",
+	Always => "always",
+	Never => "never",
+	NotSupported => "not supported",
+});
+
+spellings!(MetadataThp {
+	Disabled => "disabled",
+	Auto => "auto",
+	Always => "always",
+});
+
+spellings!(PercpuArena {
+	Disabled => "disabled",
+	Percpu => "percpu",
+	Phycpu => "phycpu",
+});
+
+spellings!(ZeroRealloc {
+	Alloc => "alloc",
+	Free => "free",
+	Abort => "abort",
+});
+
+spellings!(Junk {
+	Disabled => "false",
+	Alloc => "alloc",
+	Free => "free",
+	Both => "true",
+});
+
+#[cfg(test)]
+mod tests {
+	//! Checks that parsing round-trips jemalloc's spellings.
+
+	use super::*;
+
+	/// Round-trips every variant and rejects other spellings.
+	#[test]
+	fn spellings_round_trip() {
+		for thp in [Thp::Default, Thp::Always, Thp::Never, Thp::NotSupported] {
+			assert_eq!(thp.as_str().parse::<Thp>().unwrap(), thp);
+		}
+		for junk in [Junk::Disabled, Junk::Alloc, Junk::Free, Junk::Both] {
+			assert_eq!(junk.as_str().parse::<Junk>().unwrap(), junk);
+		}
+
+		assert_eq!(Junk::Both.as_str(), "true");
+		assert_eq!("phycpu".parse::<PercpuArena>().unwrap(), PercpuArena::Phycpu);
+		assert!(
+			"Never"
+				.parse::<Thp>()
+				.unwrap_err()
+				.is(libc::EINVAL)
+		);
+		assert!(
+			"".parse::<ZeroRealloc>()
+				.unwrap_err()
+				.is(libc::EINVAL)
+		);
+	}
+}
@@ -0,0 +1,208 @@
+//! Tokenization of jemalloc option strings.
+
+use core::{error, fmt, iter::FusedIterator};
+
+/// One `key:value` pair from an option string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConfEntry<'a> {
+	/// Option name, such as `narenas`.
+	pub key: &'a str,
+
+	/// Raw option value, which may be empty.
+	pub value: &'a str,
+
+	/// Byte offset of the key within the parsed string.
+	pub offset: usize,
+}
+
+/// A syntax error jemalloc reports while parsing an option string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConfError {
+	/// What jemalloc rejected.
+	pub kind: ConfErrorKind,
+
+	/// Byte offset at which parsing stopped.
+	pub offset: usize,
+}
+
+/// Kinds of [`ConfError`], named after jemalloc's warnings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfErrorKind {
+	/// A key contains a byte other than an ASCII letter, digit, or `_`, or is
+	/// followed by `,` instead of `:`. Jemalloc ignores the rest of the string.
+	Malformed,
+
+	/// The string ends inside a key that has no `:`.
+	EndsWithKey,
+
+	/// The string ends with `,`. The preceding pair is still applied.
+	EndsWithComma,
+}
+
+/// Iterator over the pairs of an option string.
+///
+/// Pairs are yielded in the order jemalloc applies them. A syntax error is
+/// yielded once, after which the iterator is exhausted, because jemalloc stops
+/// reading the string at the same point.
+#[derive(Clone, Debug)]
+pub struct ConfEntries<'a> {
+	/// The complete string.
+	conf: &'a str,
+
+	/// Offset of the next unread byte, or `None` once exhausted.
+	cursor: Option<usize>,
+
+	/// An error detected after the latest pair, yielded on the next call.
+	pending: Option<ConfError>,
+}
+
+/// Splits an option string as jemalloc's own parser does.
+///
+/// Keys consist of ASCII letters, digits, and `_` and end at `:`. Values run
+/// to the next `,` and are not interpreted, so an unknown key or an invalid
+/// value parses successfully here and is rejected only by jemalloc's option
+/// handlers.
+///
+/// ```
+/// use jevmalloc::opt::{self, ConfEntry};
+///
+/// let mut entries = opt::parse("narenas:4,thp:never");
+///
+/// assert_eq!(entries.next(), Some(Ok(ConfEntry { key: "narenas", value: "4", offset: 0 })));
+/// assert_eq!(entries.next().unwrap().unwrap().value, "never");
+/// assert_eq!(entries.next(), None);
+/// ```
+#[must_use]
+pub const fn parse(conf: &str) -> ConfEntries<'_> {
+	ConfEntries { conf, cursor: Some(0), pending: None }
+}
+
+impl<'a> Iterator for ConfEntries<'a> {
+	type Item = Result<ConfEntry<'a>, ConfError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(error) = self.pending.take() {
+			return Some(Err(error));
+		}
+
+		let start = self.cursor?;
+		let bytes = self.conf.as_bytes();
+		if start == bytes.len() {
+			self.cursor = None;
+			return None;
+		}
+
+		let key_len = bytes[start..]
+			.iter()
+			.position(|&byte| !(byte.is_ascii_alphanumeric() || byte == b'_'))
+			.unwrap_or(bytes.len() - start);
+		let colon = start + key_len;
+
+		match bytes.get(colon) {
+			| Some(b':') => {},
+			| found => {
+				self.cursor = None;
+				let kind = if found.is_some() {
+					ConfErrorKind::Malformed
+				} else {
+					ConfErrorKind::EndsWithKey
+				};
+
+				return Some(Err(ConfError { kind, offset: colon }));
+			},
+		}
+
+		let value_start = colon + 1;
+		let value_end = bytes[value_start..]
+			.iter()
+			.position(|&byte| byte == b',')
+			.map_or(bytes.len(), |len| value_start + len);
+
+		self.cursor = Some(bytes.len().min(value_end + 1));
+		if value_end + 1 == bytes.len() {
+			self.pending = Some(ConfError {
+				kind: ConfErrorKind::EndsWithComma,
+				offset: value_end,
+			});
+		}
+
+		Some(Ok(ConfEntry {
+			key: &self.conf[start..colon],
+			value: &self.conf[value_start..value_end],
+			offset: start,
+		}))
+	}
+}
+
+impl FusedIterator for ConfEntries<'_> {}
+
+impl error::Error for ConfError {}
+
+impl fmt::Display for ConfError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let message = match self.kind {
+			| ConfErrorKind::Malformed => "malformed conf string",
+			| ConfErrorKind::EndsWithKey => "conf string ends with key",
+			| ConfErrorKind::EndsWithComma => "conf string ends with comma",
+		};
+
+		write!(f, "{message} at byte {}", self.offset)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	//! Checks tokenization against jemalloc's parser rules.
+
+	use super::*;
+
+	/// Collects every item of a parse.
+	fn items(conf: &str) -> [Option<Result<(&str, &str), ConfErrorKind>>; 4] {
+		let mut entries = parse(conf);
+		core::array::from_fn(|_| {
+			entries.next().map(|item| {
+				item.map(|entry| (entry.key, entry.value))
+					.map_err(|error| error.kind)
+			})
+		})
+	}
+
+	/// Splits pairs and keeps empty values and values with punctuation.
+	#[test]
+	fn splits_pairs() {
+		assert_eq!(items("abort:true,prof_prefix:/tmp/x.y,junk:"), [
+			Some(Ok(("abort", "true"))),
+			Some(Ok(("prof_prefix", "/tmp/x.y"))),
+			Some(Ok(("junk", ""))),
+			None
+		]);
+		assert_eq!(items(""), [None; 4]);
+	}
+
+	/// Reports a trailing comma after the final pair.
+	#[test]
+	fn reports_trailing_comma() {
+		assert_eq!(items("narenas:1,"), [
+			Some(Ok(("narenas", "1"))),
+			Some(Err(ConfErrorKind::EndsWithComma)),
+			None,
+			None
+		]);
+	}
+
+	/// Stops at malformed keys and unterminated keys.
+	#[test]
+	fn stops_at_key_errors() {
+		assert_eq!(items("a:1,b-c:2,d:3"), [
+			Some(Ok(("a", "1"))),
+			Some(Err(ConfErrorKind::Malformed)),
+			None,
+			None
+		]);
+		assert_eq!(items("a,b:1")[0], Some(Err(ConfErrorKind::Malformed)));
+		assert_eq!(items("a:1,tcache")[1], Some(Err(ConfErrorKind::EndsWithKey)));
+
+		let error = parse("x:1,y=2").nth(1).unwrap().unwrap_err();
+		assert_eq!(error.offset, 5);
+	}
+}
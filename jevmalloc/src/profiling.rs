@@ -4,7 +4,24 @@
 //! runtime profiling remains disabled unless allocator startup configuration
 //! enables `prof`. Controls that activate, reset, or dump profiling can return
 //! `ENOENT` while that runtime option is off.
+//!
+//! The backtrace and dump hooks let the application supply its own unwinder
+//! and observe each completed dump. Sample hooks observe individual sampled
+//! allocations and their release. With `opt.prof_stats` enabled,
+//! [`class_stats`] reports sampled totals for every size class.
+
+mod classes;
+mod hooks;
+mod sample;
 
+pub use self::{
+	classes::{ClassStats, ClassStatsIter, SampledTotals, SizeClass, class_stats},
+	hooks::{
+		BacktraceHook, DumpHook, RawBacktraceHook, RawDumpHook, set_backtrace_hook,
+		set_dump_hook, set_raw_backtrace_hook, set_raw_dump_hook,
+	},
+	sample::{Sample, SampleFn, SampleFreeFn, SampleHooks},
+};
 use crate::ctl::{Result, key, raw, value};
 
 /// Resets accumulated heap-profile statistics without changing the sample rate.
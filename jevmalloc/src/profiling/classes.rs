@@ -0,0 +1,175 @@
+//! Per-size-class sampled profiling statistics.
+//!
+//! With `opt.prof_stats` enabled, jemalloc tracks the sampled allocations of
+//! every size class in `prof.stats.bins.<j>` and `prof.stats.lextents.<j>`.
+//! Reading them is a handful of counter loads per class, far cheaper than
+//! producing and parsing a full profile dump.
+
+use core::iter::FusedIterator;
+
+use crate::{
+	arenas,
+	ctl::{Error, Key, Result, key, raw},
+};
+
+/// Sampled totals for one size class, laid out as jemalloc's `prof_stats_t`.
+///
+/// The values count sampled allocations only. They are not scaled by the
+/// sample interval.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SampledTotals {
+	/// Sum of the requested sizes of the sampled allocations in bytes.
+	pub requested_bytes: u64,
+
+	/// Number of sampled allocations.
+	pub count: u64,
+}
+
+/// The family and index of one jemalloc size class.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum SizeClass {
+	/// A small class served from slab bin `j`.
+	Bin(usize),
+
+	/// A large class served from extent class `j`.
+	Lextent(usize),
+}
+
+/// Sampled live and accumulated totals for one size class.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ClassStats {
+	/// The size class described.
+	pub class: SizeClass,
+
+	/// Allocation size of the class in bytes.
+	pub size: usize,
+
+	/// Sampled allocations of this class that have not been released.
+	pub live: SampledTotals,
+
+	/// Every sampled allocation of this class since profiling began.
+	pub accum: SampledTotals,
+}
+
+/// Iterator over the sampled statistics of every size class.
+///
+/// Small classes are yielded first, followed by large classes, each in
+/// increasing size order. Every class is read when it is yielded, so values
+/// are not a consistent snapshot across classes.
+#[derive(Clone, Debug)]
+pub struct ClassStatsIter {
+	/// Template for `prof.stats.bins.<j>.live`.
+	bins_live: Key,
+
+	/// Template for `prof.stats.bins.<j>.accum`.
+	bins_accum: Key,
+
+	/// Template for `arenas.bin.<j>.size`.
+	bins_size: Key,
+
+	/// Template for `prof.stats.lextents.<j>.live`.
+	lextents_live: Key,
+
+	/// Template for `prof.stats.lextents.<j>.accum`.
+	lextents_accum: Key,
+
+	/// Template for `arenas.lextent.<j>.size`.
+	lextents_size: Key,
+
+	/// Number of small classes.
+	nbins: usize,
+
+	/// Total number of classes.
+	len: usize,
+
+	/// Position of the next class across both families.
+	next: usize,
+}
+
+/// Returns an iterator over the sampled statistics of every size class.
+///
+/// # Errors
+///
+/// Returns `ENOENT` when runtime profiling or `opt.prof_stats` is disabled, or
+/// another error if jemalloc rejects a size-class query.
+pub fn class_stats() -> Result<ClassStatsIter> {
+	let nbins = arenas::nbins()?;
+	let nlextents = arenas::nlextents()?;
+
+	Ok(ClassStatsIter {
+		bins_live: key::prof_stats_bins_live()?,
+		bins_accum: key::prof_stats_bins_accum()?,
+		bins_size: key::arenas_bin_size()?,
+		lextents_live: key::prof_stats_lextents_live()?,
+		lextents_accum: key::prof_stats_lextents_accum()?,
+		lextents_size: key::arenas_lextent_size()?,
+		nbins,
+		len: nbins
+			.checked_add(nlextents)
+			.ok_or_else(Error::invalid_argument)?,
+		next: 0,
+	})
+}
+
+impl ClassStatsIter {
+	/// Reads one class from the templates of its family.
+	fn read(&self, class: SizeClass) -> Result<ClassStats> {
+		let (index, live, accum, size) = match class {
+			| SizeClass::Bin(index) =>
+				(index, &self.bins_live, &self.bins_accum, &self.bins_size),
+			| SizeClass::Lextent(index) =>
+				(index, &self.lextents_live, &self.lextents_accum, &self.lextents_size),
+		};
+
+		// SAFETY: `arenas.bin.<j>.size` and `arenas.lextent.<j>.size` have the C
+		// output type `size_t`, and the index is below the family's count.
+		let size = unsafe { raw::get(&select(size, 2, index)) }?;
+
+		// SAFETY: `prof.stats.*.<j>.live` has the C output type `prof_stats_t`,
+		// which `SampledTotals` mirrors.
+		let live = unsafe { raw::get(&select(live, 3, index)) }?;
+
+		// SAFETY: `prof.stats.*.<j>.accum` has the C output type `prof_stats_t`,
+		// which `SampledTotals` mirrors.
+		let accum = unsafe { raw::get(&select(accum, 3, index)) }?;
+
+		Ok(ClassStats { class, size, live, accum })
+	}
+}
+
+impl Iterator for ClassStatsIter {
+	type Item = Result<ClassStats>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.next >= self.len {
+			return None;
+		}
+
+		let class = match self.next.checked_sub(self.nbins) {
+			| None => SizeClass::Bin(self.next),
+			| Some(index) => SizeClass::Lextent(index),
+		};
+
+		self.next = self.next.saturating_add(1);
+
+		Some(self.read(class))
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let remaining = self.len.saturating_sub(self.next);
+
+		(remaining, Some(remaining))
+	}
+}
+
+impl ExactSizeIterator for ClassStatsIter {}
+
+impl FusedIterator for ClassStatsIter {}
+
+/// Substitutes a class index into one MIB template.
+fn select(template: &Key, component: usize, index: usize) -> Key {
+	let mut key = template.clone();
+	key[component] = index;
+	key
+}
@@ -0,0 +1,256 @@
+//! Backtrace and dump hooks for the heap profiler.
+//!
+//! Jemalloc keeps one process-wide function pointer for each hook and passes
+//! no context argument. The safe setters therefore record a Rust callback in a
+//! static slot and install a fixed trampoline that reads it. The raw setters
+//! exchange C function pointers directly.
+
+use core::{
+	ffi::{CStr, c_char, c_uint, c_void},
+	mem::transmute_copy,
+	ptr::{null_mut, write_bytes},
+	slice,
+	sync::atomic::{AtomicPtr, Ordering},
+};
+
+use crate::ctl::{Result, key, raw};
+
+/// C signature of the `experimental.hooks.prof_backtrace` hook.
+///
+/// The hook stores up to `max_len` return addresses into `vec` and writes the
+/// number stored through `len`.
+pub type RawBacktraceHook =
+	unsafe extern "C" fn(vec: *mut *mut c_void, len: *mut c_uint, max_len: c_uint);
+
+/// C signature of the `experimental.hooks.prof_dump` hook.
+///
+/// The hook receives the NUL-terminated path of each completed profile dump.
+pub type RawDumpHook = unsafe extern "C" fn(filename: *const c_char);
+
+/// Rust unwinder invoked for each sampled allocation.
+///
+/// The callback fills the leading frames of the zeroed slice with return
+/// addresses, innermost first, and returns the number it stored. Larger
+/// counts are clamped to the slice length. It runs on the allocating thread
+/// with jemalloc's reentrancy guard raised, so any allocation it performs is
+/// serviced internally and never sampled. It must not unwind.
+pub type BacktraceHook = fn(frames: &mut [*mut c_void]) -> usize;
+
+/// Rust observer invoked after each completed profile dump.
+///
+/// The callback receives the dump's filename. It must not unwind.
+pub type DumpHook = fn(filename: &CStr);
+
+/// Rust unwinder read by [`backtrace`], or null when none is installed.
+static BACKTRACE: AtomicPtr<()> = AtomicPtr::new(null_mut());
+
+/// Unwinder displaced by the first installation of [`backtrace`].
+static DISPLACED_BACKTRACE: AtomicPtr<()> = AtomicPtr::new(null_mut());
+
+/// Rust dump observer read by [`dump`], or null when none is installed.
+static DUMP: AtomicPtr<()> = AtomicPtr::new(null_mut());
+
+/// Installs a Rust unwinder for profiling backtraces.
+///
+/// `Some` replaces jemalloc's configured unwinder, which is selected at build
+/// time and uses frame pointers with the `profiling_frameptr` feature. `None`
+/// reinstalls the unwinder that the first Rust installation displaced. The
+/// previous Rust unwinder is returned. Clearing an unwinder that was never
+/// installed performs no control operation.
+///
+/// # Errors
+///
+/// Returns `ENOENT` when runtime profiling is disabled, or another error if
+/// jemalloc rejects the exchange.
+pub fn set_backtrace_hook(hook: Option<BacktraceHook>) -> Result<Option<BacktraceHook>> {
+	let key = key::experimental_hooks_prof_backtrace()?;
+	let trampoline: RawBacktraceHook = backtrace;
+
+	// SAFETY: the slot holds only null or a `RawBacktraceHook` recorded from
+	// jemalloc's previous setting.
+	let displaced =
+		unsafe { load_fn::<RawBacktraceHook>(DISPLACED_BACKTRACE.load(Ordering::Acquire)) };
+	let replacement = match (hook, displaced) {
+		| (Some(_), _) => trampoline,
+		| (None, Some(displaced)) => displaced,
+		| (None, None) => return Ok(swap_fn(&BACKTRACE, hook)),
+	};
+
+	let previous = swap_fn(&BACKTRACE, hook);
+
+	// SAFETY: `experimental.hooks.prof_backtrace` exchanges non-null
+	// `prof_backtrace_hook_t` values. Both candidates remain valid for the life
+	// of the process and never unwind.
+	let displaced = match unsafe { raw::xchg(&key, &replacement) } {
+		| Ok(displaced) => displaced,
+		| Err(error) => {
+			swap_fn(&BACKTRACE, previous);
+			return Err(error);
+		},
+	};
+
+	if displaced as usize != trampoline as usize {
+		DISPLACED_BACKTRACE.store(displaced as *mut (), Ordering::Release);
+	}
+
+	Ok(previous)
+}
+
+/// Exchanges jemalloc's profiling backtrace hook with a C function.
+///
+/// The previous hook is returned and can be reinstalled later. Installing a
+/// raw hook bypasses any Rust unwinder set through [`set_backtrace_hook`].
+///
+/// # Errors
+///
+/// Returns `ENOENT` when runtime profiling is disabled, or another error if
+/// jemalloc rejects the exchange.
+///
+/// # Safety
+///
+/// `hook` must remain callable for the rest of the process, write at most
+/// `max_len` initialized pointers and a count no greater than `max_len`, and
+/// never unwind. Jemalloc can invoke it concurrently from every allocating
+/// thread.
+pub unsafe fn set_raw_backtrace_hook(hook: RawBacktraceHook) -> Result<RawBacktraceHook> {
+	let key = key::experimental_hooks_prof_backtrace()?;
+
+	// SAFETY: the control exchanges `prof_backtrace_hook_t` values and the
+	// caller guarantees the replacement's contract.
+	unsafe { raw::xchg(&key, &hook) }
+}
+
+/// Installs a Rust observer for completed profile dumps.
+///
+/// `None` removes the observer. The previous Rust observer is returned.
+///
+/// # Errors
+///
+/// Returns `ENOENT` when runtime profiling is disabled, or another error if
+/// jemalloc rejects the exchange.
+pub fn set_dump_hook(hook: Option<DumpHook>) -> Result<Option<DumpHook>> {
+	let key = key::experimental_hooks_prof_dump()?;
+	let trampoline: RawDumpHook = dump;
+	let replacement = hook.map(|_| trampoline);
+	let previous = swap_fn(&DUMP, hook);
+
+	// SAFETY: `experimental.hooks.prof_dump` exchanges nullable
+	// `prof_dump_hook_t` values. The trampoline is valid for the life of the
+	// process and never unwinds.
+	match unsafe { raw::xchg::<Option<RawDumpHook>>(&key, &replacement) } {
+		| Ok(_) => Ok(previous),
+		| Err(error) => {
+			swap_fn(&DUMP, previous);
+			Err(error)
+		},
+	}
+}
+
+/// Exchanges jemalloc's profile dump hook with a nullable C function.
+///
+/// The previous hook is returned. Installing a raw hook bypasses any Rust
+/// observer set through [`set_dump_hook`].
+///
+/// # Errors
+///
+/// Returns `ENOENT` when runtime profiling is disabled, or another error if
+/// jemalloc rejects the exchange.
+///
+/// # Safety
+///
+/// A non-null `hook` must remain callable for the rest of the process and
+/// never unwind.
+pub unsafe fn set_raw_dump_hook(hook: Option<RawDumpHook>) -> Result<Option<RawDumpHook>> {
+	let key = key::experimental_hooks_prof_dump()?;
+
+	// SAFETY: the control exchanges nullable `prof_dump_hook_t` values and the
+	// caller guarantees the replacement's contract.
+	unsafe { raw::xchg(&key, &hook) }
+}
+
+/// Forwards one backtrace request to the installed Rust unwinder.
+///
+/// When no Rust unwinder is installed, the request is forwarded to the
+/// displaced unwinder, or reported as an empty backtrace if none is known.
+unsafe extern "C" fn backtrace(vec: *mut *mut c_void, len: *mut c_uint, max_len: c_uint) {
+	let frames = max_len as usize;
+
+	// SAFETY: the slot holds only null or `BacktraceHook` values.
+	let Some(hook) = (unsafe { load_fn::<BacktraceHook>(BACKTRACE.load(Ordering::Acquire)) })
+	else {
+		// SAFETY: the slot holds only null or a `RawBacktraceHook`.
+		let displaced =
+			unsafe { load_fn::<RawBacktraceHook>(DISPLACED_BACKTRACE.load(Ordering::Acquire)) };
+
+		match displaced {
+			// SAFETY: jemalloc's arguments are forwarded to the hook it supplied.
+			| Some(displaced) => unsafe { displaced(vec, len, max_len) },
+
+			// SAFETY: jemalloc supplies a live count output.
+			| None => unsafe { len.write(0) },
+		}
+
+		return;
+	};
+
+	// SAFETY: jemalloc supplies `max_len` writable, aligned pointer slots. They
+	// are zeroed first so the slice never exposes uninitialized storage.
+	unsafe { write_bytes(vec, 0, frames) };
+
+	// SAFETY: the zeroed slots remain exclusively borrowed by jemalloc's
+	// caller until this hook returns.
+	let frames = unsafe { slice::from_raw_parts_mut(vec, frames) };
+
+	let stored = hook(frames).min(frames.len());
+	let stored = c_uint::try_from(stored).unwrap_or(max_len);
+
+	// SAFETY: jemalloc supplies a live count output, and the count is clamped
+	// to `max_len`.
+	unsafe { len.write(stored) };
+}
+
+/// Forwards one dump notification to the installed Rust observer.
+unsafe extern "C" fn dump(filename: *const c_char) {
+	// SAFETY: the slot holds only null or `DumpHook` values.
+	let Some(hook) = (unsafe { load_fn::<DumpHook>(DUMP.load(Ordering::Acquire)) }) else {
+		return;
+	};
+
+	if filename.is_null() {
+		return;
+	}
+
+	// SAFETY: jemalloc supplies a NUL-terminated path that remains live for the
+	// callback.
+	hook(unsafe { CStr::from_ptr(filename) });
+}
+
+/// Replaces a Rust callback slot and returns its previous occupant.
+///
+/// Each slot is accessed only through this function and [`load_fn`], always
+/// with the same function pointer type.
+pub(super) fn swap_fn<F: Copy>(slot: &AtomicPtr<()>, hook: Option<F>) -> Option<F> {
+	let hook = hook.map_or(null_mut(), |hook| {
+		// SAFETY: every `F` used here is a function pointer, which has the size
+		// and representation of a data pointer on supported targets.
+		unsafe { transmute_copy::<F, *mut ()>(&hook) }
+	});
+
+	// SAFETY: the slot holds only null or values of `F` stored above.
+	unsafe { load_fn(slot.swap(hook, Ordering::AcqRel)) }
+}
+
+/// Converts a stored function address back into its pointer type.
+///
+/// # Safety
+///
+/// `ptr` must be null or the address of a function of type `F`, and `F` must
+/// be a function pointer type.
+pub(super) unsafe fn load_fn<F: Copy>(ptr: *mut ()) -> Option<F> {
+	if ptr.is_null() {
+		return None;
+	}
+
+	// SAFETY: the caller guarantees that the non-null address is an `F`.
+	Some(unsafe { transmute_copy::<*mut (), F>(&ptr) })
+}
@@ -0,0 +1,183 @@
+//! Callbacks for allocations sampled by the heap profiler.
+//!
+//! Jemalloc reports each sampled allocation, with its backtrace, through
+//! `experimental.hooks.prof_sample` and reports the release of that same
+//! allocation through `experimental.hooks.prof_sample_free`. Unsampled
+//! allocations never reach either hook, so observing them costs nothing on the
+//! ordinary allocation path.
+
+use core::{
+	ffi::c_void,
+	ptr::{NonNull, null_mut},
+	slice,
+	sync::atomic::{AtomicBool, AtomicPtr, Ordering},
+};
+
+use libc::{c_uint, size_t};
+
+use super::hooks::{load_fn, swap_fn};
+use crate::ctl::{Error, Result, key, raw};
+
+/// One allocation selected by the heap profiler's sampler.
+#[derive(Clone, Copy, Debug)]
+pub struct Sample<'bt> {
+	/// Base address of the sampled allocation.
+	pub address: NonNull<u8>,
+
+	/// Usable size of the sampled allocation in bytes.
+	pub size: usize,
+
+	/// Return addresses captured for the allocation, innermost first.
+	pub backtrace: &'bt [*mut c_void],
+}
+
+/// Rust callback invoked for each sampled allocation.
+///
+/// The callback runs on the allocating thread after jemalloc records the
+/// sample, so it can read the application's thread-local context. Jemalloc
+/// raises its reentrancy guard first; any allocation the callback performs is
+/// serviced internally and never sampled. It must not unwind.
+pub type SampleFn = fn(sample: Sample<'_>);
+
+/// Rust callback invoked when a sampled allocation is released.
+///
+/// The callback receives the address and usable size reported to the matching
+/// [`SampleFn`] invocation. It runs on the releasing thread inside
+/// deallocation, should avoid allocating, and must not unwind.
+pub type SampleFreeFn = fn(address: NonNull<u8>, size: usize);
+
+/// C signature of the `experimental.hooks.prof_sample` hook.
+type RawSampleHook = unsafe extern "C" fn(
+	ptr: *const c_void,
+	size: size_t,
+	backtrace: *mut *mut c_void,
+	len: c_uint,
+);
+
+/// C signature of the `experimental.hooks.prof_sample_free` hook.
+type RawSampleFreeHook = unsafe extern "C" fn(ptr: *const c_void, size: size_t);
+
+/// Whether a [`SampleHooks`] guard currently owns both hooks.
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Rust sample callback read by [`sample`], or null when none is installed.
+static SAMPLE: AtomicPtr<()> = AtomicPtr::new(null_mut());
+
+/// Rust release callback read by [`sample_free`], or null when none is
+/// installed.
+static SAMPLE_FREE: AtomicPtr<()> = AtomicPtr::new(null_mut());
+
+/// An installed pair of sampled-allocation callbacks.
+///
+/// Jemalloc keeps one process-wide pair of hooks, so at most one guard can be
+/// live at a time. Dropping the guard removes both hooks. Allocations sampled
+/// while it was live but released afterward produce no release callback.
+#[must_use = "the sample hooks are removed when the guard is dropped"]
+#[derive(Debug)]
+pub struct SampleHooks {
+	/// Prevents construction outside [`SampleHooks::install`].
+	_private: (),
+}
+
+impl SampleHooks {
+	/// Installs callbacks for sampled allocations and their release.
+	///
+	/// The release hook is installed before the sample hook, so every sample
+	/// reported to `sample` can also reach `free`.
+	///
+	/// # Errors
+	///
+	/// Returns `EBUSY` while another guard is live, `ENOENT` when runtime
+	/// profiling is disabled, or another error if jemalloc rejects either
+	/// exchange. A failed installation leaves both hooks removed.
+	pub fn install(sample: SampleFn, free: Option<SampleFreeFn>) -> Result<Self> {
+		let sample_key = key::experimental_hooks_prof_sample()?;
+		let free_key = key::experimental_hooks_prof_sample_free()?;
+
+		if INSTALLED
+			.compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+			.is_err()
+		{
+			return Err(Error::busy());
+		}
+
+		let guard = Self { _private: () };
+		swap_fn(&SAMPLE, Some(sample));
+		swap_fn(&SAMPLE_FREE, free);
+
+		let sample_hook: RawSampleHook = self::sample;
+		let free_hook: RawSampleFreeHook = sample_free;
+		let free_hook = free.map(|_| free_hook);
+
+		// SAFETY: the control accepts a nullable `prof_sample_free_hook_t`. The
+		// trampoline remains valid for the life of the process and never unwinds.
+		unsafe { raw::set(&free_key, &free_hook) }?;
+
+		// SAFETY: the control accepts a nullable `prof_sample_hook_t`. The
+		// trampoline remains valid for the life of the process and never unwinds.
+		unsafe { raw::set(&sample_key, &Some(sample_hook)) }?;
+
+		Ok(guard)
+	}
+}
+
+impl Drop for SampleHooks {
+	fn drop(&mut self) {
+		let none: Option<RawSampleHook> = None;
+		let none_free: Option<RawSampleFreeHook> = None;
+
+		if let Ok(key) = key::experimental_hooks_prof_sample() {
+			// SAFETY: the control accepts a null `prof_sample_hook_t`.
+			let _: Result = unsafe { raw::set(&key, &none) };
+		}
+
+		if let Ok(key) = key::experimental_hooks_prof_sample_free() {
+			// SAFETY: the control accepts a null `prof_sample_free_hook_t`.
+			let _: Result = unsafe { raw::set(&key, &none_free) };
+		}
+
+		swap_fn::<SampleFn>(&SAMPLE, None);
+		swap_fn::<SampleFreeFn>(&SAMPLE_FREE, None);
+		INSTALLED.store(false, Ordering::Release);
+	}
+}
+
+/// Forwards one sampled allocation to the installed Rust callback.
+unsafe extern "C" fn sample(
+	ptr: *const c_void,
+	size: size_t,
+	backtrace: *mut *mut c_void,
+	len: c_uint,
+) {
+	// SAFETY: the slot holds only null or `SampleFn` values.
+	let Some(callback) = (unsafe { load_fn::<SampleFn>(SAMPLE.load(Ordering::Acquire)) }) else {
+		return;
+	};
+
+	let Some(address) = NonNull::new(ptr.cast_mut().cast::<u8>()) else {
+		return;
+	};
+
+	let backtrace = match NonNull::new(backtrace) {
+		// SAFETY: jemalloc supplies `len` initialized frames that remain live
+		// and unmodified for the callback.
+		| Some(backtrace) => unsafe { slice::from_raw_parts(backtrace.as_ptr(), len as usize) },
+		| None => &[],
+	};
+
+	callback(Sample { address, size, backtrace });
+}
+
+/// Forwards one sampled release to the installed Rust callback.
+unsafe extern "C" fn sample_free(ptr: *const c_void, size: size_t) {
+	// SAFETY: the slot holds only null or `SampleFreeFn` values.
+	let Some(callback) =
+		(unsafe { load_fn::<SampleFreeFn>(SAMPLE_FREE.load(Ordering::Acquire)) })
+	else {
+		return;
+	};
+
+	if let Some(address) = NonNull::new(ptr.cast_mut().cast::<u8>()) {
+		callback(address, size);
+	}
+}
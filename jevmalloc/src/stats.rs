@@ -5,12 +5,49 @@
 //! required. Getters do not refresh implicitly. `stats.zero_reallocs` is the
 //! sole global value in this interface that jemalloc reads directly rather
 //! than copying into the epoch snapshot.
+//!
+//! With the `stats` feature, [`Snapshot::capture`] performs that refresh and
+//! reads the global totals together, and [`Snapshot::delta`] derives changes
+//! and rates between two captures without allocating.
+//!
+//! With the `stats` feature, [`OpenMetrics`] writes global, per-arena, and
+//! optional per-size-class statistics in the `OpenMetrics` text format.
+//!
+//! With the `std` and `stats` features, [`Sampler`] records global and
+//! per-arena totals on a background thread and summarizes trailing windows.
+//!
+//! With the `alloc` feature, [`report`] decodes jemalloc's JSON report into a
+//! typed [`StatsReport`] covering arena, size-class, extent, and mutex
+//! statistics that have no fixed-name getter.
+
+#[cfg(feature = "alloc")]
+mod json;
+#[cfg(feature = "stats")]
+mod metrics;
+#[cfg(feature = "alloc")]
+mod report;
+#[cfg(all(feature = "std", feature = "stats"))]
+mod sampler;
+#[cfg(feature = "stats")]
+mod snapshot;
 
 use core::{
 	ffi::{CStr, c_char, c_void},
-	str,
+	fmt, str,
 };
 
+#[cfg(feature = "alloc")]
+pub use self::report::{
+	ArenaId, ArenaStats, BackgroundThreadStats, BinStats, ClassTotals, ExtentStats, GeneralStats,
+	LextentStats, MutexStats, PurgeStats, StatsReport, report,
+};
+#[cfg(all(feature = "std", feature = "stats"))]
+pub use self::sampler::{ArenaSample, Sample, Sampler, Summary, WindowStats};
+#[cfg(feature = "stats")]
+pub use self::{
+	metrics::OpenMetrics,
+	snapshot::{Delta, Rates, Snapshot},
+};
 use crate::{
 	ctl::{Error, Result, key, raw},
 	ffi,
@@ -144,6 +181,14 @@ impl<'buf> PrintBuffer<'buf> {
 	}
 }
 
+impl fmt::Write for PrintBuffer<'_> {
+	fn write_str(&mut self, text: &str) -> fmt::Result {
+		self.write(text.as_bytes());
+
+		if self.overflowed { Err(fmt::Error) } else { Ok(()) }
+	}
+}
+
 /// Prints an allocator statistics report through the supplied writer.
 ///
 /// Jemalloc invokes `write` synchronously with arbitrary byte fragments and
@@ -0,0 +1,421 @@
+//! Minimal JSON reader for jemalloc's `J` statistics report.
+//!
+//! The reader accepts standard JSON and builds an owned tree. It exists only
+//! to avoid a parsing dependency; it does not preserve number formatting or
+//! reject duplicate object keys, neither of which jemalloc produces.
+
+use alloc::{string::String, vec::Vec};
+use core::str;
+
+use crate::ctl::{Error, Result};
+
+/// Maximum container nesting accepted before the input is rejected.
+///
+/// Jemalloc's report nests six levels deep. The limit bounds recursion on
+/// malformed input.
+const DEPTH_LIMIT: usize = 32;
+
+/// One parsed JSON value.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) enum Value {
+	/// The `null` literal.
+	Null,
+
+	/// A `true` or `false` literal.
+	Bool(bool),
+
+	/// A numeric literal.
+	Number(Number),
+
+	/// A string with escapes resolved.
+	String(String),
+
+	/// An array in source order.
+	Array(Vec<Self>),
+
+	/// An object's members in source order.
+	Object(Vec<(String, Self)>),
+}
+
+/// A numeric literal in the narrowest representation that holds it exactly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum Number {
+	/// A nonnegative integer.
+	Unsigned(u64),
+
+	/// A negative integer.
+	Signed(i64),
+
+	/// Any other number.
+	Float(f64),
+}
+
+impl Value {
+	/// Returns the member named `key` of an object.
+	pub(super) fn get(&self, key: &str) -> Option<&Self> {
+		self.members()?
+			.iter()
+			.find_map(|(name, value)| (name == key).then_some(value))
+	}
+
+	/// Returns the members of an object.
+	pub(super) fn members(&self) -> Option<&[(String, Self)]> {
+		match self {
+			| Self::Object(members) => Some(members),
+			| _ => None,
+		}
+	}
+
+	/// Returns the elements of an array.
+	pub(super) fn elements(&self) -> Option<&[Self]> {
+		match self {
+			| Self::Array(elements) => Some(elements),
+			| _ => None,
+		}
+	}
+
+	/// Returns a string's contents.
+	pub(super) fn as_str(&self) -> Option<&str> {
+		match self {
+			| Self::String(string) => Some(string),
+			| _ => None,
+		}
+	}
+
+	/// Returns a nonnegative integer.
+	pub(super) const fn as_u64(&self) -> Option<u64> {
+		match self {
+			| Self::Number(Number::Unsigned(number)) => Some(*number),
+			| _ => None,
+		}
+	}
+
+	/// Returns an integer that fits in `i64`.
+	pub(super) fn as_i64(&self) -> Option<i64> {
+		match self {
+			| Self::Number(Number::Unsigned(number)) => i64::try_from(*number).ok(),
+			| Self::Number(Number::Signed(number)) => Some(*number),
+			| _ => None,
+		}
+	}
+}
+
+/// Parses one complete JSON document.
+///
+/// # Errors
+///
+/// Returns `EILSEQ` if `text` is not exactly one well-formed JSON value,
+/// optionally surrounded by whitespace, or nests deeper than [`DEPTH_LIMIT`].
+pub(super) fn parse(text: &str) -> Result<Value> {
+	let mut parser = Parser { bytes: text.as_bytes(), pos: 0 };
+	let value = parser.value(0)?;
+
+	parser.skip_whitespace();
+	if parser.pos != parser.bytes.len() {
+		return Err(Error::invalid_utf8());
+	}
+
+	Ok(value)
+}
+
+/// Cursor over the document being parsed.
+struct Parser<'text> {
+	/// The complete document.
+	bytes: &'text [u8],
+
+	/// Offset of the next unread byte.
+	pos: usize,
+}
+
+impl Parser<'_> {
+	/// Parses the value starting at the next non-whitespace byte.
+	fn value(&mut self, depth: usize) -> Result<Value> {
+		if depth > DEPTH_LIMIT {
+			return Err(Error::invalid_utf8());
+		}
+
+		self.skip_whitespace();
+		match self.peek() {
+			| Some(b'{') => self.object(depth),
+			| Some(b'[') => self.array(depth),
+			| Some(b'"') => self.string().map(Value::String),
+			| Some(b't') => self.literal("true", Value::Bool(true)),
+			| Some(b'f') => self.literal("false", Value::Bool(false)),
+			| Some(b'n') => self.literal("null", Value::Null),
+			| Some(b'-' | b'0'..=b'9') => self.number().map(Value::Number),
+			| _ => Err(Error::invalid_utf8()),
+		}
+	}
+
+	/// Parses an object whose opening brace is the next byte.
+	fn object(&mut self, depth: usize) -> Result<Value> {
+		let mut members = Vec::new();
+		self.expect(b'{')?;
+		self.skip_whitespace();
+		if self.eat(b'}') {
+			return Ok(Value::Object(members));
+		}
+
+		loop {
+			self.skip_whitespace();
+			let name = self.string()?;
+			self.skip_whitespace();
+			self.expect(b':')?;
+			members.push((name, self.value(depth.saturating_add(1))?));
+
+			self.skip_whitespace();
+			if self.eat(b'}') {
+				return Ok(Value::Object(members));
+			}
+
+			self.expect(b',')?;
+		}
+	}
+
+	/// Parses an array whose opening bracket is the next byte.
+	fn array(&mut self, depth: usize) -> Result<Value> {
+		let mut elements = Vec::new();
+		self.expect(b'[')?;
+		self.skip_whitespace();
+		if self.eat(b']') {
+			return Ok(Value::Array(elements));
+		}
+
+		loop {
+			elements.push(self.value(depth.saturating_add(1))?);
+
+			self.skip_whitespace();
+			if self.eat(b']') {
+				return Ok(Value::Array(elements));
+			}
+
+			self.expect(b',')?;
+		}
+	}
+
+	/// Parses a string whose opening quote is the next byte.
+	fn string(&mut self) -> Result<String> {
+		let mut string = String::new();
+		self.expect(b'"')?;
+
+		loop {
+			let start = self.pos;
+			while let Some(byte) = self.peek() {
+				if matches!(byte, b'"' | b'\\') || byte < 0x20 {
+					break;
+				}
+
+				self.pos = self.pos.saturating_add(1);
+			}
+
+			// The input is a `str`, and both delimiters are ASCII, so the run
+			// between them is valid UTF-8.
+			let run = str::from_utf8(&self.bytes[start..self.pos])
+				.map_err(|_| Error::invalid_utf8())?;
+			string.push_str(run);
+
+			match self.next() {
+				| Some(b'"') => return Ok(string),
+				| Some(b'\\') => string.push(self.escape()?),
+				| _ => return Err(Error::invalid_utf8()),
+			}
+		}
+	}
+
+	/// Decodes the escape sequence following a backslash.
+	fn escape(&mut self) -> Result<char> {
+		let escaped = match self.next() {
+			| Some(b'"') => '"',
+			| Some(b'\\') => '\\',
+			| Some(b'/') => '/',
+			| Some(b'b') => '\u{8}',
+			| Some(b'f') => '\u{c}',
+			| Some(b'n') => '\n',
+			| Some(b'r') => '\r',
+			| Some(b't') => '\t',
+			| Some(b'u') => return self.unicode_escape(),
+			| _ => return Err(Error::invalid_utf8()),
+		};
+
+		Ok(escaped)
+	}
+
+	/// Decodes a `\u` escape, combining a UTF-16 surrogate pair when present.
+	fn unicode_escape(&mut self) -> Result<char> {
+		let high = self.hex4()?;
+		if !(0xD800..0xDC00).contains(&high) {
+			return char::from_u32(high).ok_or_else(Error::invalid_utf8);
+		}
+
+		if !(self.eat(b'\\') && self.eat(b'u')) {
+			return Err(Error::invalid_utf8());
+		}
+
+		let low = self.hex4()?;
+		if !(0xDC00..0xE000).contains(&low) {
+			return Err(Error::invalid_utf8());
+		}
+
+		let code = 0x1_0000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+		char::from_u32(code).ok_or_else(Error::invalid_utf8)
+	}
+
+	/// Reads four hexadecimal digits.
+	fn hex4(&mut self) -> Result<u32> {
+		let mut code = 0;
+		for _ in 0..4 {
+			let digit = self
+				.next()
+				.and_then(|byte| char::from(byte).to_digit(16))
+				.ok_or_else(Error::invalid_utf8)?;
+
+			code = (code << 4) | digit;
+		}
+
+		Ok(code)
+	}
+
+	/// Parses a number starting at the next byte.
+	fn number(&mut self) -> Result<Number> {
+		let start = self.pos;
+		self.eat(b'-');
+		if !self.eat(b'0') {
+			self.digits()?;
+		}
+
+		if self.eat(b'.') {
+			self.digits()?;
+		}
+
+		if self.eat(b'e') || self.eat(b'E') {
+			let _sign = self.eat(b'+') || self.eat(b'-');
+			self.digits()?;
+		}
+
+		// The scanned bytes are ASCII.
+		let text =
+			str::from_utf8(&self.bytes[start..self.pos]).map_err(|_| Error::invalid_utf8())?;
+		if let Ok(number) = text.parse() {
+			return Ok(Number::Unsigned(number));
+		}
+
+		if let Ok(number) = text.parse() {
+			return Ok(Number::Signed(number));
+		}
+
+		text.parse()
+			.map(Number::Float)
+			.map_err(|_| Error::invalid_utf8())
+	}
+
+	/// Consumes one or more decimal digits.
+	fn digits(&mut self) -> Result {
+		let start = self.pos;
+		while let Some(b'0'..=b'9') = self.peek() {
+			self.pos = self.pos.saturating_add(1);
+		}
+
+		if self.pos == start {
+			Err(Error::invalid_utf8())
+		} else {
+			Ok(())
+		}
+	}
+
+	/// Consumes a keyword literal.
+	fn literal(&mut self, word: &str, value: Value) -> Result<Value> {
+		let end = self.pos.saturating_add(word.len());
+		if self.bytes.get(self.pos..end) != Some(word.as_bytes()) {
+			return Err(Error::invalid_utf8());
+		}
+
+		self.pos = end;
+
+		Ok(value)
+	}
+
+	/// Advances past insignificant whitespace.
+	fn skip_whitespace(&mut self) {
+		while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+			self.pos = self.pos.saturating_add(1);
+		}
+	}
+
+	/// Consumes `byte` or fails.
+	fn expect(&mut self, byte: u8) -> Result {
+		if self.eat(byte) {
+			Ok(())
+		} else {
+			Err(Error::invalid_utf8())
+		}
+	}
+
+	/// Consumes `byte` if it is next.
+	fn eat(&mut self, byte: u8) -> bool {
+		let matched = self.peek() == Some(byte);
+		if matched {
+			self.pos = self.pos.saturating_add(1);
+		}
+
+		matched
+	}
+
+	/// Consumes and returns the next byte.
+	fn next(&mut self) -> Option<u8> {
+		let byte = self.peek()?;
+		self.pos = self.pos.saturating_add(1);
+
+		Some(byte)
+	}
+
+	/// Returns the next byte without consuming it.
+	fn peek(&self) -> Option<u8> { self.bytes.get(self.pos).copied() }
+}
+
+#[cfg(test)]
+mod tests {
+	//! Checks the reader against representative and malformed documents.
+
+	use alloc::vec;
+
+	use super::*;
+
+	/// Parses nested containers, literals, and each number representation.
+	#[test]
+	fn parses_nested_documents() {
+		let value = parse(r#" {"a": [1, -2, 2.5e1, true, null], "b": {}} "#).unwrap();
+
+		let a = value.get("a").unwrap().elements().unwrap();
+		assert_eq!(a[0].as_u64(), Some(1));
+		assert_eq!(a[1].as_i64(), Some(-2));
+		assert_eq!(a[2], Value::Number(Number::Float(25.0)));
+		assert_eq!(a[3], Value::Bool(true));
+		assert_eq!(a[4], Value::Null);
+		assert_eq!(value.get("b"), Some(&Value::Object(vec![])));
+		assert_eq!(value.get("c"), None);
+	}
+
+	/// Resolves simple, Unicode, and surrogate-pair escapes.
+	#[test]
+	fn resolves_string_escapes() {
+		let value = parse(r#""a\"\\\n\u00e9\ud83d\ude00""#).unwrap();
+
+		assert_eq!(value.as_str(), Some("a\"\\\n\u{e9}\u{1f600}"));
+	}
+
+	/// Rejects truncated, trailing, and malformed input.
+	#[test]
+	fn rejects_malformed_documents() {
+		for text in ["", "{", "[1,]", "{\"a\" 1}", "1 2", "tru", "\"\\x\"", "01", "\"\\ud800\""] {
+			assert!(parse(text).unwrap_err().is(libc::EILSEQ), "{text}");
+		}
+	}
+
+	/// Bounds recursion on deeply nested input.
+	#[test]
+	fn rejects_excessive_nesting() {
+		let text: String = "[".repeat(DEPTH_LIMIT + 2) + &"]".repeat(DEPTH_LIMIT + 2);
+
+		assert!(parse(&text).unwrap_err().is(libc::EILSEQ));
+	}
+}
@@ -0,0 +1,443 @@
+//! `OpenMetrics` text exposition of allocator statistics.
+//!
+//! Every metric name starts with `jemalloc_` and is stable across releases.
+//! Per-arena families carry an `arena` label holding the index and a `name`
+//! label holding the arena's descriptive name. Size-class families add a
+//! `size` label holding the class size in bytes.
+
+use core::{
+	fmt::{self, Write},
+	time::Duration,
+};
+
+use super::PrintBuffer;
+use crate::{
+	arena, arenas,
+	ctl::{Error, Key, Result, key, raw},
+};
+
+/// Writes allocator statistics in the `OpenMetrics` text format.
+///
+/// The renderer refreshes the statistics epoch once, then writes global
+/// totals, background-thread counters, and per-arena statistics for every
+/// initialized arena. Per-bin and per-large-class breakdowns are optional
+/// because they multiply the output by the number of size classes. The output
+/// ends with the `# EOF` marker, so it can be served as
+/// `application/openmetrics-text; version=1.0.0` without further framing. No
+/// allocation is performed.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct OpenMetrics {
+	/// Whether bin and large-class families are written.
+	size_classes: bool,
+}
+
+/// `OpenMetrics` metric type of one family.
+#[derive(Clone, Copy)]
+enum Kind {
+	/// A value that can rise and fall.
+	Gauge,
+
+	/// A monotonic count, written with the `_total` suffix.
+	Counter,
+}
+
+/// C representation of a control value and its exposition scale.
+#[derive(Clone, Copy)]
+enum Width {
+	/// A `size_t` count.
+	Size,
+
+	/// A `uint64_t` count.
+	U64,
+
+	/// An `unsigned` count.
+	Unsigned,
+
+	/// A `uint64_t` nanosecond duration, written in seconds.
+	Nanoseconds,
+}
+
+/// Label dimensions of one family.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Scope {
+	/// One unlabelled sample.
+	Global,
+
+	/// One sample per arena, with the index at MIB component 2.
+	Arena,
+
+	/// One sample per arena and bin, with the bin at MIB component 4.
+	Bin,
+
+	/// One sample per arena and large class, with the class at MIB component 4.
+	Lextent,
+}
+
+/// Static description of one metric family.
+struct Family {
+	/// Family name, excluding any `_total` suffix.
+	name: &'static str,
+
+	/// Metric type.
+	kind: Kind,
+
+	/// Unit suffix of `name`, when it has one.
+	unit: Option<&'static str>,
+
+	/// Help text.
+	help: &'static str,
+
+	/// MIB template of the sampled control.
+	key: fn() -> Result<Key>,
+
+	/// Representation of the sampled control.
+	width: Width,
+
+	/// Label dimensions.
+	scope: Scope,
+}
+
+/// Defines a table of metric families.
+macro_rules! families {
+	($($name:literal, $kind:ident, $unit:expr, $key:ident, $width:ident, $scope:ident, $help:literal;)*) => {
+		[$(Family {
+			name: $name,
+			kind: Kind::$kind,
+			unit: $unit,
+			help: $help,
+			key: key::$key,
+			width: Width::$width,
+			scope: Scope::$scope,
+		},)*]
+	};
+}
+
+/// Every family in output order.
+#[rustfmt::skip]
+static FAMILIES: [Family; 41] = families! {
+	"jemalloc_allocated_bytes", Gauge, Some("bytes"), stats_allocated, Size, Global,
+		"Bytes allocated by the application.";
+	"jemalloc_active_bytes", Gauge, Some("bytes"), stats_active, Size, Global,
+		"Bytes in active pages.";
+	"jemalloc_metadata_bytes", Gauge, Some("bytes"), stats_metadata, Size, Global,
+		"Bytes dedicated to allocator metadata.";
+	"jemalloc_metadata_thp", Gauge, None, stats_metadata_thp, Size, Global,
+		"Transparent huge pages backing allocator metadata.";
+	"jemalloc_resident_bytes", Gauge, Some("bytes"), stats_resident, Size, Global,
+		"Upper bound on physically resident bytes.";
+	"jemalloc_mapped_bytes", Gauge, Some("bytes"), stats_mapped, Size, Global,
+		"Bytes in active extents.";
+	"jemalloc_retained_bytes", Gauge, Some("bytes"), stats_retained, Size, Global,
+		"Bytes in mappings retained for reuse.";
+	"jemalloc_zero_reallocs", Counter, None, stats_zero_reallocs, Size, Global,
+		"Calls to realloc with a non-null pointer and zero size.";
+	"jemalloc_background_threads", Gauge, None, stats_background_thread_num_threads, Size, Global,
+		"Running background worker threads.";
+	"jemalloc_background_thread_runs", Counter, None, stats_background_thread_num_runs, U64, Global,
+		"Runs performed by background worker threads.";
+	"jemalloc_background_thread_run_interval_seconds", Gauge, Some("seconds"),
+		stats_background_thread_run_interval, Nanoseconds, Global,
+		"Average interval between background worker runs.";
+	"jemalloc_arena_threads", Gauge, None, stats_arenas_nthreads, Unsigned, Arena,
+		"Threads assigned to the arena.";
+	"jemalloc_arena_uptime_seconds", Gauge, Some("seconds"), stats_arenas_uptime, Nanoseconds, Arena,
+		"Time since the arena was created.";
+	"jemalloc_arena_active_pages", Gauge, Some("pages"), stats_arenas_pactive, Size, Arena,
+		"Pages in active extents.";
+	"jemalloc_arena_dirty_pages", Gauge, Some("pages"), stats_arenas_pdirty, Size, Arena,
+		"Unused dirty pages awaiting decay.";
+	"jemalloc_arena_muzzy_pages", Gauge, Some("pages"), stats_arenas_pmuzzy, Size, Arena,
+		"Unused muzzy pages awaiting decay.";
+	"jemalloc_arena_mapped_bytes", Gauge, Some("bytes"), stats_arenas_mapped, Size, Arena,
+		"Bytes in active extents.";
+	"jemalloc_arena_retained_bytes", Gauge, Some("bytes"), stats_arenas_retained, Size, Arena,
+		"Bytes in mappings retained for reuse.";
+	"jemalloc_arena_base_bytes", Gauge, Some("bytes"), stats_arenas_base, Size, Arena,
+		"Bytes of base allocator metadata.";
+	"jemalloc_arena_internal_bytes", Gauge, Some("bytes"), stats_arenas_internal, Size, Arena,
+		"Bytes of internal allocations.";
+	"jemalloc_arena_resident_bytes", Gauge, Some("bytes"), stats_arenas_resident, Size, Arena,
+		"Upper bound on physically resident bytes.";
+	"jemalloc_arena_tcache_bytes", Gauge, Some("bytes"), stats_arenas_tcache_bytes, Size, Arena,
+		"Bytes cached in thread caches.";
+	"jemalloc_arena_dirty_purged_pages", Counter, Some("pages"), stats_arenas_dirty_purged, U64, Arena,
+		"Dirty pages purged.";
+	"jemalloc_arena_muzzy_purged_pages", Counter, Some("pages"), stats_arenas_muzzy_purged, U64, Arena,
+		"Muzzy pages purged.";
+	"jemalloc_arena_small_allocated_bytes", Gauge, Some("bytes"), stats_arenas_small_allocated, Size, Arena,
+		"Bytes allocated in small size classes.";
+	"jemalloc_arena_small_allocations", Counter, None, stats_arenas_small_nmalloc, U64, Arena,
+		"Small allocations served by the arena.";
+	"jemalloc_arena_small_deallocations", Counter, None, stats_arenas_small_ndalloc, U64, Arena,
+		"Small deallocations returned to the arena.";
+	"jemalloc_arena_small_requests", Counter, None, stats_arenas_small_nrequests, U64, Arena,
+		"Small allocation requests, including those served by thread caches.";
+	"jemalloc_arena_large_allocated_bytes", Gauge, Some("bytes"), stats_arenas_large_allocated, Size, Arena,
+		"Bytes allocated in large size classes.";
+	"jemalloc_arena_large_allocations", Counter, None, stats_arenas_large_nmalloc, U64, Arena,
+		"Large allocations served by the arena.";
+	"jemalloc_arena_large_deallocations", Counter, None, stats_arenas_large_ndalloc, U64, Arena,
+		"Large deallocations returned to the arena.";
+	"jemalloc_arena_large_requests", Counter, None, stats_arenas_large_nrequests, U64, Arena,
+		"Large allocation requests, including those served by thread caches.";
+	"jemalloc_bin_allocations", Counter, None, stats_arenas_bins_nmalloc, U64, Bin,
+		"Allocations served by the bin.";
+	"jemalloc_bin_deallocations", Counter, None, stats_arenas_bins_ndalloc, U64, Bin,
+		"Deallocations returned to the bin.";
+	"jemalloc_bin_requests", Counter, None, stats_arenas_bins_nrequests, U64, Bin,
+		"Allocation requests, including those served by thread caches.";
+	"jemalloc_bin_regions", Gauge, None, stats_arenas_bins_curregs, Size, Bin,
+		"Live regions in the bin.";
+	"jemalloc_bin_slabs", Gauge, None, stats_arenas_bins_curslabs, Size, Bin,
+		"Live slabs in the bin.";
+	"jemalloc_lextent_allocations", Counter, None, stats_arenas_lextents_nmalloc, U64, Lextent,
+		"Allocations served in the large class.";
+	"jemalloc_lextent_deallocations", Counter, None, stats_arenas_lextents_ndalloc, U64, Lextent,
+		"Deallocations returned from the large class.";
+	"jemalloc_lextent_requests", Counter, None, stats_arenas_lextents_nrequests, U64, Lextent,
+		"Allocation requests, including those served by thread caches.";
+	"jemalloc_lextent_extents", Gauge, None, stats_arenas_lextents_curlextents, Size, Lextent,
+		"Live allocations in the large class.";
+};
+
+impl OpenMetrics {
+	/// Returns a renderer for global and per-arena families only.
+	#[must_use]
+	pub const fn new() -> Self { Self { size_classes: false } }
+
+	/// Selects whether per-bin and per-large-class families are written.
+	#[must_use]
+	pub const fn with_size_classes(mut self, enabled: bool) -> Self {
+		self.size_classes = enabled;
+		self
+	}
+
+	/// Writes the exposition into `out`.
+	///
+	/// Output already written is left in place when an error occurs.
+	///
+	/// # Errors
+	///
+	/// Returns `ENOSPC` if `out` reports a formatting error, or another error
+	/// if jemalloc cannot refresh its epoch or rejects a query.
+	pub fn write<W: Write>(&self, out: &mut W) -> Result {
+		super::refresh_epoch()?;
+
+		let mut exposition = Exposition {
+			out,
+			arenas: arenas::limit()?,
+			bins: if self.size_classes { arenas::nbins()? } else { 0 },
+			lextents: if self.size_classes { arenas::nlextents()? } else { 0 },
+		};
+
+		for family in &FAMILIES {
+			exposition.family(family)?;
+		}
+
+		exposition
+			.out
+			.write_str("# EOF\n")
+			.map_err(|_| Error::insufficient_space())
+	}
+
+	/// Writes the exposition into `buf` and returns the written text.
+	///
+	/// # Errors
+	///
+	/// Returns `ENOSPC` if `buf` cannot hold the complete exposition, or
+	/// another error if jemalloc cannot refresh its epoch or rejects a query.
+	pub fn write_to_slice<'buf>(&self, buf: &'buf mut [u8]) -> Result<&'buf str> {
+		let mut output = PrintBuffer::new(buf);
+		self.write(&mut output)?;
+		output.finish()
+	}
+}
+
+/// One exposition in progress.
+struct Exposition<'out, W> {
+	/// Destination text.
+	out: &'out mut W,
+
+	/// Number of arena indices to visit.
+	arenas: usize,
+
+	/// Number of bins to visit per arena, or zero to omit bin families.
+	bins: usize,
+
+	/// Number of large classes to visit per arena, or zero to omit them.
+	lextents: usize,
+}
+
+impl<W: Write> Exposition<'_, W> {
+	/// Writes one family's metadata and samples.
+	fn family(&mut self, family: &Family) -> Result {
+		let classes = match family.scope {
+			| Scope::Global | Scope::Arena => 1,
+			| Scope::Bin => self.bins,
+			| Scope::Lextent => self.lextents,
+		};
+
+		if classes == 0 {
+			return Ok(());
+		}
+
+		let template = (family.key)()?;
+		self.header(family)?;
+
+		if family.scope == Scope::Global {
+			let value = read(&template, family.width)?;
+			return self.sample(family, Labels::None, value);
+		}
+
+		for arena in 0..self.arenas {
+			for class in 0..classes {
+				let mut key = template.clone();
+				key[2] = arena;
+				if family.scope != Scope::Arena {
+					key[4] = class;
+				}
+
+				// Uninitialized and destroyed arena indices have no statistics.
+				let value = match read(&key, family.width) {
+					| Err(error) if error.is(libc::ENOENT) => break,
+					| result => result?,
+				};
+
+				let labels = match family.scope {
+					| Scope::Bin => Labels::Class(arena, arenas::bin_size(class)?),
+					| Scope::Lextent => Labels::Class(arena, arenas::lextent_size(class)?),
+					| Scope::Global | Scope::Arena => Labels::Arena(arena),
+				};
+
+				self.sample(family, labels, value)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Writes the `TYPE`, `UNIT`, and `HELP` lines of one family.
+	fn header(&mut self, family: &Family) -> Result {
+		let kind = match family.kind {
+			| Kind::Gauge => "gauge",
+			| Kind::Counter => "counter",
+		};
+
+		self.text(format_args!("# TYPE {} {kind}\n", family.name))?;
+		if let Some(unit) = family.unit {
+			self.text(format_args!("# UNIT {} {unit}\n", family.name))?;
+		}
+
+		self.text(format_args!("# HELP {} {}\n", family.name, family.help))
+	}
+
+	/// Writes one sample line.
+	fn sample(&mut self, family: &Family, labels: Labels, value: Sample) -> Result {
+		let suffix = match family.kind {
+			| Kind::Gauge => "",
+			| Kind::Counter => "_total",
+		};
+
+		self.text(format_args!("{}{suffix}", family.name))?;
+		match labels {
+			| Labels::None => {},
+			| Labels::Arena(arena) => {
+				self.text(format_args!("{{arena=\"{arena}\",name=\""))?;
+				self.name(arena)?;
+				self.text(format_args!("\"}}"))?;
+			},
+			| Labels::Class(arena, size) => {
+				self.text(format_args!("{{arena=\"{arena}\",name=\""))?;
+				self.name(arena)?;
+				self.text(format_args!("\",size=\"{size}\"}}"))?;
+			},
+		}
+
+		match value {
+			| Sample::Count(count) => self.text(format_args!(" {count}\n")),
+			| Sample::Seconds(seconds) => self.text(format_args!(" {seconds}\n")),
+		}
+	}
+
+	/// Writes an arena's name as an escaped label value.
+	///
+	/// Invalid UTF-8 is replaced with U+FFFD. An arena destroyed while the
+	/// exposition is written is labelled with an empty name.
+	fn name(&mut self, arena: usize) -> Result {
+		let Ok(name) = arena::read_name(arena) else {
+			return Ok(());
+		};
+
+		for chunk in name.as_bytes().utf8_chunks() {
+			for character in chunk.valid().chars() {
+				match character {
+					| '\\' => self.text(format_args!("\\\\"))?,
+					| '"' => self.text(format_args!("\\\""))?,
+					| '\n' => self.text(format_args!("\\n"))?,
+					| character => self.text(format_args!("{character}"))?,
+				}
+			}
+
+			if !chunk.invalid().is_empty() {
+				self.text(format_args!("{}", char::REPLACEMENT_CHARACTER))?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Writes formatted text, mapping writer failure to `ENOSPC`.
+	fn text(&mut self, text: fmt::Arguments<'_>) -> Result {
+		self.out
+			.write_fmt(text)
+			.map_err(|_| Error::insufficient_space())
+	}
+}
+
+/// Label set of one sample.
+#[derive(Clone, Copy)]
+enum Labels {
+	/// No labels.
+	None,
+
+	/// The arena index and name.
+	Arena(usize),
+
+	/// The arena index and name, and a class size in bytes.
+	Class(usize, usize),
+}
+
+/// One sample value.
+#[derive(Clone, Copy)]
+enum Sample {
+	/// An integer count.
+	Count(u64),
+
+	/// A duration in seconds.
+	Seconds(f64),
+}
+
+/// Reads one sample through a complete MIB.
+fn read(key: &Key, width: Width) -> Result<Sample> {
+	let sample = match width {
+		// SAFETY: the family table pairs every `Size` control with `size_t`.
+		| Width::Size => Sample::Count(unsafe { raw::get::<usize>(key) }? as u64),
+
+		// SAFETY: the family table pairs every `U64` control with `uint64_t`.
+		| Width::U64 => Sample::Count(unsafe { raw::get::<u64>(key) }?),
+
+		// SAFETY: the family table pairs every `Unsigned` control with
+		// `unsigned`.
+		| Width::Unsigned => Sample::Count(u64::from(unsafe { raw::get::<libc::c_uint>(key) }?)),
+
+		| Width::Nanoseconds => {
+			// SAFETY: the family table pairs every `Nanoseconds` control with
+			// `uint64_t`.
+			let nanos = unsafe { raw::get::<u64>(key) }?;
+
+			Sample::Seconds(Duration::from_nanos(nanos).as_secs_f64())
+		},
+	};
+
+	Ok(sample)
+}
@@ -0,0 +1,654 @@
+//! Typed statistics decoded from jemalloc's JSON report.
+//!
+//! [`report`] requests the `J` form of `malloc_stats_print` and converts it
+//! into a [`StatsReport`]. Jemalloc refreshes its epoch once before printing,
+//! so every value in the tree belongs to the same snapshot. Sections omitted by
+//! the jemalloc build, such as detailed counters without the `stats` feature,
+//! decode as empty collections and zero values.
+
+use alloc::{string::String, vec::Vec};
+use core::str;
+
+use super::{json, json::Value, print_raw};
+use crate::ctl::{Error, Result};
+
+/// A complete statistics report captured in one call.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StatsReport {
+	/// The jemalloc version string.
+	pub version: String,
+
+	/// Process-wide memory totals.
+	pub general: GeneralStats,
+
+	/// Background worker activity.
+	pub background_threads: BackgroundThreadStats,
+
+	/// Process-wide mutex contention counters.
+	pub mutexes: Vec<MutexStats>,
+
+	/// Per-arena statistics, followed by the merged and destroyed summaries
+	/// when jemalloc reports them.
+	pub arenas: Vec<ArenaStats>,
+}
+
+/// Process-wide memory totals in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GeneralStats {
+	/// Bytes allocated by the application.
+	pub allocated: u64,
+
+	/// Bytes in active pages.
+	pub active: u64,
+
+	/// Bytes dedicated to allocator metadata.
+	pub metadata: u64,
+
+	/// Transparent huge pages backing metadata, counted in pages.
+	pub metadata_thp: u64,
+
+	/// Upper bound on physically resident bytes.
+	pub resident: u64,
+
+	/// Bytes in active extents.
+	pub mapped: u64,
+
+	/// Bytes in mappings retained for reuse.
+	pub retained: u64,
+
+	/// Number of `realloc` calls with a non-null pointer and zero size.
+	pub zero_reallocs: u64,
+}
+
+/// Background worker activity.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BackgroundThreadStats {
+	/// Number of running workers.
+	pub num_threads: u64,
+
+	/// Cumulative runs across all workers.
+	pub num_runs: u64,
+
+	/// Average run interval in nanoseconds.
+	pub run_interval: u64,
+}
+
+/// Contention counters for one named mutex.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MutexStats {
+	/// The mutex name used by jemalloc's report.
+	pub name: String,
+
+	/// Number of lock acquisitions.
+	pub num_ops: u64,
+
+	/// Number of acquisitions that waited.
+	pub num_wait: u64,
+
+	/// Number of acquisitions that succeeded while spinning.
+	pub num_spin_acq: u64,
+
+	/// Number of times ownership moved between threads.
+	pub num_owner_switch: u64,
+
+	/// Total waiting time in nanoseconds.
+	pub total_wait_time: u64,
+
+	/// Longest single wait in nanoseconds.
+	pub max_wait_time: u64,
+
+	/// Largest number of threads waiting at once.
+	pub max_num_thds: u64,
+}
+
+/// The arena a set of [`ArenaStats`] describes.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ArenaId {
+	/// One initialized arena.
+	Index(usize),
+
+	/// The sum over every arena.
+	Merged,
+
+	/// The sum over destroyed arenas.
+	Destroyed,
+}
+
+/// Statistics for one arena or arena summary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArenaStats {
+	/// The arena described.
+	pub id: ArenaId,
+
+	/// Number of threads assigned to the arena.
+	pub nthreads: u64,
+
+	/// Time since the arena was created, in nanoseconds.
+	pub uptime_ns: u64,
+
+	/// The arena's DSS precedence.
+	pub dss: String,
+
+	/// Dirty-page decay time in milliseconds, or `-1` when disabled.
+	pub dirty_decay_ms: i64,
+
+	/// Muzzy-page decay time in milliseconds, or `-1` when disabled.
+	pub muzzy_decay_ms: i64,
+
+	/// Number of active pages.
+	pub pactive: u64,
+
+	/// Number of dirty pages.
+	pub pdirty: u64,
+
+	/// Number of muzzy pages.
+	pub pmuzzy: u64,
+
+	/// Dirty-page purge activity.
+	pub dirty_purge: PurgeStats,
+
+	/// Muzzy-page purge activity.
+	pub muzzy_purge: PurgeStats,
+
+	/// Totals over small size classes.
+	pub small: ClassTotals,
+
+	/// Totals over large size classes.
+	pub large: ClassTotals,
+
+	/// Bytes in active extents.
+	pub mapped: u64,
+
+	/// Bytes in mappings retained for reuse.
+	pub retained: u64,
+
+	/// Bytes of base metadata.
+	pub base: u64,
+
+	/// Bytes of internal allocations.
+	pub internal: u64,
+
+	/// Transparent huge pages backing metadata, counted in pages.
+	pub metadata_thp: u64,
+
+	/// Bytes cached in thread caches.
+	pub tcache_bytes: u64,
+
+	/// Upper bound on physically resident bytes.
+	pub resident: u64,
+
+	/// Bytes leaked by failed purges.
+	pub abandoned_vm: u64,
+
+	/// Number of cached extent structures.
+	pub extent_avail: u64,
+
+	/// Contention counters for the arena's mutexes.
+	pub mutexes: Vec<MutexStats>,
+
+	/// Per-class statistics for small size classes.
+	pub bins: Vec<BinStats>,
+
+	/// Per-class statistics for large size classes.
+	pub lextents: Vec<LextentStats>,
+
+	/// Per-page-size-class statistics for unused extents.
+	pub extents: Vec<ExtentStats>,
+}
+
+/// Purge activity for one page state.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PurgeStats {
+	/// Number of purge sweeps.
+	pub npurge: u64,
+
+	/// Number of `madvise` or equivalent calls.
+	pub nmadvise: u64,
+
+	/// Number of pages purged.
+	pub purged: u64,
+}
+
+/// Allocation totals over one family of size classes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ClassTotals {
+	/// Bytes currently allocated.
+	pub allocated: u64,
+
+	/// Number of allocations served by the arena.
+	pub nmalloc: u64,
+
+	/// Number of deallocations returned to the arena.
+	pub ndalloc: u64,
+
+	/// Number of allocation requests, including those served by caches.
+	pub nrequests: u64,
+
+	/// Number of thread-cache fills.
+	pub nfills: u64,
+
+	/// Number of thread-cache flushes.
+	pub nflushes: u64,
+}
+
+/// Statistics for one small size class.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BinStats {
+	/// Allocation size of the class in bytes.
+	pub size: u64,
+
+	/// Number of allocations served by the bin.
+	pub nmalloc: u64,
+
+	/// Number of deallocations returned to the bin.
+	pub ndalloc: u64,
+
+	/// Number of live regions.
+	pub curregs: u64,
+
+	/// Number of allocation requests, including those served by caches.
+	pub nrequests: u64,
+
+	/// Number of thread-cache fills.
+	pub nfills: u64,
+
+	/// Number of thread-cache flushes.
+	pub nflushes: u64,
+
+	/// Number of times the current slab was replaced.
+	pub nreslabs: u64,
+
+	/// Number of live slabs.
+	pub curslabs: u64,
+
+	/// Number of slabs with free regions.
+	pub nonfull_slabs: u64,
+
+	/// Contention counters for the bin mutex, when reported.
+	pub mutex: Option<MutexStats>,
+}
+
+/// Statistics for one large size class.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LextentStats {
+	/// Allocation size of the class in bytes.
+	pub size: u64,
+
+	/// Number of allocations served by the arena.
+	pub nmalloc: u64,
+
+	/// Number of deallocations returned to the arena.
+	pub ndalloc: u64,
+
+	/// Number of allocation requests, including those served by caches.
+	pub nrequests: u64,
+
+	/// Number of live allocations.
+	pub curlextents: u64,
+}
+
+/// Unused extents of one page-size class.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExtentStats {
+	/// Number of dirty extents.
+	pub ndirty: u64,
+
+	/// Number of muzzy extents.
+	pub nmuzzy: u64,
+
+	/// Number of retained extents.
+	pub nretained: u64,
+
+	/// Bytes in dirty extents.
+	pub dirty_bytes: u64,
+
+	/// Bytes in muzzy extents.
+	pub muzzy_bytes: u64,
+
+	/// Bytes in retained extents.
+	pub retained_bytes: u64,
+}
+
+/// Captures and decodes a complete statistics report.
+///
+/// The JSON text is buffered in a growable allocation before decoding.
+///
+/// # Errors
+///
+/// Returns `EILSEQ` if jemalloc produces no report or the report is not the
+/// documented JSON structure. Jemalloc produces no report when it cannot
+/// refresh its epoch.
+pub fn report() -> Result<StatsReport> {
+	let mut text = Vec::new();
+	print_raw(c"J", |fragment| text.extend_from_slice(fragment));
+
+	let text = str::from_utf8(&text).map_err(|_| Error::invalid_utf8())?;
+	decode(&json::parse(text)?)
+}
+
+/// Converts a parsed report into its typed form.
+fn decode(document: &Value) -> Result<StatsReport> {
+	let root = document
+		.get("jemalloc")
+		.ok_or_else(Error::invalid_utf8)?;
+	let geometry = root.get("arenas");
+	let stats = root.get("stats");
+
+	let mut arenas = Vec::new();
+	if let Some(members) = root.get("stats.arenas").map(object).transpose()? {
+		for (name, arena) in members {
+			let id = match name.as_str() {
+				| "merged" => ArenaId::Merged,
+				| "destroyed" => ArenaId::Destroyed,
+				| index => ArenaId::Index(index.parse().map_err(|_| Error::invalid_utf8())?),
+			};
+
+			arenas.push(decode_arena(id, arena, geometry)?);
+		}
+	}
+
+	Ok(StatsReport {
+		version: string(Some(root), "version")?,
+		general: GeneralStats {
+			allocated: number(stats, "allocated")?,
+			active: number(stats, "active")?,
+			metadata: number(stats, "metadata")?,
+			metadata_thp: number(stats, "metadata_thp")?,
+			resident: number(stats, "resident")?,
+			mapped: number(stats, "mapped")?,
+			retained: number(stats, "retained")?,
+			zero_reallocs: number(stats, "zero_reallocs")?,
+		},
+		background_threads: {
+			let workers = stats.and_then(|stats| stats.get("background_thread"));
+
+			BackgroundThreadStats {
+				num_threads: number(workers, "num_threads")?,
+				num_runs: number(workers, "num_runs")?,
+				run_interval: number(workers, "run_interval")?,
+			}
+		},
+		mutexes: decode_mutexes(stats.and_then(|stats| stats.get("mutexes")))?,
+		arenas,
+	})
+}
+
+/// Converts one member of `stats.arenas`.
+fn decode_arena(id: ArenaId, arena: &Value, geometry: Option<&Value>) -> Result<ArenaStats> {
+	let arena = Some(arena);
+	let small = arena.and_then(|arena| arena.get("small"));
+	let large = arena.and_then(|arena| arena.get("large"));
+
+	let bin_sizes = sizes(geometry, "bin")?;
+	let bins = elements(arena, "bins")?
+		.iter()
+		.enumerate()
+		.map(|(index, bin)| {
+			let bin = Some(bin);
+
+			Ok(BinStats {
+				size: bin_sizes.get(index).copied().unwrap_or_default(),
+				nmalloc: number(bin, "nmalloc")?,
+				ndalloc: number(bin, "ndalloc")?,
+				curregs: number(bin, "curregs")?,
+				nrequests: number(bin, "nrequests")?,
+				nfills: number(bin, "nfills")?,
+				nflushes: number(bin, "nflushes")?,
+				nreslabs: number(bin, "nreslabs")?,
+				curslabs: number(bin, "curslabs")?,
+				nonfull_slabs: number(bin, "nonfull_slabs")?,
+				mutex: bin
+					.and_then(|bin| bin.get("mutex"))
+					.map(|mutex| decode_mutex(String::new(), mutex))
+					.transpose()?,
+			})
+		})
+		.collect::<Result<_>>()?;
+
+	let lextent_sizes = sizes(geometry, "lextent")?;
+	let lextents = elements(arena, "lextents")?
+		.iter()
+		.enumerate()
+		.map(|(index, lextent)| {
+			let lextent = Some(lextent);
+
+			Ok(LextentStats {
+				size: lextent_sizes
+					.get(index)
+					.copied()
+					.unwrap_or_default(),
+				nmalloc: number(lextent, "nmalloc")?,
+				ndalloc: number(lextent, "ndalloc")?,
+				nrequests: number(lextent, "nrequests")?,
+				curlextents: number(lextent, "curlextents")?,
+			})
+		})
+		.collect::<Result<_>>()?;
+
+	let extents = elements(arena, "extents")?
+		.iter()
+		.map(|extent| {
+			let extent = Some(extent);
+
+			Ok(ExtentStats {
+				ndirty: number(extent, "ndirty")?,
+				nmuzzy: number(extent, "nmuzzy")?,
+				nretained: number(extent, "nretained")?,
+				dirty_bytes: number(extent, "dirty_bytes")?,
+				muzzy_bytes: number(extent, "muzzy_bytes")?,
+				retained_bytes: number(extent, "retained_bytes")?,
+			})
+		})
+		.collect::<Result<_>>()?;
+
+	Ok(ArenaStats {
+		id,
+		nthreads: number(arena, "nthreads")?,
+		uptime_ns: number(arena, "uptime_ns")?,
+		dss: string(arena, "dss")?,
+		dirty_decay_ms: signed(arena, "dirty_decay_ms")?,
+		muzzy_decay_ms: signed(arena, "muzzy_decay_ms")?,
+		pactive: number(arena, "pactive")?,
+		pdirty: number(arena, "pdirty")?,
+		pmuzzy: number(arena, "pmuzzy")?,
+		dirty_purge: PurgeStats {
+			npurge: number(arena, "dirty_npurge")?,
+			nmadvise: number(arena, "dirty_nmadvise")?,
+			purged: number(arena, "dirty_purged")?,
+		},
+		muzzy_purge: PurgeStats {
+			npurge: number(arena, "muzzy_npurge")?,
+			nmadvise: number(arena, "muzzy_nmadvise")?,
+			purged: number(arena, "muzzy_purged")?,
+		},
+		small: decode_totals(small)?,
+		large: decode_totals(large)?,
+		mapped: number(arena, "mapped")?,
+		retained: number(arena, "retained")?,
+		base: number(arena, "base")?,
+		internal: number(arena, "internal")?,
+		metadata_thp: number(arena, "metadata_thp")?,
+		tcache_bytes: number(arena, "tcache_bytes")?,
+		resident: number(arena, "resident")?,
+		abandoned_vm: number(arena, "abandoned_vm")?,
+		extent_avail: number(arena, "extent_avail")?,
+		mutexes: decode_mutexes(arena.and_then(|arena| arena.get("mutexes")))?,
+		bins,
+		lextents,
+		extents,
+	})
+}
+
+/// Converts the `small` or `large` totals of one arena.
+fn decode_totals(totals: Option<&Value>) -> Result<ClassTotals> {
+	Ok(ClassTotals {
+		allocated: number(totals, "allocated")?,
+		nmalloc: number(totals, "nmalloc")?,
+		ndalloc: number(totals, "ndalloc")?,
+		nrequests: number(totals, "nrequests")?,
+		nfills: number(totals, "nfills")?,
+		nflushes: number(totals, "nflushes")?,
+	})
+}
+
+/// Converts an object of named mutex counters.
+fn decode_mutexes(mutexes: Option<&Value>) -> Result<Vec<MutexStats>> {
+	let Some(mutexes) = mutexes else {
+		return Ok(Vec::new());
+	};
+
+	object(mutexes)?
+		.iter()
+		.map(|(name, mutex)| decode_mutex(name.clone(), mutex))
+		.collect()
+}
+
+/// Converts one mutex counter object.
+fn decode_mutex(name: String, mutex: &Value) -> Result<MutexStats> {
+	object(mutex)?;
+	let mutex = Some(mutex);
+
+	Ok(MutexStats {
+		name,
+		num_ops: number(mutex, "num_ops")?,
+		num_wait: number(mutex, "num_wait")?,
+		num_spin_acq: number(mutex, "num_spin_acq")?,
+		num_owner_switch: number(mutex, "num_owner_switch")?,
+		total_wait_time: number(mutex, "total_wait_time")?,
+		max_wait_time: number(mutex, "max_wait_time")?,
+		max_num_thds: number(mutex, "max_num_thds")?,
+	})
+}
+
+/// Reads the `size` of each element of a geometry array.
+fn sizes(geometry: Option<&Value>, key: &str) -> Result<Vec<u64>> {
+	elements(geometry, key)?
+		.iter()
+		.map(|class| number(Some(class), "size"))
+		.collect()
+}
+
+/// Returns the members of an object value.
+fn object(value: &Value) -> Result<&[(String, Value)]> {
+	value.members().ok_or_else(Error::invalid_utf8)
+}
+
+/// Returns an optional array member, treating absence as empty.
+fn elements<'doc>(parent: Option<&'doc Value>, key: &str) -> Result<&'doc [Value]> {
+	match parent.and_then(|parent| parent.get(key)) {
+		| None => Ok(&[]),
+		| Some(value) => value.elements().ok_or_else(Error::invalid_utf8),
+	}
+}
+
+/// Reads an optional unsigned member, treating absence as zero.
+fn number(parent: Option<&Value>, key: &str) -> Result<u64> {
+	match parent.and_then(|parent| parent.get(key)) {
+		| None => Ok(0),
+		| Some(value) => value.as_u64().ok_or_else(Error::invalid_utf8),
+	}
+}
+
+/// Reads an optional signed member, treating absence as zero.
+fn signed(parent: Option<&Value>, key: &str) -> Result<i64> {
+	match parent.and_then(|parent| parent.get(key)) {
+		| None => Ok(0),
+		| Some(value) => value.as_i64().ok_or_else(Error::invalid_utf8),
+	}
+}
+
+/// Reads an optional string member, treating absence as empty.
+fn string(parent: Option<&Value>, key: &str) -> Result<String> {
+	match parent.and_then(|parent| parent.get(key)) {
+		| None => Ok(String::new()),
+		| Some(value) => value
+			.as_str()
+			.map(String::from)
+			.ok_or_else(Error::invalid_utf8),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	//! Decodes a trimmed report with jemalloc's JSON layout.
+
+	use super::*;
+
+	/// A report reduced to one bin, lextent, extent, and mutex per section.
+	const REPORT: &str = r#"{
+		"jemalloc": {
+			"version": "5.3.1-0-g0",
+			"arenas": {
+				"narenas": 1,
+				"bin": [{"size": 8, "nregs": 512, "slab_size": 4096, "nshards": 1}],
+				"lextent": [{"size": 16384}]
+			},
+			"stats": {
+				"allocated": 100,
+				"active": 4096,
+				"metadata": 2000,
+				"metadata_thp": 0,
+				"resident": 8192,
+				"mapped": 8192,
+				"retained": 0,
+				"zero_reallocs": 0,
+				"background_thread": {"num_threads": 1, "num_runs": 7, "run_interval": 10},
+				"mutexes": {"ctl": {"num_ops": 3, "num_wait": 1, "max_num_thds": 1}}
+			},
+			"stats.arenas": {
+				"merged": {"nthreads": 1, "dss": "secondary", "dirty_decay_ms": -1},
+				"0": {
+					"nthreads": 1,
+					"dss": "secondary",
+					"dirty_decay_ms": 10000,
+					"muzzy_decay_ms": 0,
+					"dirty_npurge": 2,
+					"small": {"allocated": 8, "nmalloc": 1, "ndalloc": 0, "nrequests": 1},
+					"bins": [{"nmalloc": 1, "curregs": 1, "mutex": {"num_ops": 1}}],
+					"lextents": [{"nmalloc": 2, "curlextents": 1}],
+					"extents": [{"ndirty": 1, "dirty_bytes": 4096}]
+				}
+			}
+		}
+	}"#;
+
+	/// Maps every section into its typed field.
+	#[test]
+	fn decodes_report_sections() {
+		let report = decode(&json::parse(REPORT).unwrap()).unwrap();
+
+		assert_eq!(report.version, "5.3.1-0-g0");
+		assert_eq!(report.general.allocated, 100);
+		assert_eq!(report.general.resident, 8192);
+		assert_eq!(report.background_threads.num_runs, 7);
+		assert_eq!(report.mutexes[0].name, "ctl");
+		assert_eq!(report.mutexes[0].num_wait, 1);
+
+		let [merged, arena] = report.arenas.as_slice() else {
+			panic!("expected two arena summaries");
+		};
+
+		assert_eq!(merged.id, ArenaId::Merged);
+		assert_eq!(merged.dirty_decay_ms, -1);
+		assert_eq!(arena.id, ArenaId::Index(0));
+		assert_eq!(arena.dss, "secondary");
+		assert_eq!(arena.dirty_purge.npurge, 2);
+		assert_eq!(arena.small.allocated, 8);
+		assert_eq!(arena.bins[0].size, 8);
+		assert_eq!(arena.bins[0].curregs, 1);
+		assert_eq!(arena.bins[0].mutex.as_ref().unwrap().num_ops, 1);
+		assert_eq!(arena.lextents[0].size, 16384);
+		assert_eq!(arena.lextents[0].curlextents, 1);
+		assert_eq!(arena.extents[0].dirty_bytes, 4096);
+	}
+
+	/// Rejects documents whose values have the wrong JSON type.
+	#[test]
+	fn rejects_mistyped_values() {
+		let report = json::parse(r#"{"jemalloc": {"stats": {"allocated": "100"}}}"#).unwrap();
+		assert!(decode(&report).unwrap_err().is(libc::EILSEQ));
+
+		let report = json::parse("{}").unwrap();
+		assert!(decode(&report).unwrap_err().is(libc::EILSEQ));
+	}
+}
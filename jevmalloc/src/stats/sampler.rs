@@ -0,0 +1,421 @@
+//! Background statistics sampling into an in-memory time series.
+
+use std::{
+	collections::VecDeque,
+	sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+	thread::{self, JoinHandle},
+	time::Duration,
+	vec::Vec,
+};
+
+use libc::c_uint;
+
+use super::{Delta, Rates, Snapshot};
+use crate::{
+	arenas,
+	ctl::{Error, Key, Result, key, raw},
+};
+
+/// One sampled point: global totals and every initialized arena.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Sample {
+	/// Global totals read after the sample's epoch refresh.
+	pub snapshot: Snapshot,
+
+	/// Per-arena totals from the same refresh, in index order.
+	pub arenas: Vec<ArenaSample>,
+}
+
+/// Totals for one arena within a [`Sample`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ArenaSample {
+	/// The arena index.
+	pub index: usize,
+
+	/// Threads assigned to the arena.
+	pub threads: usize,
+
+	/// Pages in active extents.
+	pub active_pages: usize,
+
+	/// Unused dirty pages.
+	pub dirty_pages: usize,
+
+	/// Unused muzzy pages.
+	pub muzzy_pages: usize,
+
+	/// Bytes in active extents.
+	pub mapped: usize,
+
+	/// Upper bound on physically resident bytes.
+	pub resident: usize,
+}
+
+/// Minimum, maximum, and mean of one gauge over a window.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Summary {
+	/// Smallest observed value.
+	pub min: usize,
+
+	/// Largest observed value.
+	pub max: usize,
+
+	/// Arithmetic mean of the observed values.
+	pub avg: f64,
+}
+
+/// Aggregates of the global totals over a trailing window.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WindowStats {
+	/// Number of samples in the window.
+	pub samples: usize,
+
+	/// Changes from the oldest to the newest sample in the window.
+	pub delta: Delta,
+
+	/// Per-second rates over the window, when it spans a nonzero time.
+	pub rates: Option<Rates>,
+
+	/// Allocated bytes.
+	pub allocated: Summary,
+
+	/// Active bytes.
+	pub active: Summary,
+
+	/// Metadata bytes.
+	pub metadata: Summary,
+
+	/// Resident bytes.
+	pub resident: Summary,
+
+	/// Mapped bytes.
+	pub mapped: Summary,
+
+	/// Retained bytes.
+	pub retained: Summary,
+}
+
+/// A background thread recording statistics at a fixed interval.
+///
+/// The thread refreshes the statistics epoch once per sample and keeps the
+/// newest `capacity` samples in a ring buffer, discarding the oldest. Samples
+/// that fail to read are skipped. Dropping the sampler stops and joins the
+/// thread.
+#[derive(Debug)]
+pub struct Sampler {
+	/// State shared with the sampling thread.
+	shared: Arc<Shared>,
+
+	/// The sampling thread, taken when it is joined.
+	thread: Option<JoinHandle<()>>,
+}
+
+/// State shared between a [`Sampler`] and its thread.
+#[derive(Debug)]
+struct Shared {
+	/// Recorded samples, oldest first, and the stop request.
+	state: Mutex<State>,
+
+	/// Wakes the sampling thread early when a stop is requested.
+	wake: Condvar,
+}
+
+/// Mutable sampler state.
+#[derive(Debug)]
+struct State {
+	/// Recorded samples, oldest first.
+	ring: VecDeque<Sample>,
+
+	/// Maximum number of retained samples.
+	capacity: usize,
+
+	/// Whether the sampling thread should exit.
+	stop: bool,
+}
+
+impl Sampler {
+	/// Starts sampling every `interval`, retaining `capacity` samples.
+	///
+	/// The first sample is taken before this function returns, so statistics
+	/// errors are reported here rather than silently skipped.
+	///
+	/// # Errors
+	///
+	/// Returns `EINVAL` if `interval` is zero or `capacity` is zero, `EAGAIN`
+	/// if the thread cannot be spawned, or another error if the first sample
+	/// cannot be read.
+	pub fn spawn(interval: Duration, capacity: usize) -> Result<Self> {
+		if interval.is_zero() || capacity == 0 {
+			return Err(Error::invalid_argument());
+		}
+
+		let mut ring = VecDeque::with_capacity(capacity);
+		ring.push_back(capture()?);
+
+		let shared = Arc::new(Shared {
+			state: Mutex::new(State { ring, capacity, stop: false }),
+			wake: Condvar::new(),
+		});
+
+		let thread = thread::Builder::new()
+			.name("jevmalloc-sampler".into())
+			.spawn({
+				let shared = Arc::clone(&shared);
+				move || run(&shared, interval)
+			})
+			.map_err(|_| Error::try_again())?;
+
+		Ok(Self { shared, thread: Some(thread) })
+	}
+
+	/// Returns a copy of every retained sample, oldest first.
+	#[must_use]
+	pub fn samples(&self) -> Vec<Sample> { self.shared.lock().ring.iter().cloned().collect() }
+
+	/// Returns the newest sample.
+	#[must_use]
+	pub fn latest(&self) -> Option<Sample> { self.shared.lock().ring.back().cloned() }
+
+	/// Aggregates the samples taken within `window` of the newest one.
+	///
+	/// Returns `None` when no sample has been recorded.
+	#[must_use]
+	pub fn window(&self, window: Duration) -> Option<WindowStats> {
+		let snapshots: Vec<Snapshot> = self
+			.shared
+			.lock()
+			.ring
+			.iter()
+			.map(|sample| sample.snapshot)
+			.collect();
+
+		let newest = *snapshots.last()?;
+		let start = newest.uptime.saturating_sub(window);
+		let skipped = snapshots.partition_point(|snapshot| snapshot.uptime < start);
+		let snapshots = &snapshots[skipped..];
+
+		let oldest = snapshots[0];
+		let mut stats = WindowStats::default();
+		let mut totals = [0_u128; 6];
+
+		for snapshot in snapshots {
+			let first = stats.samples == 0;
+			let gauges = gauges(snapshot);
+			let summaries = summaries(&mut stats);
+
+			for ((summary, total), value) in summaries.into_iter().zip(&mut totals).zip(gauges) {
+				if first {
+					summary.min = value;
+					summary.max = value;
+				}
+
+				summary.min = summary.min.min(value);
+				summary.max = summary.max.max(value);
+				*total += value as u128;
+			}
+
+			stats.samples += 1;
+		}
+
+		#[expect(clippy::cast_precision_loss)]
+		let count = stats.samples as f64;
+		for (summary, total) in summaries(&mut stats).into_iter().zip(totals) {
+			#[expect(clippy::cast_precision_loss)]
+			let total = total as f64;
+			summary.avg = total / count;
+		}
+
+		stats.delta = newest.delta(&oldest);
+		stats.rates = stats.delta.rates();
+
+		Some(stats)
+	}
+
+	/// Aggregates the last minute of samples.
+	#[must_use]
+	pub fn last_minute(&self) -> Option<WindowStats> { self.window(Duration::from_mins(1)) }
+
+	/// Aggregates the last five minutes of samples.
+	#[must_use]
+	pub fn last_five_minutes(&self) -> Option<WindowStats> { self.window(Duration::from_mins(5)) }
+}
+
+impl Drop for Sampler {
+	fn drop(&mut self) {
+		self.shared.lock().stop = true;
+		self.shared.wake.notify_all();
+
+		if let Some(thread) = self.thread.take() {
+			let _: thread::Result<()> = thread.join();
+		}
+	}
+}
+
+impl Shared {
+	/// Locks the state, recovering it if the sampling thread panicked.
+	fn lock(&self) -> MutexGuard<'_, State> {
+		self.state
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+/// Records samples until a stop is requested.
+fn run(shared: &Shared, interval: Duration) {
+	let mut state = shared.lock();
+
+	loop {
+		state = shared
+			.wake
+			.wait_timeout_while(state, interval, |state| !state.stop)
+			.unwrap_or_else(PoisonError::into_inner)
+			.0;
+
+		if state.stop {
+			return;
+		}
+
+		drop(state);
+		let sample = capture();
+		state = shared.lock();
+
+		if let Ok(sample) = sample {
+			if state.ring.len() == state.capacity {
+				state.ring.pop_front();
+			}
+
+			state.ring.push_back(sample);
+		}
+	}
+}
+
+/// Refreshes the epoch and reads global and per-arena totals.
+fn capture() -> Result<Sample> {
+	let snapshot = Snapshot::capture()?;
+	let mut arenas = Vec::new();
+
+	for index in 0..arenas::limit()? {
+		// Uninitialized and destroyed arena indices have no statistics.
+		let threads = match read::<c_uint>(key::stats_arenas_nthreads, index) {
+			| Err(error) if error.is(libc::ENOENT) => continue,
+			| threads => threads?,
+		};
+
+		arenas.push(ArenaSample {
+			index,
+			threads: threads as usize,
+			active_pages: read(key::stats_arenas_pactive, index)?,
+			dirty_pages: read(key::stats_arenas_pdirty, index)?,
+			muzzy_pages: read(key::stats_arenas_pmuzzy, index)?,
+			mapped: read(key::stats_arenas_mapped, index)?,
+			resident: read(key::stats_arenas_resident, index)?,
+		});
+	}
+
+	Ok(Sample { snapshot, arenas })
+}
+
+/// Reads one `stats.arenas.<i>` control for arena `index`.
+fn read<T: Copy>(template: fn() -> Result<Key>, index: usize) -> Result<T> {
+	let mut key = template()?;
+	key[2] = index;
+
+	// SAFETY: closed call sites pair each `stats.arenas.<i>` template with its
+	// documented C output type.
+	unsafe { raw::get(&key) }
+}
+
+/// Returns the summarized gauges of one snapshot in [`summaries`] order.
+const fn gauges(snapshot: &Snapshot) -> [usize; 6] {
+	[
+		snapshot.allocated,
+		snapshot.active,
+		snapshot.metadata,
+		snapshot.resident,
+		snapshot.mapped,
+		snapshot.retained,
+	]
+}
+
+/// Returns the summaries of a window in [`gauges`] order.
+fn summaries(stats: &mut WindowStats) -> [&mut Summary; 6] {
+	[
+		&mut stats.allocated,
+		&mut stats.active,
+		&mut stats.metadata,
+		&mut stats.resident,
+		&mut stats.mapped,
+		&mut stats.retained,
+	]
+}
+
+#[cfg(test)]
+mod tests {
+	//! Checks window aggregation over synthetic samples without a thread.
+
+	use super::*;
+
+	/// Builds a sampler holding one sample per `(seconds, bytes)` pair.
+	fn sampler(points: &[(u64, usize)]) -> Sampler {
+		let ring = points
+			.iter()
+			.map(|&(seconds, bytes)| Sample {
+				snapshot: Snapshot {
+					epoch: seconds,
+					uptime: Duration::from_secs(seconds),
+					allocated: bytes,
+					active: bytes,
+					metadata: bytes,
+					resident: bytes,
+					mapped: bytes,
+					retained: bytes,
+					..Snapshot::default()
+				},
+				arenas: Vec::new(),
+			})
+			.collect();
+
+		Sampler {
+			shared: Arc::new(Shared {
+				state: Mutex::new(State {
+					ring,
+					capacity: points.len(),
+					stop: false,
+				}),
+				wake: Condvar::new(),
+			}),
+			thread: None,
+		}
+	}
+
+	/// Summarizes only the samples inside the trailing window.
+	#[test]
+	fn window_covers_trailing_samples() {
+		let sampler = sampler(&[(0, 9000), (50, 100), (80, 300), (110, 200)]);
+		let stats = sampler.last_minute().unwrap();
+
+		assert_eq!(stats.samples, 3);
+		assert_eq!(stats.allocated.min, 100);
+		assert_eq!(stats.allocated.max, 300);
+		assert!((stats.retained.avg - 200.0).abs() < f64::EPSILON);
+		assert_eq!(stats.delta.elapsed, Duration::from_mins(1));
+		assert_eq!(stats.delta.allocated, 100);
+
+		let rates = stats.rates.unwrap();
+		assert!((rates.active - 100.0 / 60.0).abs() < 1e-9);
+
+		assert_eq!(sampler.last_five_minutes().unwrap().samples, 4);
+	}
+
+	/// Reports no rate for a single sample and nothing for an empty ring.
+	#[test]
+	fn degenerate_windows() {
+		let stats = sampler(&[(5, 10)]).last_minute().unwrap();
+
+		assert_eq!(stats.samples, 1);
+		assert_eq!(stats.mapped.min, 10);
+		assert_eq!(stats.rates, None);
+		assert_eq!(sampler(&[]).latest(), None);
+		assert_eq!(sampler(&[]).window(Duration::from_secs(1)), None);
+	}
+}
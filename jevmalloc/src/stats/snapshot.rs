@@ -0,0 +1,258 @@
+//! Consistent multi-value statistics snapshots.
+
+use core::time::Duration;
+
+use super::{
+	active, allocated, background_thread_num_runs, background_thread_num_threads, mapped,
+	metadata, refresh_epoch, resident, retained,
+};
+use crate::ctl::{Result, key, raw};
+
+/// Global statistics read from one epoch refresh.
+///
+/// [`Snapshot::capture`] refreshes jemalloc's cached statistics once and then
+/// reads every field from that cache, so the values are mutually consistent.
+/// The timestamp is arena 0's uptime, which jemalloc computes during the same
+/// refresh; it is monotonic for the life of the process.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Snapshot {
+	/// The epoch produced by the refresh.
+	pub epoch: u64,
+
+	/// Allocator uptime when the refresh completed.
+	pub uptime: Duration,
+
+	/// Bytes allocated by the application.
+	pub allocated: usize,
+
+	/// Bytes in active pages.
+	pub active: usize,
+
+	/// Bytes dedicated to allocator metadata.
+	pub metadata: usize,
+
+	/// Upper bound on physically resident bytes.
+	pub resident: usize,
+
+	/// Bytes in active extents.
+	pub mapped: usize,
+
+	/// Bytes in mappings retained for reuse.
+	pub retained: usize,
+
+	/// Number of running background workers.
+	pub background_threads: usize,
+
+	/// Cumulative runs across all background workers.
+	pub background_thread_runs: u64,
+}
+
+/// Differences between two snapshots.
+///
+/// Byte fields are signed changes from the earlier snapshot to the later one.
+/// Changes beyond the range of `i64` saturate.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Delta {
+	/// Time between the two refreshes.
+	pub elapsed: Duration,
+
+	/// Number of epoch refreshes between the two snapshots.
+	pub epochs: u64,
+
+	/// Change in allocated bytes.
+	pub allocated: i64,
+
+	/// Change in active bytes.
+	pub active: i64,
+
+	/// Change in metadata bytes.
+	pub metadata: i64,
+
+	/// Change in resident bytes.
+	pub resident: i64,
+
+	/// Change in mapped bytes.
+	pub mapped: i64,
+
+	/// Change in retained bytes.
+	pub retained: i64,
+
+	/// Change in the number of running background workers.
+	pub background_threads: i64,
+
+	/// Background worker runs performed between the two snapshots.
+	pub background_thread_runs: u64,
+}
+
+/// Per-second rates derived from a [`Delta`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rates {
+	/// Allocated bytes gained per second.
+	pub allocated: f64,
+
+	/// Active bytes gained per second.
+	pub active: f64,
+
+	/// Metadata bytes gained per second.
+	pub metadata: f64,
+
+	/// Resident bytes gained per second.
+	pub resident: f64,
+
+	/// Mapped bytes gained per second.
+	pub mapped: f64,
+
+	/// Retained bytes gained per second.
+	pub retained: f64,
+
+	/// Background worker runs per second.
+	pub background_thread_runs: f64,
+}
+
+impl Snapshot {
+	/// Refreshes the statistics epoch once and reads every field.
+	///
+	/// # Errors
+	///
+	/// Returns an error if jemalloc cannot refresh its epoch or rejects any
+	/// query.
+	pub fn capture() -> Result<Self> {
+		let epoch = refresh_epoch()?;
+		let key = key::stats_arenas_uptime()?;
+
+		// SAFETY: `stats.arenas.<i>.uptime` has the C output type `uint64_t`,
+		// and the template selects arena 0, which always exists.
+		let uptime = unsafe { raw::get::<u64>(&key) }?;
+
+		Ok(Self {
+			epoch,
+			uptime: Duration::from_nanos(uptime),
+			allocated: allocated()?,
+			active: active()?,
+			metadata: metadata()?,
+			resident: resident()?,
+			mapped: mapped()?,
+			retained: retained()?,
+			background_threads: background_thread_num_threads()?,
+			background_thread_runs: background_thread_num_runs()?,
+		})
+	}
+
+	/// Returns the changes from `earlier` to this snapshot.
+	///
+	/// Monotonic fields saturate at zero when `earlier` was actually captured
+	/// later.
+	#[must_use]
+	pub fn delta(&self, earlier: &Self) -> Delta {
+		Delta {
+			elapsed: self.uptime.saturating_sub(earlier.uptime),
+			epochs: self.epoch.saturating_sub(earlier.epoch),
+			allocated: change(self.allocated, earlier.allocated),
+			active: change(self.active, earlier.active),
+			metadata: change(self.metadata, earlier.metadata),
+			resident: change(self.resident, earlier.resident),
+			mapped: change(self.mapped, earlier.mapped),
+			retained: change(self.retained, earlier.retained),
+			background_threads: change(self.background_threads, earlier.background_threads),
+			background_thread_runs: self
+				.background_thread_runs
+				.saturating_sub(earlier.background_thread_runs),
+		}
+	}
+}
+
+impl Delta {
+	/// Returns each change divided by the elapsed time in seconds.
+	///
+	/// Returns `None` when no time elapsed between the snapshots.
+	#[must_use]
+	pub fn rates(&self) -> Option<Rates> {
+		let seconds = self.elapsed.as_secs_f64();
+		if seconds <= 0.0 {
+			return None;
+		}
+
+		#[expect(clippy::cast_precision_loss)]
+		let rate = |change: i64| change as f64 / seconds;
+
+		#[expect(clippy::cast_precision_loss)]
+		let runs = self.background_thread_runs as f64 / seconds;
+
+		Some(Rates {
+			allocated: rate(self.allocated),
+			active: rate(self.active),
+			metadata: rate(self.metadata),
+			resident: rate(self.resident),
+			mapped: rate(self.mapped),
+			retained: rate(self.retained),
+			background_thread_runs: runs,
+		})
+	}
+}
+
+/// Returns the signed change between two byte counts, saturating at `i64`.
+fn change(later: usize, earlier: usize) -> i64 {
+	if later >= earlier {
+		i64::try_from(later - earlier).unwrap_or(i64::MAX)
+	} else {
+		i64::try_from(earlier - later).map_or(i64::MIN, |decrease| -decrease)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	//! Checks delta and rate arithmetic without reading allocator state.
+
+	use super::*;
+
+	/// Builds a snapshot at `seconds` with every byte gauge set to `bytes`.
+	fn at(seconds: u64, bytes: usize, runs: u64) -> Snapshot {
+		Snapshot {
+			epoch: seconds,
+			uptime: Duration::from_secs(seconds),
+			allocated: bytes,
+			active: bytes,
+			metadata: bytes,
+			resident: bytes,
+			mapped: bytes,
+			retained: bytes,
+			background_threads: 1,
+			background_thread_runs: runs,
+		}
+	}
+
+	/// Reports signed changes and per-second rates.
+	#[test]
+	fn delta_reports_changes_and_rates() {
+		let delta = at(12, 1000, 30).delta(&at(10, 3000, 10));
+
+		assert_eq!(delta.elapsed, Duration::from_secs(2));
+		assert_eq!(delta.epochs, 2);
+		assert_eq!(delta.allocated, -2000);
+		assert_eq!(delta.background_threads, 0);
+		assert_eq!(delta.background_thread_runs, 20);
+
+		let rates = delta.rates().unwrap();
+		assert!((rates.allocated + 1000.0).abs() < f64::EPSILON);
+		assert!((rates.background_thread_runs - 10.0).abs() < f64::EPSILON);
+	}
+
+	/// Saturates reversed operands and reports no rate without elapsed time.
+	#[test]
+	fn reversed_delta_saturates() {
+		let delta = at(10, 0, 10).delta(&at(12, 0, 30));
+
+		assert_eq!(delta.elapsed, Duration::ZERO);
+		assert_eq!(delta.epochs, 0);
+		assert_eq!(delta.background_thread_runs, 0);
+		assert_eq!(delta.rates(), None);
+	}
+
+	/// Saturates byte changes outside the range of `i64`.
+	#[cfg(target_pointer_width = "64")]
+	#[test]
+	fn byte_changes_saturate() {
+		assert_eq!(change(usize::MAX, 0), i64::MAX);
+		assert_eq!(change(0, usize::MAX), i64::MIN);
+	}
+}
@@ -91,7 +91,7 @@ impl ThreadCache {
 
 		// SAFETY: this owner supplies the live cache's `unsigned` identifier and
 		// the mutable borrow excludes another safe operation on the handle.
-		unsafe { raw::set(&key, &index) }
+		unsafe { raw::set(&key, &index) }.map_err(|error| error.with_index(index))
 	}
 
 	/// Destroys this explicit cache and consumes its owner.
@@ -121,7 +121,7 @@ impl ThreadCache {
 
 		// SAFETY: this handle exclusively owns the live explicit cache selected
 		// by the exact `unsigned` identifier.
-		unsafe { raw::set(&key, &index) }?;
+		unsafe { raw::set(&key, &index) }.map_err(|error| error.with_index(index))?;
 		self.owned = false;
 
 		Ok(())
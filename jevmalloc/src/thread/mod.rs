@@ -3,12 +3,20 @@
 //! [`this`] contains state and commands for the calling thread, including its
 //! automatically managed allocation cache. [`ThreadCache`] instead owns one
 //! explicitly created cache selected through extended-allocation flags.
+//! [`AllocScope`] and [`measure`] report the bytes a code path allocates, and
+//! [`Accounted`] extends that accounting to futures polled on any thread.
 
 pub mod cache;
 #[cfg(feature = "stats")]
 pub mod counters;
+pub mod scope;
+pub mod task;
 pub mod this;
 
-pub use self::cache::{ThreadCache, ThreadCacheDestroyError};
 #[cfg(feature = "stats")]
 pub use self::counters::ThreadCounters;
+pub use self::{
+	cache::{ThreadCache, ThreadCacheDestroyError},
+	scope::{AllocScope, AllocUsage, measure},
+	task::{Accounted, TaskRecord, TaskUsage},
+};
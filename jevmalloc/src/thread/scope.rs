@@ -0,0 +1,214 @@
+//! Allocation measurement over a lexical scope of the calling thread.
+
+use core::marker::PhantomData;
+#[cfg(feature = "stats")]
+use core::{cell::Cell, cmp};
+
+#[cfg(feature = "stats")]
+use super::{ThreadCounters, this};
+#[cfg(not(feature = "stats"))]
+use crate::ctl::Error;
+use crate::ctl::Result;
+
+/// Bytes allocated and released by the calling thread within a scope.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct AllocUsage {
+	/// Bytes allocated since the scope was entered.
+	pub allocated: u64,
+
+	/// Bytes deallocated since the scope was entered.
+	pub deallocated: u64,
+
+	/// Allocated minus deallocated bytes.
+	pub net: i64,
+
+	/// Approximate maximum of `net` reached since the scope was entered.
+	pub peak: u64,
+}
+
+/// Measures the calling thread's allocations from entry to each observation.
+///
+/// Allocated and deallocated bytes are exact differences of jemalloc's
+/// per-thread counters. The peak comes from `thread.peak`, which jemalloc
+/// updates approximately and which has one reset point per thread. Entering a
+/// scope resets it, so inner scopes must be opened through [`nested`], which
+/// first folds the peak observed so far into every enclosing scope. An
+/// unrelated scope entered while another is live makes the outer peak
+/// understate.
+///
+/// A scope performs no allocation and is confined to the thread that entered
+/// it. Without the `stats` feature, entering one fails with `ENOENT`.
+///
+/// [`nested`]: Self::nested
+#[derive(Debug)]
+pub struct AllocScope<'parent> {
+	/// The enclosing scope, if this one was opened through [`Self::nested`].
+	#[cfg_attr(not(feature = "stats"), expect(dead_code))]
+	parent: Option<&'parent Self>,
+
+	/// Counter state recorded on entry.
+	#[cfg(feature = "stats")]
+	entry: Entry,
+
+	/// Keeps the scope on the thread whose counters it observes.
+	not_send_or_sync: PhantomData<*mut ()>,
+}
+
+/// Counter state of a scope with statistics support.
+#[cfg(feature = "stats")]
+#[derive(Debug)]
+struct Entry {
+	/// Direct handles to the calling thread's counters.
+	counters: ThreadCounters,
+
+	/// Allocated bytes at entry.
+	allocated: u64,
+
+	/// Deallocated bytes at entry.
+	deallocated: u64,
+
+	/// Net bytes at entry, as a wrapping difference.
+	net: i64,
+
+	/// Highest relative peak observed before the latest nested reset.
+	floor: Cell<u64>,
+
+	/// Net bytes at the latest reset, relative to `net`.
+	offset: Cell<i64>,
+}
+
+impl AllocScope<'static> {
+	/// Enters a top-level scope on the calling thread.
+	///
+	/// # Errors
+	///
+	/// Returns `ENOENT` without the `stats` feature, or an error if jemalloc
+	/// rejects a counter query or the peak reset.
+	pub fn enter() -> Result<Self> { Self::open(None) }
+}
+
+impl<'parent> AllocScope<'parent> {
+	/// Enters a scope nested within this one.
+	///
+	/// The current peak is folded into this scope and each of its ancestors
+	/// before the thread peak is reset for the new scope.
+	///
+	/// # Errors
+	///
+	/// Returns an error if jemalloc rejects a counter query or the peak reset.
+	pub fn nested(&self) -> Result<AllocScope<'_>> { AllocScope::open(Some(self)) }
+
+	/// Runs `f` in a scope nested within this one and reports its usage.
+	///
+	/// # Errors
+	///
+	/// Returns an error without calling `f` if the nested scope cannot be
+	/// entered, or after calling it if its usage cannot be read.
+	pub fn measure<R, F: FnOnce() -> R>(&self, f: F) -> Result<(R, AllocUsage)> {
+		let scope = self.nested()?;
+		let value = f();
+
+		Ok((value, scope.usage()?))
+	}
+
+	/// Reports usage from entry up to this call.
+	///
+	/// # Errors
+	///
+	/// Returns an error if jemalloc rejects the peak query.
+	#[cfg(feature = "stats")]
+	pub fn usage(&self) -> Result<AllocUsage> {
+		let entry = &self.entry;
+		let raw_peak = this::peak()?;
+		let allocated = entry
+			.counters
+			.allocated()
+			.wrapping_sub(entry.allocated);
+		let deallocated = entry
+			.counters
+			.deallocated()
+			.wrapping_sub(entry.deallocated);
+		let net = allocated.wrapping_sub(deallocated).cast_signed();
+
+		Ok(AllocUsage {
+			allocated,
+			deallocated,
+			net,
+			peak: cmp::max(self.peak_with(raw_peak), net.max(0).cast_unsigned()),
+		})
+	}
+
+	/// Reports usage from entry up to this call.
+	///
+	/// # Errors
+	///
+	/// A scope cannot be entered without the `stats` feature, so this is
+	/// unreachable in practice; it returns `ENOENT`.
+	#[cfg(not(feature = "stats"))]
+	pub fn usage(&self) -> Result<AllocUsage> { Err(Error::not_found()) }
+
+	/// Enters a scope below `parent`, folding the current peak into every
+	/// enclosing scope before resetting it.
+	#[cfg(feature = "stats")]
+	fn open(parent: Option<&'parent Self>) -> Result<Self> {
+		let counters = ThreadCounters::current()?;
+		let raw_peak = this::peak()?;
+		let allocated = counters.allocated();
+		let deallocated = counters.deallocated();
+		let net = allocated.wrapping_sub(deallocated).cast_signed();
+
+		let mut ancestor = parent;
+		while let Some(scope) = ancestor {
+			let entry = &scope.entry;
+			entry.floor.set(scope.peak_with(raw_peak));
+			entry.offset.set(net.wrapping_sub(entry.net));
+			ancestor = scope.parent;
+		}
+
+		this::reset_peak()?;
+
+		Ok(Self {
+			parent,
+			entry: Entry {
+				counters,
+				allocated,
+				deallocated,
+				net,
+				floor: Cell::new(0),
+				offset: Cell::new(0),
+			},
+			not_send_or_sync: PhantomData,
+		})
+	}
+
+	/// Reports that scopes cannot be entered without statistics support.
+	#[cfg(not(feature = "stats"))]
+	fn open(_parent: Option<&'parent Self>) -> Result<Self> { Err(Error::not_found()) }
+
+	/// Combines a raw thread peak with this scope's folded history.
+	#[cfg(feature = "stats")]
+	fn peak_with(&self, raw_peak: u64) -> u64 {
+		let entry = &self.entry;
+		let reached = entry
+			.offset
+			.get()
+			.saturating_add_unsigned(raw_peak)
+			.max(0)
+			.cast_unsigned();
+
+		cmp::max(entry.floor.get(), reached)
+	}
+}
+
+/// Runs `f` in a new top-level [`AllocScope`] and reports its usage.
+///
+/// # Errors
+///
+/// Returns an error without calling `f` if the scope cannot be entered, or
+/// after calling it if its usage cannot be read.
+pub fn measure<R, F: FnOnce() -> R>(f: F) -> Result<(R, AllocUsage)> {
+	let scope = AllocScope::enter()?;
+	let value = f();
+
+	Ok((value, scope.usage()?))
+}
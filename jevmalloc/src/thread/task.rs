@@ -0,0 +1,179 @@
+//! Allocation accounting for futures that migrate between threads.
+
+use core::{
+	future::Future,
+	ops::Deref,
+	pin::Pin,
+	sync::atomic::{AtomicI64, AtomicU64, Ordering},
+	task::{Context, Poll},
+};
+
+use super::AllocScope;
+
+/// Shared allocation totals for one task.
+///
+/// Every [`Accounted`] future that refers to the record adds the bytes its
+/// polls allocate and deallocate. Updates are atomic, so the record can be
+/// read from any thread while the task runs, and several futures, such as a
+/// task and the subtasks it spawns, may share one record.
+#[derive(Debug, Default)]
+pub struct TaskRecord {
+	/// Bytes allocated across all accounted polls.
+	allocated: AtomicU64,
+
+	/// Bytes deallocated across all accounted polls.
+	deallocated: AtomicU64,
+
+	/// Running allocated minus deallocated bytes.
+	net: AtomicI64,
+
+	/// Approximate maximum of `net`.
+	peak: AtomicU64,
+
+	/// Number of accounted polls.
+	polls: AtomicU64,
+
+	/// Number of polls run without accounting because measurement failed.
+	untracked_polls: AtomicU64,
+}
+
+/// A point-in-time copy of a [`TaskRecord`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TaskUsage {
+	/// Bytes allocated across all accounted polls.
+	pub allocated: u64,
+
+	/// Bytes deallocated across all accounted polls.
+	pub deallocated: u64,
+
+	/// Allocated minus deallocated bytes.
+	pub net: i64,
+
+	/// Approximate maximum of `net` reached during any accounted poll.
+	pub peak: u64,
+
+	/// Number of accounted polls.
+	pub polls: u64,
+
+	/// Number of polls run without accounting because measurement failed,
+	/// including every poll when the `stats` feature is disabled.
+	pub untracked_polls: u64,
+}
+
+/// A future whose polls are charged to a [`TaskRecord`].
+///
+/// Each poll runs inside an [`AllocScope`] on whichever thread the executor
+/// uses, so allocations follow the task across threads. Only work performed
+/// inside `poll` is counted; memory a waker or another task frees on this
+/// task's behalf is charged to whoever frees it. Entering the scope resets
+/// the thread peak, so an enclosing [`AllocScope`] on the polling thread sees
+/// an understated peak.
+///
+/// `R` is any shared handle to the record, such as `&TaskRecord` or
+/// `Arc<TaskRecord>`.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Accounted<F, R> {
+	/// The wrapped future.
+	future: F,
+
+	/// The record charged for each poll.
+	record: R,
+}
+
+impl TaskRecord {
+	/// Creates an empty record.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			allocated: AtomicU64::new(0),
+			deallocated: AtomicU64::new(0),
+			net: AtomicI64::new(0),
+			peak: AtomicU64::new(0),
+			polls: AtomicU64::new(0),
+			untracked_polls: AtomicU64::new(0),
+		}
+	}
+
+	/// Returns the current totals.
+	///
+	/// Fields are read individually, so a copy taken during a concurrent poll
+	/// of a shared record can mix totals from before and after it.
+	#[must_use]
+	pub fn usage(&self) -> TaskUsage {
+		TaskUsage {
+			allocated: self.allocated.load(Ordering::Relaxed),
+			deallocated: self.deallocated.load(Ordering::Relaxed),
+			net: self.net.load(Ordering::Relaxed),
+			peak: self.peak.load(Ordering::Relaxed),
+			polls: self.polls.load(Ordering::Relaxed),
+			untracked_polls: self.untracked_polls.load(Ordering::Relaxed),
+		}
+	}
+
+	/// Runs one poll inside an allocation scope and charges its usage.
+	fn charge<T, P: FnOnce() -> T>(&self, poll: P) -> T {
+		let Ok(scope) = AllocScope::enter() else {
+			self.untracked_polls
+				.fetch_add(1, Ordering::Relaxed);
+			return poll();
+		};
+
+		let output = poll();
+		let Ok(usage) = scope.usage() else {
+			self.untracked_polls
+				.fetch_add(1, Ordering::Relaxed);
+			return output;
+		};
+
+		let before = self.net.fetch_add(usage.net, Ordering::Relaxed);
+		let reached = before
+			.saturating_add_unsigned(usage.peak)
+			.max(0)
+			.cast_unsigned();
+
+		self.allocated
+			.fetch_add(usage.allocated, Ordering::Relaxed);
+		self.deallocated
+			.fetch_add(usage.deallocated, Ordering::Relaxed);
+		self.peak.fetch_max(reached, Ordering::Relaxed);
+		self.polls.fetch_add(1, Ordering::Relaxed);
+
+		output
+	}
+}
+
+impl<F, R> Accounted<F, R>
+where
+	R: Deref<Target = TaskRecord>,
+{
+	/// Wraps `future`, charging its polls to `record`.
+	pub const fn new(future: F, record: R) -> Self { Self { future, record } }
+
+	/// Returns the record charged for each poll.
+	pub fn record(&self) -> &TaskRecord { &self.record }
+
+	/// Unwraps the future and its record handle.
+	pub fn into_inner(self) -> (F, R) { (self.future, self.record) }
+}
+
+impl<F, R> Future for Accounted<F, R>
+where
+	F: Future,
+	R: Deref<Target = TaskRecord>,
+{
+	type Output = F::Output;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		// SAFETY: `future` is structurally pinned. It is never moved out of a
+		// pinned wrapper, `Accounted` implements neither `Drop` nor `Unpin`
+		// manually, and `into_inner` requires an unpinned value.
+		let this = unsafe { self.get_unchecked_mut() };
+
+		// SAFETY: `this` came from a pinned reference, so `future` is pinned
+		// for as long as the wrapper, per the projection contract above.
+		let future = unsafe { Pin::new_unchecked(&mut this.future) };
+
+		this.record.charge(|| future.poll(cx))
+	}
+}
@@ -2,7 +2,10 @@
 
 #![cfg(test)]
 
+#[cfg(target_os = "linux")]
+use core::ptr;
 use core::{
+	mem::MaybeUninit,
 	ptr::{NonNull, null_mut},
 	sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
 };
@@ -12,9 +15,13 @@ use jevmalloc::{
 	Arena, Extent, ExtentAlloc, ExtentAllocation, ExtentCallbacks, ExtentHookResult, ExtentHooks,
 	Jemalloc, RawExtentHooks, arena, ctl, ffi, thread,
 };
-#[cfg(target_env = "msvc")]
+#[cfg(target_os = "linux")]
+use jevmalloc::{FileHooks, HugePageHooks, NumaArenas, NumaHooks, SecretHooks};
+#[cfg(any(target_env = "msvc", target_os = "linux"))]
 use libc::c_int;
 use libc::c_void;
+#[cfg(target_os = "linux")]
+use libc::{c_long, c_uint, c_ulong};
 
 /// Jemalloc's C boolean representation under cl.exe.
 #[cfg(target_env = "msvc")]
@@ -338,6 +345,408 @@ fn typed_extent_hooks_at_creation() {
 	unsafe { seed.try_destroy() }.unwrap();
 }
 
+/// Backs an arena with the built-in hugepage table.
+#[cfg(target_os = "linux")]
+#[test]
+fn huge_page_extent_hooks() {
+	/// The hugepage table under test.
+	static HUGE: HugePageHooks = HugePageHooks::new();
+
+	const SIZE: usize = 8 * 1024 * 1024;
+
+	let _guard = CONTROL.lock().unwrap();
+	let arena = Arena::create_with_huge_pages(&HUGE).unwrap();
+	with_allocation(arena, SIZE, 0xA5, |_| {
+		assert!(HUGE.explicit_bytes() + HUGE.transparent_bytes() >= SIZE);
+		assert_eq!(HUGE.page_size(), HugePageHooks::DEFAULT_PAGE_SIZE);
+	});
+}
+
+/// Splits hugepage extents for small allocations and returns every explicit
+/// mapping to the system pool on destruction.
+#[cfg(target_os = "linux")]
+#[test]
+fn huge_page_small_allocations_release_pool() {
+	/// The hugepage table under test.
+	static HUGE: HugePageHooks = HugePageHooks::new();
+
+	const COUNT: usize = 256;
+	const SIZE: usize = 4096;
+
+	let _guard = CONTROL.lock().unwrap();
+	let arena = Arena::create_with_huge_pages(&HUGE).unwrap();
+	let flags = arena.flags() | ffi::MALLOCX_TCACHE_NONE;
+
+	let allocations: Vec<_> = (0..COUNT)
+		.map(|index| {
+			// SAFETY: the size is nonzero, the arena is live, and the tcache is
+			// bypassed.
+			let allocation = unsafe { ffi::mallocx(SIZE << (index % 4), flags) };
+			NonNull::new(allocation.cast::<u8>()).expect("hugepage allocation failed")
+		})
+		.collect();
+
+	for (index, allocation) in allocations.into_iter().enumerate() {
+		// SAFETY: the allocation is live and spans at least `SIZE` writable
+		// bytes.
+		unsafe { allocation.write_bytes(0x5A, SIZE) };
+
+		// SAFETY: the allocation is live and the flags select its original arena.
+		unsafe { ffi::sdallocx(allocation.as_ptr().cast(), SIZE << (index % 4), flags) };
+	}
+
+	// SAFETY: every data allocation was freed without a tcache, and the static
+	// hugepage table remains valid.
+	unsafe { arena.try_destroy() }.unwrap();
+	assert_eq!(HUGE.explicit_bytes(), 0);
+}
+
+/// Carves an arena's allocations from one region until it is exhausted, then
+/// reuses the region once the arena is dropped.
+#[test]
+fn region_extent_hooks() {
+	const REGION: usize = 16 * 1024 * 1024;
+	const CHUNK: usize = 1024 * 1024;
+
+	let _guard = CONTROL.lock().unwrap();
+	let mut region = Box::new_uninit_slice(REGION);
+	let bounds = region.as_ptr_range();
+	let bounds = bounds.start.addr()..bounds.end.addr();
+
+	for _ in 0..2 {
+		let arena = Arena::create_in_region(&mut region).unwrap();
+		assert!(arena.capacity() <= REGION);
+		let flags = arena.arena().flags() | ffi::MALLOCX_TCACHE_NONE;
+
+		let mut allocations = Vec::new();
+		let allocate = || {
+			// SAFETY: the size is nonzero, the arena is live, and the tcache is
+			// bypassed.
+			NonNull::new(unsafe { ffi::mallocx(CHUNK, flags) })
+		};
+		while let Some(allocation) = allocate() {
+			assert!(bounds.contains(&allocation.as_ptr().addr()));
+			allocations.push(allocation);
+			assert!(allocations.len() <= REGION / CHUNK, "the pool exceeded its region");
+		}
+		assert!(!allocations.is_empty());
+
+		for allocation in allocations {
+			// SAFETY: the allocation is live and the flags select its original
+			// arena.
+			unsafe { ffi::dallocx(allocation.as_ptr(), flags) };
+		}
+		assert!(arena.available() > 0);
+	}
+}
+
+/// Creation fails, and releases the region, when it cannot hold the arena.
+#[test]
+fn region_too_small() {
+	let _guard = CONTROL.lock().unwrap();
+	let mut region = [MaybeUninit::uninit(); 4096];
+
+	Arena::create_in_region(&mut region).unwrap_err();
+	region.fill(MaybeUninit::new(0));
+}
+
+/// Places an arena in a memfd and reads an allocation back through the file.
+#[cfg(target_os = "linux")]
+#[test]
+fn memfd_extent_hooks() {
+	/// The file-backed table under test.
+	static SHARED: FileHooks = FileHooks::new();
+
+	const CAPACITY: usize = 64 * 1024 * 1024;
+	const SIZE: usize = 1024 * 1024;
+
+	let _guard = CONTROL.lock().unwrap();
+	let arena = Arena::create_in_memfd(&SHARED, c"jevmalloc-test", CAPACITY).unwrap();
+	let fd = SHARED.fd().unwrap();
+	assert_eq!(SHARED.capacity(), CAPACITY);
+	assert!(
+		Arena::create_in_memfd(&SHARED, c"jevmalloc-test", CAPACITY)
+			.unwrap_err()
+			.is(libc::EBUSY)
+	);
+
+	with_allocation(arena, SIZE, 0x5A, |allocation| {
+		let offset = SHARED.offset_of(allocation.as_ptr()).unwrap();
+		let mut read = [0_u8; 64];
+		// SAFETY: the buffer is writable for its length and the descriptor is
+		// live.
+		let count = unsafe {
+			libc::pread(fd, read.as_mut_ptr().cast(), read.len(), offset.try_into().unwrap())
+		};
+		assert_eq!(count, 64);
+		assert!(read.iter().all(|&byte| byte == 0x5A));
+	});
+}
+
+/// Discards a file's existing contents so zeroed allocations stay zeroed.
+#[cfg(target_os = "linux")]
+#[test]
+fn file_extent_hooks_discard_contents() {
+	/// The file-backed table under test.
+	static STALE: FileHooks = FileHooks::new();
+
+	const CAPACITY: usize = 16 * 1024 * 1024;
+	const SIZE: usize = 1024 * 1024;
+
+	let _guard = CONTROL.lock().unwrap();
+	// SAFETY: the name is a valid C string and the flags request a new
+	// descriptor.
+	let fd = unsafe { libc::memfd_create(c"jevmalloc-stale".as_ptr(), libc::MFD_CLOEXEC) };
+	assert!(fd >= 0);
+
+	let stale = vec![0xEE_u8; CAPACITY];
+	// SAFETY: the buffer is readable for its length and the descriptor is live.
+	let count = unsafe { libc::write(fd, stale.as_ptr().cast(), stale.len()) };
+	assert_eq!(count, CAPACITY.cast_signed());
+
+	// SAFETY: the new memfd is read-write, its contents are given up, and the
+	// table owns it from here on.
+	let arena = unsafe { Arena::create_in_file(&STALE, fd, CAPACITY) }.unwrap();
+	let flags = arena.flags() | ffi::MALLOCX_TCACHE_NONE | ffi::MALLOCX_ZERO;
+
+	// SAFETY: the size is nonzero, the arena is live, and the tcache is bypassed.
+	let allocation = unsafe { ffi::mallocx(SIZE, flags) };
+	let allocation = NonNull::new(allocation.cast::<u8>()).expect("file allocation failed");
+
+	// SAFETY: the allocation is live and spans `SIZE` initialized bytes.
+	let bytes = unsafe { core::slice::from_raw_parts(allocation.as_ptr(), SIZE) };
+	assert!(bytes.iter().all(|&byte| byte == 0));
+
+	// SAFETY: the allocation is live and the flags select its original arena.
+	unsafe { ffi::dallocx(allocation.as_ptr().cast(), flags) };
+
+	// SAFETY: the only data allocation was freed without a tcache, and the
+	// static file-backed table remains valid.
+	unsafe { arena.try_destroy() }.unwrap();
+}
+
+/// Binds arenas to node 0 and confirms the kernel's policy for their pages.
+#[cfg(target_os = "linux")]
+#[test]
+fn numa_extent_hooks() {
+	/// A table bound to the node every Linux machine has.
+	static NODE_0: NumaHooks = NumaHooks::new(0);
+
+	/// One arena per node.
+	static NUMA: NumaArenas = NumaArenas::new();
+
+	/// `get_mempolicy` flag that reports the node holding a page.
+	const MPOL_F_NODE: c_long = 1;
+
+	/// `get_mempolicy` flag that queries the policy of an address.
+	const MPOL_F_ADDR: c_long = 2;
+
+	const SIZE: usize = 1024 * 1024;
+
+	let _guard = CONTROL.lock().unwrap();
+	let arena = Arena::create_on_node(&NODE_0).unwrap();
+	with_allocation(arena, SIZE, 0x3C, |allocation| {
+		let mut mode: c_int = -1;
+		let mut mask: [c_ulong; 16] = [0; 16];
+		// SAFETY: the mode and 1024-bit mask are writable, and the address is
+		// mapped.
+		let status = unsafe {
+			libc::syscall(
+				libc::SYS_get_mempolicy,
+				ptr::from_mut(&mut mode),
+				mask.as_mut_ptr(),
+				1024 as c_ulong,
+				allocation.as_ptr(),
+				MPOL_F_ADDR,
+			)
+		};
+		assert_eq!(status, 0);
+		assert_eq!(mode, libc::MPOL_BIND);
+		assert_eq!(mask[0], 1);
+
+		let mut node: c_int = -1;
+		// SAFETY: the node output is writable, and the touched address is
+		// mapped.
+		let status = unsafe {
+			libc::syscall(
+				libc::SYS_get_mempolicy,
+				ptr::from_mut(&mut node),
+				null_mut::<c_ulong>(),
+				0 as c_ulong,
+				allocation.as_ptr(),
+				MPOL_F_NODE | MPOL_F_ADDR,
+			)
+		};
+		assert_eq!(status, 0);
+		assert_eq!(node, 0);
+	});
+
+	let previous = thread::this::arena_id().unwrap();
+	let routed = NUMA.route_current_thread().unwrap();
+	assert_eq!(thread::this::arena_id().unwrap(), routed.index());
+	assert_eq!(NUMA.arena(current_node()).unwrap().index(), routed.index());
+	assert!(NUMA.arena(64).unwrap_err().is(libc::EINVAL));
+
+	// SAFETY: the previous arena is an automatic arena that is never reset or
+	// destroyed.
+	unsafe { thread::this::set_arena(previous) }.unwrap();
+}
+
+/// Confirms that secret arena pages are locked and excluded from dumps and
+/// forked children.
+#[cfg(target_os = "linux")]
+#[test]
+fn secret_extent_hooks() {
+	/// The secret table under test.
+	static SECRETS: SecretHooks = SecretHooks::new();
+
+	const SIZE: usize = 64 * 1024;
+
+	raise_memlock_limit();
+	let _guard = CONTROL.lock().unwrap();
+	let arena = Arena::create_secret(&SECRETS).unwrap();
+	with_allocation(arena, SIZE, 0xA5, |allocation| {
+		let vm_flags = mapping_flags(allocation.as_ptr().addr()).expect("allocation is mapped");
+		for flag in ["lo", "dd", "wf"] {
+			assert!(
+				vm_flags.iter().any(|set| set == flag),
+				"missing VmFlags {flag}: {vm_flags:?}"
+			);
+		}
+	});
+}
+
+/// Wipes an extent that jemalloc purges but keeps mapped for reuse.
+#[cfg(target_os = "linux")]
+#[test]
+fn secret_extent_hooks_wipe_purged_extents() {
+	/// The secret table under test.
+	static SECRETS: SecretHooks = SecretHooks::new();
+
+	const SIZE: usize = 64 * 1024;
+
+	raise_memlock_limit();
+	let _guard = CONTROL.lock().unwrap();
+	let arena = Arena::create_secret(&SECRETS).unwrap();
+	// Purge freed extents at once, and keep purged ones mapped as muzzy.
+	arena.set_dirty_decay(0).unwrap();
+	arena.set_muzzy_decay(-1).unwrap();
+	let flags = arena.flags() | ffi::MALLOCX_TCACHE_NONE;
+
+	// SAFETY: the size is nonzero, the arena is live, and the tcache is bypassed.
+	let allocation = unsafe { ffi::mallocx(SIZE, flags) };
+	let allocation = NonNull::new(allocation.cast::<u8>()).expect("secret allocation failed");
+
+	// SAFETY: the allocation is live and spans `SIZE` writable bytes.
+	unsafe { allocation.write_bytes(0xA5, SIZE) };
+
+	// SAFETY: the allocation is live and the flags select its original arena.
+	unsafe { ffi::dallocx(allocation.as_ptr().cast(), flags) };
+	arena.decay().unwrap();
+
+	// SAFETY: the purged extent stays mapped and readable while muzzy decay is
+	// disabled, and no other thread uses the arena.
+	let bytes = unsafe { core::slice::from_raw_parts(allocation.as_ptr(), SIZE) };
+	assert!(bytes.iter().all(|&byte| byte == 0), "a purged secret extent kept its contents");
+
+	// SAFETY: the only data allocation was freed without a tcache, and the
+	// static secret table remains valid.
+	unsafe { arena.try_destroy() }.unwrap();
+}
+
+/// Allocates `size` bytes from `arena` without a tcache, fills them with
+/// `fill`, and hands the allocation to `inspect` before freeing it and
+/// destroying the arena.
+///
+/// The arena's hook table must be static.
+#[cfg(target_os = "linux")]
+fn with_allocation(arena: Arena, size: usize, fill: u8, inspect: impl FnOnce(NonNull<u8>)) {
+	let flags = arena.flags() | ffi::MALLOCX_TCACHE_NONE;
+
+	// SAFETY: the size is nonzero, the arena is live, and the tcache is bypassed.
+	let allocation = unsafe { ffi::mallocx(size, flags) };
+	let allocation = NonNull::new(allocation.cast::<u8>()).expect("allocation failed");
+
+	// SAFETY: the allocation is live and spans `size` writable bytes.
+	unsafe { allocation.write_bytes(fill, size) };
+	inspect(allocation);
+
+	// SAFETY: the allocation is live and the flags select its original arena.
+	unsafe { ffi::dallocx(allocation.as_ptr().cast(), flags) };
+
+	// SAFETY: the only data allocation was freed without a tcache, and the
+	// static hook table remains valid.
+	unsafe { arena.try_destroy() }.unwrap();
+}
+
+/// Returns the NUMA node of the CPU the calling thread runs on.
+#[cfg(target_os = "linux")]
+fn current_node() -> usize {
+	let mut node: c_uint = 0;
+
+	// SAFETY: `getcpu` writes the node through the valid pointer and accepts
+	// null for the CPU and cache arguments.
+	let status = unsafe {
+		libc::syscall(
+			libc::SYS_getcpu,
+			null_mut::<c_uint>(),
+			ptr::from_mut(&mut node),
+			null_mut::<c_void>(),
+		)
+	};
+	assert_eq!(status, 0);
+
+	usize::try_from(node).unwrap()
+}
+
+/// Raises the `RLIMIT_MEMLOCK` soft limit to the hard limit, so secret arenas
+/// can lock their metadata and extents without `CAP_IPC_LOCK`.
+#[cfg(target_os = "linux")]
+fn raise_memlock_limit() {
+	let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+
+	// SAFETY: the resource is valid and the output points to a live `rlimit`.
+	assert_eq!(unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &raw mut limit) }, 0);
+	limit.rlim_cur = limit.rlim_max;
+
+	// SAFETY: the resource is valid and the soft limit does not exceed the
+	// hard limit.
+	assert_eq!(unsafe { libc::setrlimit(libc::RLIMIT_MEMLOCK, &raw const limit) }, 0);
+}
+
+/// Returns the `VmFlags` of the mapping containing `address`, read from
+/// `/proc/self/smaps`.
+#[cfg(target_os = "linux")]
+fn mapping_flags(address: usize) -> Option<Vec<String>> {
+	let smaps = std::fs::read_to_string("/proc/self/smaps").unwrap();
+	let mut inside = false;
+
+	for line in smaps.lines() {
+		if let Some(flags) = line.strip_prefix("VmFlags:") {
+			if inside {
+				return Some(
+					flags
+						.split_whitespace()
+						.map(String::from)
+						.collect(),
+				);
+			}
+		} else if let Some((start, end)) = line
+			.split_whitespace()
+			.next()
+			.and_then(|range| range.split_once('-'))
+		{
+			let start = usize::from_str_radix(start, 16);
+			let end = usize::from_str_radix(end, 16);
+			if let (Ok(start), Ok(end)) = (start, end) {
+				inside = (start..end).contains(&address);
+			}
+		}
+	}
+
+	None
+}
+
 /// Replaces an arena's data hooks through the typed setter and invokes them.
 #[test]
 fn typed_extent_hooks_at_replacement() {
@@ -0,0 +1,78 @@
+//! Exercises the global allocation budget.
+
+#![cfg(test)]
+
+use core::{
+	alloc::Layout,
+	sync::atomic::{AtomicUsize, Ordering},
+};
+use std::alloc::{alloc, dealloc, realloc};
+
+use jevmalloc::{Jemalloc, global::budget};
+
+/// Routes test-harness allocations through the budgeted allocator.
+#[global_allocator]
+static ALLOC: Jemalloc = Jemalloc;
+
+/// Usage reported by the most recent soft-limit crossing.
+static CROSSED_AT: AtomicUsize = AtomicUsize::new(0);
+
+/// Records a soft-limit crossing.
+fn record(used: usize) { CROSSED_AT.store(used, Ordering::Relaxed); }
+
+/// Counts allocations, reports the soft crossing, and enforces the hard limit.
+///
+/// The limits are process-wide, so every phase runs in this one test.
+#[test]
+fn limits_track_and_bound_global_allocations() {
+	const MIB: usize = 1 << 20;
+	let layout = Layout::from_size_align(16 * MIB, 64).unwrap();
+	let small = Layout::from_size_align(4096, 8).unwrap();
+
+	let before = budget::used();
+	let blocks: [*mut u8; 64] = core::array::from_fn(|_| {
+		// SAFETY: the layout is nonzero.
+		unsafe { alloc(small) }
+	});
+	assert!(blocks.iter().all(|block| !block.is_null()));
+	assert!(budget::used() >= before + 64 * small.size());
+
+	for block in blocks {
+		// SAFETY: every block was allocated above with `small`.
+		unsafe { dealloc(block, small) };
+	}
+
+	// SAFETY: the layout is nonzero.
+	let block = unsafe { alloc(layout) };
+	assert!(!block.is_null());
+	assert!(budget::used() >= 16 * MIB);
+
+	// SAFETY: `block` was allocated above with `layout`.
+	unsafe { dealloc(block, layout) };
+
+	let baseline = budget::used();
+	assert!(budget::set_soft_limit_handler(Some(record)).is_none());
+	budget::set_soft_limit(Some(baseline + 8 * MIB));
+	budget::set_hard_limit(Some(baseline + 32 * MIB));
+	assert_eq!(budget::soft_limit(), Some(baseline + 8 * MIB));
+	assert_eq!(budget::hard_limit(), Some(baseline + 32 * MIB));
+
+	// SAFETY: the layout is nonzero.
+	let block = unsafe { alloc(layout) };
+	assert!(!block.is_null());
+	assert!(CROSSED_AT.load(Ordering::Relaxed) >= baseline + 16 * MIB);
+
+	// SAFETY: `block` is live with `layout`, and the new size is nonzero.
+	let grown = unsafe { realloc(block, layout, 64 * MIB) };
+	assert!(grown.is_null());
+
+	// SAFETY: a failed reallocation leaves `block` live with `layout`.
+	unsafe { dealloc(block, layout) };
+	assert!(budget::used() < baseline + 8 * MIB);
+
+	budget::set_hard_limit(None);
+	budget::set_soft_limit(None);
+	assert!(budget::set_soft_limit_handler(None).is_some());
+	assert_eq!(budget::hard_limit(), None);
+	assert_eq!(budget::soft_limit(), None);
+}
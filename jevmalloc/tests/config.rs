@@ -2,7 +2,12 @@
 
 #![cfg(test)]
 
-use jevmalloc::{Jemalloc, config};
+use jevmalloc::{
+	Jemalloc, arenas,
+	config::{self, Effective},
+	ctl::Value,
+	opt,
+};
 
 /// Routes the test harness through the observed jemalloc instance.
 #[global_allocator]
@@ -22,4 +27,31 @@ fn documented_config_getters_succeed() {
 	config::stats().unwrap();
 	config::utrace().unwrap();
 	config::xmalloc().unwrap();
+	assert!(!config::version().unwrap().is_empty());
+}
+
+/// Checks that the effective report agrees with the individual getters.
+#[test]
+fn effective_report_matches_getters() {
+	let effective = Effective::collect().unwrap();
+	let page = arenas::page().unwrap();
+
+	assert_eq!(effective.get("version"), Some(Some(Value::Str(config::version().unwrap()))));
+	assert_eq!(effective.get("arenas.page"), Some(Some(Value::Size(page))));
+	assert_eq!(effective.get("opt.tcache"), Some(Some(Value::Bool(opt::tcache().unwrap()))));
+	assert_eq!(
+		effective.get("build.feature.stats"),
+		Some(Some(Value::Bool(cfg!(feature = "stats"))))
+	);
+	assert_eq!(effective.get("config.prof"), Some(Some(Value::Bool(config::prof().unwrap()))));
+	assert_eq!(effective.get("opt.no_such_option"), None);
+	assert_eq!(Effective::read("arenas.page").unwrap().unwrap(), Some(Value::Size(page)));
+
+	let text = effective.to_string();
+	assert_eq!(text.lines().count(), effective.iter().len());
+	assert!(text.contains(&format!("arenas.page={page}\n")));
+
+	if !config::prof().unwrap() {
+		assert_eq!(effective.get("opt.prof"), Some(None));
+	}
 }
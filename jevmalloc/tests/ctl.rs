@@ -5,7 +5,11 @@
 use core::alloc::{GlobalAlloc, Layout};
 use std::sync::Mutex;
 
-use jevmalloc::{Dss, Jemalloc, arenas, ctl, stats, thread};
+use jevmalloc::{
+	Dss, Jemalloc, arenas,
+	ctl::{self, ReadOnly, ReadWrite},
+	opt, stats, thread,
+};
 
 /// Jemalloc's C `bool` representation when built by cl.exe.
 #[cfg(target_env = "msvc")]
@@ -22,6 +26,14 @@ static ALLOC: Jemalloc = Jemalloc;
 /// Serializes tests that update process-wide allocator state.
 static CONTROL: Mutex<()> = Mutex::new(());
 
+jevmalloc::control! {
+	/// The startup arena count, defined outside the crate's typed getters.
+	unsafe static NARENAS: libc::c_uint, ReadOnly = "opt.narenas";
+
+	/// The dirty decay time applied to arenas created later.
+	unsafe static DIRTY_DECAY: isize, ReadWrite = "arenas.dirty_decay_ms";
+}
+
 /// Checks a basic allocation while the control-test allocator is installed.
 #[test]
 fn allocator_smoke() {
@@ -60,6 +72,129 @@ fn raw_mib_access() {
 	assert_eq!(after, cache);
 }
 
+/// Checks that descriptor-defined controls agree with the typed controls.
+#[test]
+fn descriptor_controls() {
+	let _guard = CONTROL.lock().unwrap();
+	assert_eq!(NARENAS.name(), "opt.narenas");
+	assert_eq!(NARENAS.key().unwrap(), ctl::raw::mibs("opt.narenas").unwrap());
+	assert_eq!(NARENAS.get().unwrap(), opt::narenas().unwrap());
+
+	let dirty = DIRTY_DECAY.get().unwrap();
+	assert_eq!(dirty, arenas::dirty_decay().unwrap());
+
+	let other = isize::from(dirty == 0);
+	assert_eq!(DIRTY_DECAY.xchg(other).unwrap(), dirty);
+	assert_eq!(arenas::dirty_decay().unwrap(), other);
+
+	DIRTY_DECAY.set(dirty).unwrap();
+	assert_eq!(DIRTY_DECAY.get().unwrap(), dirty);
+}
+
+/// Checks that templates fill placeholders as name translation does.
+#[test]
+fn key_templates_match_translation() {
+	let nmalloc = ctl::KeyTemplate::new("stats.arenas.<i>.bins.<j>.nmalloc").unwrap();
+	let last_bin = arenas::nbins().unwrap() - 1;
+	assert_eq!(nmalloc.arity(), 2);
+	assert_eq!(
+		nmalloc.key(&[0, last_bin]).unwrap(),
+		ctl::raw::mibs(&format!("stats.arenas.0.bins.{last_bin}.nmalloc")).unwrap()
+	);
+	assert_eq!(
+		nmalloc
+			.key(&[jevmalloc::DESTROYED_ARENAS, 0])
+			.unwrap()[2],
+		4097
+	);
+	assert!(
+		nmalloc
+			.key(&[0, last_bin + 1])
+			.unwrap_err()
+			.is(libc::EINVAL)
+	);
+	assert!(
+		nmalloc
+			.key(&[jevmalloc::ARENA_INDEX_LIMIT, 0])
+			.unwrap_err()
+			.is(libc::EINVAL)
+	);
+	assert!(nmalloc.key(&[0]).unwrap_err().is(libc::EINVAL));
+
+	let purge = ctl::KeyTemplate::new("arena.<i>.purge").unwrap();
+	assert_eq!(purge.key(&[jevmalloc::ALL_ARENAS]).unwrap()[1], 4096);
+	assert!(
+		purge
+			.key(&[jevmalloc::DESTROYED_ARENAS])
+			.unwrap_err()
+			.is(libc::EINVAL)
+	);
+
+	let lextent = ctl::KeyTemplate::new("arenas.lextent.<i>.size").unwrap();
+	let last = arenas::nlextents().unwrap() - 1;
+	// SAFETY: `arenas.lextent.<i>.size` has the C output type `size_t`.
+	let size = unsafe { ctl::raw::get::<usize>(&lextent.key(&[last]).unwrap()) }.unwrap();
+	assert_eq!(size, arenas::lextent_size(last).unwrap());
+
+	assert!(
+		ctl::KeyTemplate::new("thread.<i>.arena")
+			.unwrap_err()
+			.is(libc::EINVAL)
+	);
+}
+
+/// Checks that the walk reads schema controls with their typed values.
+#[test]
+fn walk_reads_known_controls() {
+	let mut names = Vec::new();
+	let mut bin_size = None;
+	for item in ctl::walk().unwrap() {
+		let (name, value) = item.unwrap();
+		if name.as_str() == "arenas.bin.0.size" {
+			bin_size = Some(value);
+		}
+		names.push(name.to_string());
+	}
+
+	assert_eq!(names[0], "version");
+	assert!(names.iter().any(|name| name == "opt.narenas"));
+	assert!(
+		names
+			.iter()
+			.any(|name| name == "arena.0.dirty_decay_ms")
+	);
+	assert_eq!(bin_size, Some(ctl::Value::Size(arenas::bin_size(0).unwrap())));
+	if jevmalloc::config::stats().unwrap() {
+		assert!(
+			names
+				.iter()
+				.any(|name| name == "stats.arenas.0.bins.0.nmalloc")
+		);
+	}
+}
+
+/// Checks that a failed operation names the control, index, and operation.
+#[test]
+fn errors_name_the_failing_control() {
+	let index = jevmalloc::ARENA_INDEX_LIMIT - 1;
+	let template = ctl::KeyTemplate::new("arena.<i>.dirty_decay_ms").unwrap();
+	let key = template.key(&[index]).unwrap();
+
+	// SAFETY: `arena.<i>.dirty_decay_ms` has the C output type `ssize_t`.
+	let error = unsafe { ctl::raw::get::<isize>(&key) }.unwrap_err();
+	let context = error.context().unwrap();
+
+	assert!(error.is(libc::ENOENT) || error.is(libc::EFAULT));
+	assert_eq!(context.name(), "arena.0.dirty_decay_ms");
+	assert_eq!(context.operation(), ctl::Operation::Read);
+	assert_eq!(context.index(), Some(index));
+	assert!(
+		error
+			.to_string()
+			.starts_with("arena.4094.dirty_decay_ms read: ")
+	);
+}
+
 /// Checks that a refresh advances jemalloc's allocator-maintained epoch.
 #[test]
 fn epoch_refresh_is_explicit() {
@@ -124,6 +259,29 @@ fn arena_queries_are_consistent() {
 	assert_eq!(arenas::is_affine(), mode != "disabled");
 }
 
+/// Checks that size classes are reported in increasing order.
+#[test]
+fn size_classes_are_ordered() {
+	let bins = arenas::nbins().unwrap();
+	let lextents = arenas::nlextents().unwrap();
+	assert!(bins > 0 && lextents > 0);
+
+	let largest_bin = arenas::bin_size(bins - 1).unwrap();
+	assert!(arenas::bin_size(0).unwrap() > 0);
+	assert!(arenas::bin_size(0).unwrap() < largest_bin);
+	assert!(arenas::lextent_size(0).unwrap() > largest_bin);
+	assert!(
+		arenas::bin_size(bins)
+			.unwrap_err()
+			.is(libc::EINVAL)
+	);
+	assert!(
+		arenas::lextent_size(lextents)
+			.unwrap_err()
+			.is(libc::EINVAL)
+	);
+}
+
 /// Checks both all-arena reclamation commands through the allocator-wide API.
 #[test]
 fn all_arenas_trim() { arenas::trim().unwrap(); }
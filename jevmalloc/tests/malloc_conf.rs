@@ -0,0 +1,49 @@
+//! Exercises the compile-time `malloc_conf` export.
+
+#![cfg(test)]
+
+use jevmalloc::{
+	Jemalloc,
+	opt::{self, MallocConf, Thp},
+};
+
+/// Routes the test harness through the configured jemalloc instance.
+#[global_allocator]
+static ALLOC: Jemalloc = Jemalloc;
+
+jevmalloc::malloc_conf!(
+	MallocConf::new()
+		.narenas(3)
+		.dirty_decay_ms(-1)
+		.tcache_max(4096)
+		.thp(Thp::Default)
+);
+
+/// Checks that jemalloc applied the exported options at startup.
+#[test]
+fn exported_options_take_effect() {
+	assert_eq!(opt::narenas().unwrap(), 3);
+	assert_eq!(opt::dirty_decay_ms().unwrap(), -1);
+	assert_eq!(opt::tcache_max().unwrap(), 4096);
+	assert_eq!(opt::thp().unwrap().to_bytes(), b"default");
+}
+
+/// Checks that the audit finds every exported option applied.
+#[cfg(feature = "std")]
+#[test]
+fn audit_reports_exported_options_applied() {
+	let audit = opt::audit().unwrap();
+	let linked: Vec<_> = audit
+		.findings
+		.iter()
+		.filter(|finding| finding.source == opt::Source::Linked)
+		.map(|finding| (finding.key.as_str(), finding.effective.as_deref(), finding.outcome))
+		.collect();
+
+	assert_eq!(linked, [
+		("narenas", Some("3"), opt::Outcome::Applied),
+		("dirty_decay_ms", Some("-1"), opt::Outcome::Applied),
+		("tcache_max", Some("4096"), opt::Outcome::Applied),
+		("thp", Some("default"), opt::Outcome::Applied),
+	]);
+}
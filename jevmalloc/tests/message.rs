@@ -0,0 +1,85 @@
+//! Exercises redirection of jemalloc's diagnostic output.
+
+#![cfg(test)]
+
+use core::{
+	ptr::{null, null_mut},
+	sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+use std::sync::Mutex;
+
+use jevmalloc::{Jemalloc, ffi, message};
+
+/// Routes test-harness allocations through the same jemalloc instance.
+#[global_allocator]
+static ALLOC: Jemalloc = Jemalloc;
+
+/// Serializes tests that replace the process-wide handler.
+static HANDLER: Mutex<()> = Mutex::new(());
+
+/// Bytes received by [`count`].
+static RECEIVED: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether [`count`] saw the report's opening line.
+static SAW_HEADER: AtomicBool = AtomicBool::new(false);
+
+/// Counts received bytes without allocating.
+fn count(fragment: &[u8]) {
+	RECEIVED.fetch_add(fragment.len(), Ordering::Relaxed);
+	if fragment.starts_with(b"___ Begin jemalloc statistics ___") {
+		SAW_HEADER.store(true, Ordering::Relaxed);
+	}
+}
+
+/// Prints a statistics report through `malloc_message`.
+fn print_report() {
+	// SAFETY: a null writer selects `malloc_message`, and null options select
+	// the default report.
+	unsafe { ffi::malloc_stats_print(None, null_mut(), null()) };
+}
+
+/// Routes a default-writer report through the installed handler.
+#[test]
+fn handler_receives_report_fragments() {
+	let _guard = HANDLER.lock().unwrap();
+
+	assert!(message::set_message_handler(Some(count)).is_none());
+	print_report();
+	assert!(message::set_message_handler(None).is_some());
+
+	assert!(RECEIVED.load(Ordering::Relaxed) > 0);
+	assert!(SAW_HEADER.load(Ordering::Relaxed));
+}
+
+/// Forwards fragments to the `log` crate.
+#[cfg(feature = "log")]
+#[test]
+fn log_adapter_emits_records() {
+	/// Counts records under the `jemalloc` target.
+	struct Counter;
+
+	/// Records emitted under the `jemalloc` target.
+	static RECORDS: AtomicUsize = AtomicUsize::new(0);
+
+	impl log::Log for Counter {
+		fn enabled(&self, _metadata: &log::Metadata<'_>) -> bool { true }
+
+		fn log(&self, record: &log::Record<'_>) {
+			if record.target() == "jemalloc" {
+				RECORDS.fetch_add(1, Ordering::Relaxed);
+			}
+		}
+
+		fn flush(&self) {}
+	}
+
+	let _guard = HANDLER.lock().unwrap();
+	log::set_logger(&Counter).unwrap();
+	log::set_max_level(log::LevelFilter::Info);
+
+	message::set_message_handler(Some(message::log_message));
+	print_report();
+	message::set_message_handler(None);
+
+	assert!(RECORDS.load(Ordering::Relaxed) > 0);
+}
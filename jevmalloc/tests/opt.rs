@@ -2,7 +2,9 @@
 
 #![cfg(test)]
 
-use jevmalloc::{Jemalloc, config, ctl, opt};
+use core::{ffi::CStr, fmt::Display};
+
+use jevmalloc::{Jemalloc, arenas, config, ctl, opt};
 
 /// Routes the test harness through the observed jemalloc instance.
 #[global_allocator]
@@ -76,9 +78,57 @@ fn documented_opt_getters_match_the_build() {
 	matches_availability(prof, opt::prof_final());
 	matches_availability(prof, opt::prof_leak());
 	matches_availability(prof, opt::prof_leak_error());
+	matches_availability(prof, opt::prof_stats());
 
 	succeeds(opt::zero_realloc());
 	succeeds(opt::debug_double_free_max_scan());
 	succeeds(opt::disable_large_size_classes());
 	succeeds(opt::process_madvise_max_batch());
+
+	succeeds(opt::mutex_max_spin());
+	succeeds(opt::remote_free_max());
+	succeeds(opt::tcache_gc_incr_bytes());
+	succeeds(opt::tcache_gc_delay_bytes());
+	succeeds(opt::lg_tcache_flush_small_div());
+	succeeds(opt::lg_tcache_flush_large_div());
+	matches_availability(cfg!(feature = "check_use_after_free"), opt::lg_san_uaf_align());
+	succeeds(opt::san_guard_small());
+	succeeds(opt::san_guard_large());
+	matches_availability(prof, opt::prof_sys_thread_name());
+	matches_availability(prof, opt::prof_time_resolution());
+	matches_availability(false, opt::experimental_infallible_new());
+
+	succeeds(opt::hpa());
+	succeeds(opt::hpa_slab_max_alloc());
+	succeeds(opt::hpa_hugification_threshold());
+	succeeds(opt::hpa_hugify_delay_ms());
+	succeeds(opt::hpa_min_purge_interval_ms());
+	succeeds(opt::hpa_dirty_mult());
+	succeeds(opt::hpa_sec_nshards());
+	succeeds(opt::hpa_sec_max_alloc());
+	succeeds(opt::hpa_sec_max_bytes());
+	succeeds(opt::hpa_sec_bytes_after_flush());
+	succeeds(opt::hpa_sec_batch_fill_extra());
+}
+
+/// Requires a parsed mode to display as its raw option string.
+fn displays_as<T: Display>(typed: ctl::Result<T>, raw: ctl::Result<&CStr>) {
+	assert_eq!(typed.unwrap().to_string().as_bytes(), raw.unwrap().to_bytes());
+}
+
+/// Checks that every parsed mode agrees with its raw getter.
+#[test]
+fn typed_modes_match_raw_strings() {
+	displays_as(opt::metadata_thp_mode(), opt::metadata_thp());
+	displays_as(opt::dss_mode(), opt::dss());
+	displays_as(opt::percpu_arena_mode(), opt::percpu_arena());
+	displays_as(opt::thp_mode(), opt::thp());
+	displays_as(opt::zero_realloc_mode(), opt::zero_realloc());
+	if config::fill().unwrap() {
+		displays_as(opt::junk_mode(), opt::junk());
+	}
+
+	let mode = arenas::percpu_arena().unwrap();
+	assert_eq!(mode.to_string(), arenas::percpu_mode().unwrap());
+	assert_eq!(arenas::is_percpu(), mode == opt::PercpuArena::Percpu);
 }
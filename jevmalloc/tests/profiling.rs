@@ -2,8 +2,17 @@
 
 #![cfg(test)]
 
+use core::{
+	ffi::{CStr, c_void},
+	hint::black_box,
+	ptr::NonNull,
+	sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+use std::{ffi::CString, fs, sync::Mutex};
+
 use jevmalloc::{
-	Jemalloc, ctl, is_prof_enabled, prof_enable, prof_gdump, prof_interval, prof_reset, thread,
+	Jemalloc, arenas, ctl, is_prof_enabled, prof_enable, prof_gdump, prof_interval, prof_reset,
+	profiling, thread,
 };
 
 /// Jemalloc's C `bool` representation when built by cl.exe.
@@ -30,7 +39,7 @@ const CONFIG: Option<&'static libc::c_char> = Some(
 	// pattern is valid, and the referenced NUL-terminated bytes are static.
 	unsafe {
 		ConfigPtr {
-			byte: &b"prof:true,prof_active:false,prof_gdump:false\0"[0],
+			byte: &b"prof:true,prof_active:false,prof_gdump:false,prof_stats:true\0"[0],
 		}
 		.char
 	},
@@ -48,9 +57,58 @@ pub static PREFIXED_MALLOC_CONF: Option<&'static libc::c_char> = CONFIG;
 #[global_allocator]
 static ALLOC: Jemalloc = Jemalloc;
 
+/// Serializes tests that change process-wide profiling state.
+static CONTROL: Mutex<()> = Mutex::new(());
+
+/// Number of backtraces requested from [`unwind`].
+static UNWINDS: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether [`observe_dump`] has received a notification.
+static DUMPED: AtomicBool = AtomicBool::new(false);
+
+/// Number of sampled allocations reported to [`record_sample`].
+static SAMPLES: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of sampled releases reported to [`record_sample_free`].
+static SAMPLE_FREES: AtomicUsize = AtomicUsize::new(0);
+
+/// Records one synthetic frame for each sampled allocation.
+fn unwind(frames: &mut [*mut c_void]) -> usize {
+	UNWINDS.fetch_add(1, Ordering::Relaxed);
+	let Some(frame) = frames.first_mut() else {
+		return 0;
+	};
+
+	*frame = (unwind as *const ()).cast_mut().cast::<c_void>();
+
+	1
+}
+
+/// Records that a dump notification arrived with a filename.
+fn observe_dump(filename: &CStr) {
+	if !filename.is_empty() {
+		DUMPED.store(true, Ordering::Relaxed);
+	}
+}
+
+/// Counts one sampled allocation.
+fn record_sample(sample: profiling::Sample<'_>) {
+	if sample.size > 0 {
+		SAMPLES.fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+/// Counts one sampled release.
+fn record_sample_free(_address: NonNull<u8>, size: usize) {
+	if size > 0 {
+		SAMPLE_FREES.fetch_add(1, Ordering::Relaxed);
+	}
+}
+
 /// Checks global and current-thread profiling state exchanges.
 #[test]
 fn profiling_state_round_trips() {
+	let _guard = CONTROL.lock().unwrap();
 	let global = is_prof_enabled().unwrap();
 	let thread_enabled = thread::this::is_prof_enabled().unwrap();
 	let gdump_key = ctl::raw::mibs("prof.gdump").unwrap();
@@ -69,3 +127,115 @@ fn profiling_state_round_trips() {
 /// Checks the profile reset command with its optional input omitted.
 #[test]
 fn profiling_reset_uses_the_current_sample_rate() { prof_reset().unwrap(); }
+
+/// Routes sampled allocations through a Rust unwinder, then restores the
+/// configured one.
+#[test]
+fn backtrace_hook_receives_samples() {
+	let _guard = CONTROL.lock().unwrap();
+	assert!(
+		profiling::set_backtrace_hook(Some(unwind))
+			.unwrap()
+			.is_none()
+	);
+
+	let global = prof_enable(true).unwrap();
+	let thread_enabled = thread::this::prof_enable(true).unwrap();
+
+	// The default mean sample interval is 512 KiB, so 16 MiB of traffic is
+	// sampled with overwhelming probability.
+	for _ in 0..256 {
+		drop(black_box(Vec::<u8>::with_capacity(64 * 1024)));
+	}
+
+	thread::this::prof_enable(thread_enabled).unwrap();
+	prof_enable(global).unwrap();
+
+	assert!(
+		profiling::set_backtrace_hook(None)
+			.unwrap()
+			.is_some()
+	);
+	assert!(UNWINDS.load(Ordering::Relaxed) > 0);
+}
+
+/// Observes an explicit dump through the Rust dump hook.
+#[test]
+fn dump_hook_observes_dumps() {
+	let _guard = CONTROL.lock().unwrap();
+	let path = std::env::temp_dir().join(format!("jevmalloc-{}.heap", std::process::id()));
+	let filename = CString::new(path.to_str().unwrap()).unwrap();
+	let key = ctl::raw::mibs("prof.dump").unwrap();
+
+	assert!(
+		profiling::set_dump_hook(Some(observe_dump))
+			.unwrap()
+			.is_none()
+	);
+
+	// SAFETY: `prof.dump` accepts an optional `const char *` filename, which
+	// jemalloc reads only during this call.
+	unsafe { ctl::raw::set(&key, &filename.as_ptr()) }.unwrap();
+
+	assert!(profiling::set_dump_hook(None).unwrap().is_some());
+	assert!(DUMPED.load(Ordering::Relaxed));
+	fs::remove_file(path).unwrap();
+}
+
+/// Reports sampled allocations and their release while the guard is live.
+#[test]
+fn sample_hooks_observe_allocations_and_frees() {
+	let _guard = CONTROL.lock().unwrap();
+	let hooks = profiling::SampleHooks::install(record_sample, Some(record_sample_free)).unwrap();
+
+	let busy = profiling::SampleHooks::install(record_sample, None).unwrap_err();
+	assert_eq!(busy.code(), libc::EBUSY);
+
+	let global = prof_enable(true).unwrap();
+	let thread_enabled = thread::this::prof_enable(true).unwrap();
+
+	for _ in 0..256 {
+		drop(black_box(Vec::<u8>::with_capacity(64 * 1024)));
+	}
+
+	thread::this::prof_enable(thread_enabled).unwrap();
+	prof_enable(global).unwrap();
+	drop(hooks);
+
+	assert!(SAMPLES.load(Ordering::Relaxed) > 0);
+	assert!(SAMPLE_FREES.load(Ordering::Relaxed) > 0);
+	drop(profiling::SampleHooks::install(record_sample, None).unwrap());
+}
+
+/// Reads sampled totals for every size class after sampled traffic.
+#[test]
+fn class_stats_cover_every_size_class() {
+	let _guard = CONTROL.lock().unwrap();
+	assert!(jevmalloc::opt::prof_stats().unwrap());
+
+	let global = prof_enable(true).unwrap();
+	let thread_enabled = thread::this::prof_enable(true).unwrap();
+
+	for _ in 0..256 {
+		drop(black_box(Vec::<u8>::with_capacity(64 * 1024)));
+	}
+
+	thread::this::prof_enable(thread_enabled).unwrap();
+	prof_enable(global).unwrap();
+
+	let classes = profiling::class_stats().unwrap();
+	let expected = arenas::nbins().unwrap() + arenas::nlextents().unwrap();
+	assert_eq!(classes.len(), expected);
+
+	let mut previous = 0;
+	let mut sampled = 0;
+	for class in classes {
+		let class = class.unwrap();
+		assert!(class.size > previous);
+		assert!(class.live.count <= class.accum.count);
+		previous = class.size;
+		sampled += class.accum.count;
+	}
+
+	assert!(sampled > 0);
+}
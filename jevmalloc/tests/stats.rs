@@ -65,6 +65,91 @@ fn thread_counters_remain_distinct_and_monotonic() {
 	);
 }
 
+/// Measures nested scopes and folds the inner peak into the outer scope.
+#[test]
+fn alloc_scopes_nest() {
+	let outer = thread::AllocScope::enter().unwrap();
+	let kept = vec![0_u8; 1 << 20];
+
+	let (len, inner) = outer
+		.measure(|| {
+			let temporary = vec![0_u8; 4 << 20];
+			std::hint::black_box(&temporary).len()
+		})
+		.unwrap();
+	assert_eq!(len, 4 << 20);
+	assert!(inner.allocated >= 4 << 20);
+	assert!(inner.deallocated >= 4 << 20);
+	assert!(inner.peak >= 4 << 20);
+	assert!(inner.net < 1 << 20);
+
+	let usage = outer.usage().unwrap();
+	assert!(usage.allocated >= 5 << 20);
+	assert!(usage.net >= 1 << 20);
+	assert!(usage.peak >= 5 << 20);
+	drop(kept);
+
+	let ((), usage) = thread::measure(|| drop(vec![0_u8; 64])).unwrap();
+	assert!(usage.allocated >= 64);
+}
+
+/// Charges polls on two threads to one task record.
+#[test]
+fn accounted_futures_follow_tasks_across_threads() {
+	use core::{
+		future::Future,
+		pin::Pin,
+		task::{Context, Poll, Waker},
+	};
+	use std::sync::Arc;
+
+	/// Returns `Pending` once so the task can move between polls.
+	struct YieldOnce(bool);
+
+	impl Future for YieldOnce {
+		type Output = ();
+
+		fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+			if self.0 {
+				return Poll::Ready(());
+			}
+
+			self.0 = true;
+			Poll::Pending
+		}
+	}
+
+	let record = Arc::new(thread::TaskRecord::new());
+	let mut task = Box::pin(thread::Accounted::new(
+		async {
+			let held = vec![0_u8; 2 << 20];
+			YieldOnce(false).await;
+			drop(held);
+			vec![0_u8; 1 << 20].len()
+		},
+		Arc::clone(&record),
+	));
+
+	let mut cx = Context::from_waker(Waker::noop());
+	assert!(task.as_mut().poll(&mut cx).is_pending());
+	assert!(record.usage().net >= 2 << 20);
+
+	let output = std::thread::spawn(move || {
+		let mut cx = Context::from_waker(Waker::noop());
+		task.as_mut().poll(&mut cx)
+	})
+	.join()
+	.unwrap();
+	assert_eq!(output, Poll::Ready(1 << 20));
+
+	let usage = record.usage();
+	assert_eq!(usage.polls, 2);
+	assert_eq!(usage.untracked_polls, 0);
+	assert!(usage.allocated >= 3 << 20);
+	assert!(usage.deallocated >= 3 << 20);
+	assert!(usage.peak >= 2 << 20);
+}
+
 /// Checks the peak and mutex-statistics command controls.
 #[test]
 fn reset_commands_succeed() {
@@ -72,3 +157,112 @@ fn reset_commands_succeed() {
 	let _peak = thread::this::peak().unwrap();
 	stats_reset().unwrap();
 }
+
+/// Decodes the JSON report and checks it against the fixed-name getters.
+#[cfg(feature = "alloc")]
+#[test]
+fn report_decodes_every_section() {
+	let report = stats::report().unwrap();
+
+	assert!(!report.version.is_empty());
+	assert!(report.general.active >= report.general.allocated);
+	assert!(report.general.resident >= report.general.active);
+	assert!(!report.mutexes.is_empty());
+
+	let arena = report
+		.arenas
+		.iter()
+		.find(|arena| arena.id == stats::ArenaId::Index(0))
+		.unwrap();
+
+	assert_eq!(arena.bins.len(), jevmalloc::arenas::nbins().unwrap());
+	assert_eq!(arena.lextents.len(), jevmalloc::arenas::nlextents().unwrap());
+	assert!(!arena.extents.is_empty());
+	assert!(arena.bins.iter().all(|bin| bin.size > 0));
+	assert!(arena.small.nrequests > 0);
+}
+
+/// Captures two refreshed snapshots and compares them.
+#[test]
+fn snapshots_advance_with_each_refresh() {
+	let earlier = stats::Snapshot::capture().unwrap();
+	let later = stats::Snapshot::capture().unwrap();
+
+	assert!(earlier.active >= earlier.allocated);
+	assert!(earlier.resident >= earlier.active);
+
+	let delta = later.delta(&earlier);
+	assert!(delta.epochs >= 1);
+	assert!(later.uptime >= earlier.uptime);
+	assert_eq!(delta.elapsed, later.uptime.saturating_sub(earlier.uptime));
+}
+
+/// Renders the `OpenMetrics` exposition with escaped arena-name labels.
+#[test]
+fn openmetrics_exposition_is_complete() {
+	let arena = jevmalloc::Arena::create().unwrap();
+	arena.set_name(c"quote\"d").unwrap();
+
+	let mut buf = vec![0_u8; 4 << 20];
+	let text = stats::OpenMetrics::new()
+		.with_size_classes(true)
+		.write_to_slice(&mut buf)
+		.unwrap();
+
+	assert!(text.starts_with("# TYPE jemalloc_allocated_bytes gauge\n"));
+	assert!(text.ends_with("# EOF\n"));
+	assert!(text.contains("\njemalloc_background_thread_runs_total "));
+	assert!(text.contains("\njemalloc_arena_threads{arena=\"0\",name=\""));
+	assert!(text.contains(&format!(
+		"\njemalloc_arena_active_pages{{arena=\"{}\",name=\"quote\\\"d\"}} ",
+		arena.index()
+	)));
+	assert!(text.contains("\njemalloc_bin_regions{arena=\"0\",name=\""));
+
+	let compact = stats::OpenMetrics::new()
+		.write_to_slice(&mut buf)
+		.unwrap();
+	assert!(!compact.contains("jemalloc_bin_"));
+
+	let error = stats::OpenMetrics::new()
+		.write_to_slice(&mut [0; 64])
+		.unwrap_err();
+	assert!(error.is(libc::ENOSPC));
+}
+
+/// Records samples on the background thread and summarizes them.
+#[cfg(feature = "std")]
+#[test]
+fn sampler_records_a_time_series() {
+	use std::time::Duration;
+
+	let sampler = stats::Sampler::spawn(Duration::from_millis(5), 4).unwrap();
+	while sampler.samples().len() < 4 {
+		std::thread::sleep(Duration::from_millis(5));
+	}
+
+	let samples = sampler.samples();
+	assert_eq!(samples.len(), 4);
+	assert!(
+		samples
+			.windows(2)
+			.all(|pair| pair[0].snapshot.epoch < pair[1].snapshot.epoch)
+	);
+	assert!(
+		samples[3]
+			.arenas
+			.iter()
+			.any(|arena| arena.index == 0)
+	);
+
+	let window = sampler.last_minute().unwrap();
+	assert_eq!(window.samples, 4);
+	assert!(window.allocated.min <= window.allocated.max);
+	assert!(window.delta.epochs >= 3);
+
+	assert!(
+		stats::Sampler::spawn(Duration::ZERO, 1)
+			.unwrap_err()
+			.is(libc::EINVAL)
+	);
+}
@@ -102,6 +102,22 @@ fn explicit_cache_lifecycle() {
 	cache.try_destroy().unwrap();
 }
 
+/// Reports scopes as unavailable when statistics are compiled out.
+#[cfg(not(feature = "stats"))]
+#[test]
+fn alloc_scope_requires_statistics() {
+	assert!(
+		thread::AllocScope::enter()
+			.unwrap_err()
+			.is(libc::ENOENT)
+	);
+	assert!(
+		thread::measure(|| ())
+			.unwrap_err()
+			.is(libc::ENOENT)
+	);
+}
+
 /// Routes one allocation and deallocation through an explicit cache.
 fn allocate_and_deallocate(cache: &ThreadCache) {
 	let flags = cache.flags();